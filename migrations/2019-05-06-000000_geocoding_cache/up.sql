@@ -0,0 +1,8 @@
+-- Addresses that have already been resolved
+-- lat/lng are NULL if an address could not be resolved
+CREATE TABLE geocoding_cache (
+    address TEXT PRIMARY KEY NOT NULL,
+    created INTEGER NOT NULL,
+    lat DOUBLE,
+    lng DOUBLE
+);
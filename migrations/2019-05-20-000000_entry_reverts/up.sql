@@ -0,0 +1,12 @@
+-- Entry versions that have been created by reverting
+-- to an earlier version of the same entry
+CREATE TABLE entry_reverts (
+    entry_id         TEXT NOT NULL,
+    entry_version    INTEGER NOT NULL,
+    reverted_version INTEGER NOT NULL,
+    reverted_by      TEXT NOT NULL,
+    created          INTEGER NOT NULL,
+    PRIMARY KEY (entry_id, entry_version),
+    FOREIGN KEY (entry_id, entry_version) REFERENCES entries(id,version),
+    FOREIGN KEY (reverted_by) REFERENCES users(username)
+);
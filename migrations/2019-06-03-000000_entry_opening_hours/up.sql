@@ -0,0 +1,2 @@
+-- Opening hours in the syntax of OpenStreetMap
+ALTER TABLE entries ADD COLUMN opening_hours TEXT DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+-- Removing columns from a table is not supported by SQLite
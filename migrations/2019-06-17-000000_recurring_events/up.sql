@@ -0,0 +1,3 @@
+-- Recurrence rules (RRULE) and excluded dates (EXDATE) of RFC 5545
+ALTER TABLE events ADD COLUMN rrule TEXT DEFAULT NULL;
+ALTER TABLE events ADD COLUMN exdates TEXT DEFAULT NULL;
@@ -18,6 +18,7 @@ pub struct CsvRecord {
     pub categories: String,
     pub tags: String,
     pub license: Option<String>,
+    pub opening_hours: Option<String>,
     pub avg_rating: f64,
 }
 
@@ -35,6 +36,7 @@ impl From<(Entry, Vec<Category>, AvgRatingValue)> for CsvRecord {
             location,
             homepage,
             license,
+            opening_hours,
             ..
         } = e.clone();
 
@@ -72,6 +74,7 @@ impl From<(Entry, Vec<Category>, AvgRatingValue)> for CsvRecord {
             license,
             categories,
             tags: e.tags.join(","),
+            opening_hours: opening_hours.map(|oh| oh.to_string()),
             avg_rating: avg_rating.into(),
         }
     }
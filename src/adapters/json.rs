@@ -1,4 +1,8 @@
-use crate::core::{db::IndexedEntry, entities as e, util::geo::MapPoint};
+use crate::core::{
+    db::{EntryCluster, EntryFacets, IndexedEntry},
+    entities as e, usecases,
+    util::geo::{MapMultiPolygon, MapPoint, MapPolygon},
+};
 
 #[rustfmt::skip]
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -23,6 +27,7 @@ pub struct Entry {
     pub license        : Option<String>,
     pub image_url      : Option<String>,
     pub image_link_url : Option<String>,
+    pub opening_hours  : Option<String>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -57,6 +62,10 @@ pub struct Event {
     pub registration: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub organizer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rrule: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exdates: Vec<i64>,
 }
 
 impl From<e::Event> for Event {
@@ -73,6 +82,8 @@ impl From<e::Event> for Event {
             homepage,
             registration,
             organizer,
+            rrule,
+            exdates,
             ..
         } = e;
 
@@ -108,6 +119,8 @@ impl From<e::Event> for Event {
 
         let start = start.timestamp();
         let end = end.map(|end| end.timestamp());
+        let rrule = rrule.map(|r| r.to_string());
+        let exdates = exdates.into_iter().map(|x| x.timestamp()).collect();
 
         Event {
             id,
@@ -128,6 +141,8 @@ impl From<e::Event> for Event {
             tags,
             registration,
             organizer,
+            rrule,
+            exdates,
         }
     }
 }
@@ -180,19 +195,42 @@ pub struct EntrySearchResult {
     pub lng: f64,
     pub title: String,
     pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub street: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
     pub categories: Vec<String>,
     pub tags: Vec<String>,
     pub ratings: EntrySearchRatings,
+    /// The distance in meters (if requested)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance: Option<f64>,
+    /// HTML snippets with all matched terms
+    /// enclosed in `<b>` tags (if requested)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_snippet: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_snippet: Option<String>,
 }
 
 impl From<IndexedEntry> for EntrySearchResult {
     fn from(from: IndexedEntry) -> Self {
+        let snippets = from.snippets.unwrap_or_default();
+        let address = from.address.unwrap_or_default();
         Self {
             id: from.id,
             lat: from.pos.lat().to_deg(),
             lng: from.pos.lng().to_deg(),
             title: from.title,
             description: from.description,
+            street: address.street,
+            zip: address.zip,
+            city: address.city,
+            country: address.country,
             categories: from.categories,
             tags: from.tags,
             ratings: EntrySearchRatings {
@@ -204,6 +242,9 @@ impl From<IndexedEntry> for EntrySearchResult {
                 solidarity: from.ratings.solidarity,
                 transparency: from.ratings.transparency,
             },
+            distance: from.distance.map(|d| d.to_meters()),
+            title_snippet: snippets.title,
+            description_snippet: snippets.description,
         }
     }
 }
@@ -212,6 +253,138 @@ impl From<IndexedEntry> for EntrySearchResult {
 pub struct SearchResponse {
     pub visible: Vec<EntrySearchResult>,
     pub invisible: Vec<EntrySearchResult>,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<SearchFacets>,
+}
+
+#[derive(Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+impl From<(String, usize)> for FacetCount {
+    fn from((value, count): (String, usize)) -> Self {
+        Self { value, count }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SearchFacets {
+    pub categories: Vec<FacetCount>,
+    pub tags: Vec<FacetCount>,
+}
+
+impl From<EntryFacets> for SearchFacets {
+    fn from(from: EntryFacets) -> Self {
+        Self {
+            categories: from.categories.into_iter().map(Into::into).collect(),
+            tags: from.tags.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SearchCluster {
+    /// The centroid of all entries in the cluster
+    pub lat: f64,
+    pub lng: f64,
+    pub count: usize,
+    /// The entry with the highest total rating
+    pub top_entry: EntrySearchResult,
+}
+
+impl From<EntryCluster> for SearchCluster {
+    fn from(from: EntryCluster) -> Self {
+        Self {
+            lat: from.center.lat().to_deg(),
+            lng: from.center.lng().to_deg(),
+            count: from.count,
+            top_entry: from.top_entry.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct EntryFieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl From<usecases::EntryFieldChange> for EntryFieldChange {
+    fn from(from: usecases::EntryFieldChange) -> Self {
+        Self {
+            field: from.field.into(),
+            old: from.old,
+            new: from.new,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct EntryHistoryItem {
+    pub version: u64,
+    pub created: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<i64>,
+    /// The earlier version that has been restored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverted_version: Option<u64>,
+    /// The username of the scout who restored it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverted_by: Option<String>,
+    /// The changes since the previous version
+    pub changes: Vec<EntryFieldChange>,
+}
+
+impl From<usecases::EntryHistoryItem> for EntryHistoryItem {
+    fn from(from: usecases::EntryHistoryItem) -> Self {
+        let (reverted_version, reverted_by) = match from.revert {
+            Some(revert) => (Some(revert.reverted_version), Some(revert.reverted_by)),
+            None => (None, None),
+        };
+        Self {
+            version: from.version,
+            created: from.created.into(),
+            archived: from.archived.map(Into::into),
+            reverted_version,
+            reverted_by,
+            changes: from.changes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Address {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub street: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+}
+
+impl From<e::Address> for Address {
+    fn from(from: e::Address) -> Self {
+        let e::Address {
+            street,
+            zip,
+            city,
+            country,
+        } = from;
+        Self {
+            street,
+            zip,
+            city,
+            country,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -229,6 +402,72 @@ pub struct BboxSubscription {
     pub north_east_lng: f64,
 }
 
+#[derive(Serialize)]
+pub struct RebuildIndexStatus {
+    pub in_progress: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished: Option<i64>,
+    pub indexed_count: usize,
+    pub total_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A GeoJSON geometry that describes an area, i.e.
+/// either a `Polygon` or a `MultiPolygon`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum GeoJsonArea {
+    Polygon {
+        coordinates: Vec<Vec<Vec<f64>>>,
+    },
+    MultiPolygon {
+        coordinates: Vec<Vec<Vec<Vec<f64>>>>,
+    },
+}
+
+// JSON -> Entity
+
+impl GeoJsonArea {
+    /// Returns `None` if the geometry contains invalid
+    /// positions or degenerated rings.
+    pub fn try_into_map_area(self) -> Option<MapMultiPolygon> {
+        // GeoJSON positions are ordered as [longitude, latitude, ...]
+        fn ring(positions: Vec<Vec<f64>>) -> Option<Vec<MapPoint>> {
+            positions
+                .into_iter()
+                .map(|pos| {
+                    if pos.len() < 2 {
+                        return None;
+                    }
+                    MapPoint::try_from_lat_lng_deg(pos[1], pos[0])
+                })
+                .collect()
+        }
+        fn polygon(rings: Vec<Vec<Vec<f64>>>) -> Option<MapPolygon> {
+            let mut rings = rings.into_iter();
+            let exterior = ring(rings.next()?)?;
+            let holes = rings.map(ring).collect::<Option<_>>()?;
+            Some(MapPolygon::new(exterior, holes))
+        }
+        let polygons = match self {
+            GeoJsonArea::Polygon { coordinates } => vec![polygon(coordinates)?],
+            GeoJsonArea::MultiPolygon { coordinates } => coordinates
+                .into_iter()
+                .map(polygon)
+                .collect::<Option<_>>()?,
+        };
+        let area = MapMultiPolygon::new(polygons);
+        if area.is_valid() {
+            Some(area)
+        } else {
+            None
+        }
+    }
+}
+
 // Entity -> JSON
 
 impl Entry {
@@ -246,6 +485,7 @@ impl Entry {
             license,
             image_url,
             image_link_url,
+            opening_hours,
             ..
         } = e;
         let e::Location { pos, address } = location;
@@ -281,6 +521,7 @@ impl Entry {
             license,
             image_url,
             image_link_url,
+            opening_hours: opening_hours.map(|oh| oh.to_string()),
         }
     }
 }
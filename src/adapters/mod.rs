@@ -1,3 +1,4 @@
 pub mod csv;
 pub mod json;
+pub mod mvt;
 pub mod user_communication;
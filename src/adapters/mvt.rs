@@ -0,0 +1,289 @@
+//! Encoding of entries as Mapbox Vector Tiles (MVT).
+//!
+//! Reference: https://github.com/mapbox/vector-tile-spec/tree/master/2.1
+
+use crate::core::{
+    db::IndexedEntry,
+    util::geo::{MapBbox, MapPoint},
+};
+
+use std::{collections::HashMap, f64::consts::PI};
+
+pub const MAX_TILE_ZOOM: u8 = 22;
+
+/// The name of the single layer in each tile.
+pub const ENTRIES_LAYER_NAME: &str = "entries";
+
+/// The resolution of a tile in both directions.
+const TILE_EXTENT: u32 = 4096;
+
+/// Entries slightly outside of the tile are included to
+/// avoid clipping of symbols at the tile boundaries.
+const TILE_BUFFER: u32 = 64;
+
+/// The latitude limit of the Web Mercator projection.
+const MAX_MERCATOR_LAT_DEG: f64 = 85.051_128_779_806_59;
+
+const MVT_VERSION: u32 = 2;
+
+/// The address of a single tile in the XYZ tiling scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileCoord {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+fn tile_x_to_lng_deg(x: f64, tile_count: f64) -> f64 {
+    (x / tile_count * 360.0 - 180.0).max(-180.0).min(180.0)
+}
+
+fn tile_y_to_lat_deg(y: f64, tile_count: f64) -> f64 {
+    (PI * (1.0 - 2.0 * y / tile_count))
+        .sinh()
+        .atan()
+        .to_degrees()
+        .max(-90.0)
+        .min(90.0)
+}
+
+fn lng_deg_to_tile_x(lng_deg: f64, tile_count: f64) -> f64 {
+    (lng_deg + 180.0) / 360.0 * tile_count
+}
+
+fn lat_deg_to_tile_y(lat_deg: f64, tile_count: f64) -> f64 {
+    let lat = lat_deg
+        .max(-MAX_MERCATOR_LAT_DEG)
+        .min(MAX_MERCATOR_LAT_DEG)
+        .to_radians();
+    (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * tile_count
+}
+
+impl TileCoord {
+    pub fn is_valid(&self) -> bool {
+        self.z <= MAX_TILE_ZOOM && self.x < self.tile_count() && self.y < self.tile_count()
+    }
+
+    /// The number of tiles in each direction at this zoom level.
+    fn tile_count(&self) -> u32 {
+        1 << u32::from(self.z.min(MAX_TILE_ZOOM))
+    }
+
+    /// The bounding box of the tile including the buffer.
+    pub fn bbox(&self) -> MapBbox {
+        debug_assert!(self.is_valid());
+        let tile_count = f64::from(self.tile_count());
+        let buffer = f64::from(TILE_BUFFER) / f64::from(TILE_EXTENT);
+        let west = tile_x_to_lng_deg(f64::from(self.x) - buffer, tile_count);
+        let east = tile_x_to_lng_deg(f64::from(self.x) + 1.0 + buffer, tile_count);
+        let north = tile_y_to_lat_deg(f64::from(self.y) - buffer, tile_count);
+        let south = tile_y_to_lat_deg(f64::from(self.y) + 1.0 + buffer, tile_count);
+        MapBbox::new(
+            MapPoint::from_lat_lng_deg(south, west),
+            MapPoint::from_lat_lng_deg(north, east),
+        )
+    }
+
+    /// Projects the point into the coordinate space of the tile,
+    /// i.e. (0, 0) is the upper left and (extent, extent) the
+    /// lower right corner.
+    fn project(&self, pos: MapPoint) -> (i32, i32) {
+        let tile_count = f64::from(self.tile_count());
+        let extent = f64::from(TILE_EXTENT);
+        let x = (lng_deg_to_tile_x(pos.lng().to_deg(), tile_count) - f64::from(self.x)) * extent;
+        let y = (lat_deg_to_tile_y(pos.lat().to_deg(), tile_count) - f64::from(self.y)) * extent;
+        (x.round() as i32, y.round() as i32)
+    }
+}
+
+/// A minimal writer for the subset of the Protocol
+/// Buffers encoding that is needed for vector tiles.
+#[derive(Debug, Default)]
+struct ProtobufWriter {
+    buf: Vec<u8>,
+}
+
+const WIRE_TYPE_VARINT: u32 = 0;
+const WIRE_TYPE_64BIT: u32 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u32 = 2;
+
+fn zigzag(val: i32) -> u32 {
+    ((val << 1) ^ (val >> 31)) as u32
+}
+
+impl ProtobufWriter {
+    fn write_varint(&mut self, mut val: u64) {
+        while val >= 0x80 {
+            self.buf.push((val as u8) | 0x80);
+            val >>= 7;
+        }
+        self.buf.push(val as u8);
+    }
+
+    fn write_key(&mut self, field: u32, wire_type: u32) {
+        self.write_varint(u64::from((field << 3) | wire_type));
+    }
+
+    fn write_uint(&mut self, field: u32, val: u32) {
+        self.write_key(field, WIRE_TYPE_VARINT);
+        self.write_varint(u64::from(val));
+    }
+
+    fn write_double(&mut self, field: u32, val: f64) {
+        self.write_key(field, WIRE_TYPE_64BIT);
+        self.buf.extend_from_slice(&val.to_bits().to_le_bytes());
+    }
+
+    fn write_bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.write_key(field, WIRE_TYPE_LENGTH_DELIMITED);
+        self.write_varint(bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn write_string(&mut self, field: u32, val: &str) {
+        self.write_bytes(field, val.as_bytes());
+    }
+
+    fn write_packed_uints(&mut self, field: u32, vals: &[u32]) {
+        let mut packed = ProtobufWriter::default();
+        for val in vals {
+            packed.write_varint(u64::from(*val));
+        }
+        self.write_bytes(field, &packed.buf);
+    }
+}
+
+// Field numbers of the messages in vector_tile.proto
+const TILE_LAYERS: u32 = 3;
+const LAYER_NAME: u32 = 1;
+const LAYER_FEATURES: u32 = 2;
+const LAYER_KEYS: u32 = 3;
+const LAYER_VALUES: u32 = 4;
+const LAYER_EXTENT: u32 = 5;
+const LAYER_VERSION: u32 = 15;
+const FEATURE_TAGS: u32 = 2;
+const FEATURE_TYPE: u32 = 3;
+const FEATURE_GEOMETRY: u32 = 4;
+const VALUE_STRING: u32 = 1;
+const VALUE_DOUBLE: u32 = 3;
+
+const GEOM_TYPE_POINT: u32 = 1;
+const COMMAND_MOVE_TO: u32 = 1;
+
+/// The property keys of all features in the same
+/// order as their values are added.
+const PROPERTY_KEYS: [&str; 4] = ["id", "title", "categories", "rating"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PropertyValue {
+    String(String),
+    /// The bits of a double value
+    Double(u64),
+}
+
+/// Collects the unique property values of all features.
+#[derive(Debug, Default)]
+struct PropertyValues {
+    indexes: HashMap<PropertyValue, u32>,
+    values: Vec<PropertyValue>,
+}
+
+impl PropertyValues {
+    fn index_of(&mut self, value: PropertyValue) -> u32 {
+        if let Some(index) = self.indexes.get(&value) {
+            return *index;
+        }
+        let index = self.values.len() as u32;
+        self.indexes.insert(value.clone(), index);
+        self.values.push(value);
+        index
+    }
+}
+
+/// Encodes all entries as point features of a single layer.
+/// Entries without a valid position are skipped.
+pub fn encode_entries(tile: TileCoord, entries: &[IndexedEntry]) -> Vec<u8> {
+    debug_assert!(tile.is_valid());
+    let mut layer = ProtobufWriter::default();
+    layer.write_uint(LAYER_VERSION, MVT_VERSION);
+    layer.write_string(LAYER_NAME, ENTRIES_LAYER_NAME);
+    let mut values = PropertyValues::default();
+    for entry in entries.iter().filter(|e| e.pos.is_valid()) {
+        let properties = [
+            PropertyValue::String(entry.id.clone()),
+            PropertyValue::String(entry.title.clone()),
+            PropertyValue::String(entry.categories.join(",")),
+            PropertyValue::Double(f64::from(entry.ratings.total()).to_bits()),
+        ];
+        let tags: Vec<_> = properties
+            .iter()
+            .cloned()
+            .enumerate()
+            .flat_map(|(key_index, value)| vec![key_index as u32, values.index_of(value)])
+            .collect();
+        let (x, y) = tile.project(entry.pos);
+        let geometry = [(COMMAND_MOVE_TO & 0x7) | (1 << 3), zigzag(x), zigzag(y)];
+        let mut feature = ProtobufWriter::default();
+        feature.write_packed_uints(FEATURE_TAGS, &tags);
+        feature.write_uint(FEATURE_TYPE, GEOM_TYPE_POINT);
+        feature.write_packed_uints(FEATURE_GEOMETRY, &geometry);
+        layer.write_bytes(LAYER_FEATURES, &feature.buf);
+    }
+    for key in PROPERTY_KEYS.iter() {
+        layer.write_string(LAYER_KEYS, key);
+    }
+    for value in values.values {
+        let mut encoded_value = ProtobufWriter::default();
+        match value {
+            PropertyValue::String(s) => encoded_value.write_string(VALUE_STRING, &s),
+            PropertyValue::Double(bits) => {
+                encoded_value.write_double(VALUE_DOUBLE, f64::from_bits(bits))
+            }
+        }
+        layer.write_bytes(LAYER_VALUES, &encoded_value.buf);
+    }
+    layer.write_uint(LAYER_EXTENT, TILE_EXTENT);
+    let mut tile = ProtobufWriter::default();
+    tile.write_bytes(TILE_LAYERS, &layer.buf);
+    tile.buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_varints() {
+        let mut writer = ProtobufWriter::default();
+        writer.write_varint(1);
+        writer.write_varint(300);
+        assert_eq!(vec![0x01, 0xAC, 0x02], writer.buf);
+        assert_eq!(0, zigzag(0));
+        assert_eq!(1, zigzag(-1));
+        assert_eq!(2, zigzag(1));
+        assert_eq!(4095, zigzag(-2048));
+    }
+
+    #[test]
+    fn validate_tile_coords() {
+        assert!(TileCoord { z: 0, x: 0, y: 0 }.is_valid());
+        assert!(!TileCoord { z: 0, x: 1, y: 0 }.is_valid());
+        assert!(TileCoord { z: 2, x: 3, y: 3 }.is_valid());
+        assert!(!TileCoord { z: 2, x: 3, y: 4 }.is_valid());
+        assert!(!TileCoord { z: 23, x: 0, y: 0 }.is_valid());
+    }
+
+    #[test]
+    fn project_into_tile() {
+        let tile = TileCoord { z: 1, x: 1, y: 0 };
+        let bbox = tile.bbox();
+        assert!(bbox.contains_point(MapPoint::from_lat_lng_deg(45.0, 90.0)));
+        assert!(!bbox.contains_point(MapPoint::from_lat_lng_deg(-45.0, 90.0)));
+        assert!(!bbox.contains_point(MapPoint::from_lat_lng_deg(45.0, -90.0)));
+        assert_eq!((0, 4096), tile.project(MapPoint::from_lat_lng_deg(0.0, 0.0)));
+        assert_eq!(
+            (2048, 0),
+            tile.project(MapPoint::from_lat_lng_deg(MAX_MERCATOR_LAT_DEG, 90.0))
+        );
+    }
+}
@@ -3,11 +3,12 @@ use super::{
     error::RepoError,
     repositories::*,
     util::{
-        geo::{MapBbox, MapPoint},
+        geo::{Distance, MapBbox, MapMultiPolygon, MapPoint},
         time::Timestamp,
     },
 };
 
+use chrono::NaiveDateTime;
 use failure::Fallible;
 
 type Result<T> = std::result::Result<T, RepoError>;
@@ -16,18 +17,41 @@ pub trait EntryGateway {
     fn get_entry(&self, _: &str) -> Result<Entry>;
     fn get_entries(&self, ids: &[&str]) -> Result<Vec<Entry>>;
 
+    /// All versions of an entry ordered by version, including
+    /// archived versions. Fails with `RepoError::NotFound` if
+    /// the entry does not exist.
+    fn get_entry_history(&self, id: &str) -> Result<Vec<Entry>>;
+
     fn all_entries(&self) -> Result<Vec<Entry>>;
+
+    /// The state of all entries at the given time, i.e. the latest
+    /// version of each entry that had been created before and was
+    /// not archived yet. The results are ordered by id.
+    fn all_entries_as_of(&self, as_of: Timestamp) -> Result<Vec<Entry>>;
     fn count_entries(&self) -> Result<usize>;
 
+    /// Loads the next chunk of all current entries ordered by id,
+    /// i.e. at most `limit` entries with an id greater than `after_id`.
+    fn all_entries_chunk(&self, after_id: Option<&str>, limit: usize) -> Result<Vec<Entry>>;
+
+    /// Ids of all entries that have been created, updated,
+    /// or archived since the given time.
+    fn changed_entry_ids_since(&self, since: Timestamp) -> Result<Vec<String>>;
+
     fn create_entry(&self, _: Entry) -> Result<()>;
     fn update_entry(&self, _: &Entry) -> Result<()>;
     fn import_multiple_entries(&mut self, _: &[Entry]) -> Result<()>;
     fn archive_entries(&self, ids: &[&str], archived: Timestamp) -> Result<usize>;
+
+    fn create_entry_revert(&self, revert: &EntryRevert) -> Result<()>;
+    /// All reverts of an entry ordered by version.
+    fn get_entry_reverts(&self, entry_id: &str) -> Result<Vec<EntryRevert>>;
 }
 
 pub trait EventGateway {
     fn create_event(&self, _: Event) -> Result<()>;
     fn get_event(&self, _: &str) -> Result<Event>;
+    fn get_events(&self, ids: &[&str]) -> Result<Vec<Event>>;
     fn all_events(&self) -> Result<Vec<Event>>;
     fn update_event(&self, _: &Event) -> Result<()>;
     fn archive_events(&self, ids: &[&str], archived: Timestamp) -> Result<usize>;
@@ -82,28 +106,228 @@ pub struct IndexedEntry {
     pub pos: MapPoint,
     pub title: String,
     pub description: String,
+    /// `None` if the entry has no address
+    pub address: Option<Address>,
     pub categories: Vec<String>,
     pub tags: Vec<String>,
     pub ratings: AvgRatings,
+    /// The great-circle distance from the center of a
+    /// radius query or the reference point for sorting
+    pub distance: Option<Distance>,
+    /// Only available if highlighting has been requested
+    pub snippets: Option<EntrySnippets>,
+}
+
+/// Excerpts of the title and description in which all matched
+/// terms are enclosed in `<b>` tags. All other characters are
+/// HTML-escaped.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EntrySnippets {
+    /// `None` if no terms matched within the title
+    pub title: Option<String>,
+    /// `None` if no terms matched within the description
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntrySortOrder {
+    /// Highest total rating first
+    Rating,
+    /// Best matching text first
+    Relevance,
+    /// Closest to the given point first
+    Distance(MapPoint),
+    /// Most recently created first
+    Newest,
+}
+
+impl Default for EntrySortOrder {
+    fn default() -> Self {
+        EntrySortOrder::Rating
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct EntryIndexQuery<'a, 'b> {
     pub include_bbox: Option<MapBbox>,
     pub exclude_bbox: Option<MapBbox>,
+    /// Only entries within the great-circle distance
+    /// around the center point
+    pub radius: Option<(MapPoint, Distance)>,
+    /// Only entries inside of the polygon(s)
+    pub area: Option<MapMultiPolygon>,
+    /// At least one of these categories must match
     pub categories: Vec<&'a str>,
+    /// Each of these categories must match
+    pub required_categories: Vec<&'a str>,
+    pub excluded_categories: Vec<&'a str>,
     pub ids: Vec<&'b str>,
     pub hash_tags: Vec<String>,
+    /// At least one tag of each group must match
+    pub hash_tag_alternatives: Vec<Vec<String>>,
+    pub excluded_hash_tags: Vec<String>,
+    /// Each phrase must match either the title or the description
+    pub phrases: Vec<String>,
     pub text_tags: Vec<String>,
     pub text: Option<String>,
+    /// Only entries with at least this average total rating
+    pub min_rating: Option<AvgRatingValue>,
+    /// Only entries with at least these average ratings per context
+    pub min_context_ratings: Vec<(RatingContext, AvgRatingValue)>,
+    /// Only entries with opening hours that include this
+    /// (local) time
+    pub open_at: Option<NaiveDateTime>,
+    /// Tolerate typos when matching text and text tags
+    pub fuzzy: bool,
+    pub sort: EntrySortOrder,
+    /// Mark the matched terms in snippets of the title and description
+    pub highlight: bool,
+}
+
+/// A single page of search results.
+#[derive(Debug, Default, Clone)]
+pub struct IndexedEntriesPage {
+    pub entries: Vec<IndexedEntry>,
+    /// The total number of entries that match the query,
+    /// independent of the requested offset and limit.
+    pub total_count: usize,
+}
+
+/// The number of matching entries per category and tag.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EntryFacets {
+    pub categories: Vec<(String, usize)>,
+    /// Only the most frequent tags ordered by descending count
+    pub tags: Vec<(String, usize)>,
+}
+
+/// A grid of equally sized cells for clustering entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusterGrid {
+    /// The height of each cell in degrees
+    pub lat_deg: f64,
+    /// The width of each cell in degrees
+    pub lng_deg: f64,
+}
+
+/// All matching entries within a single cell of a grid.
+#[derive(Debug, Clone)]
+pub struct EntryCluster {
+    /// The centroid of all entries in the cell
+    pub center: MapPoint,
+    pub count: usize,
+    /// The entry with the highest total rating
+    pub top_entry: IndexedEntry,
+}
+
+/// What should be suggested while typing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestionKind {
+    Tag,
+    Title,
 }
 
 pub trait EntryIndex {
-    fn query_entries(&self, query: &EntryIndexQuery, limit: usize) -> Fallible<Vec<IndexedEntry>>;
+    fn query_entries(&self, query: &EntryIndexQuery, limit: usize) -> Fallible<Vec<IndexedEntry>> {
+        Ok(self.query_entries_page(query, 0, limit)?.entries)
+    }
+
+    /// Results are ordered deterministically, i.e. consecutive
+    /// pages neither overlap nor skip any entries as long as
+    /// the index is not modified in between.
+    fn query_entries_page(
+        &self,
+        query: &EntryIndexQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Fallible<IndexedEntriesPage>;
+
+    fn query_facets(&self, query: &EntryIndexQuery, max_tags: usize) -> Fallible<EntryFacets>;
+
+    /// Groups all matching entries by the cells of the grid,
+    /// ordered by the descending number of entries per cell.
+    fn query_clusters(
+        &self,
+        query: &EntryIndexQuery,
+        grid: &ClusterGrid,
+    ) -> Fallible<Vec<EntryCluster>>;
+
+    /// Tags or titles that start with the given prefix, ordered
+    /// by the descending number of (visible) entries.
+    fn suggest(
+        &self,
+        kind: SuggestionKind,
+        prefix: &str,
+        bbox: Option<&MapBbox>,
+        limit: usize,
+    ) -> Fallible<Vec<(String, usize)>>;
 }
 
 pub trait EntryIndexer: EntryIndex {
     fn add_or_update_entry(&mut self, entry: &Entry, ratings: &AvgRatings) -> Fallible<()>;
     fn remove_entry_by_id(&mut self, id: &str) -> Fallible<()>;
+
+    /// Commits all changes without changing the high water mark.
     fn flush(&mut self) -> Fallible<()>;
+
+    /// Commits all changes and sets the high water mark. The caller
+    /// must ensure that all changes that have been stored before
+    /// the given time have been indexed.
+    fn flush_with_high_water_mark(&mut self, high_water_mark: Timestamp) -> Fallible<()>;
+
+    /// All changes that have been stored before this time are
+    /// indexed, i.e. only more recent changes need to be indexed
+    /// after a restart. `None` if no high water mark has been set.
+    fn high_water_mark(&self) -> Fallible<Option<Timestamp>>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct EventIndexQuery {
+    pub bbox: Option<MapBbox>,
+    pub start_min: Option<NaiveDateTime>,
+    pub start_max: Option<NaiveDateTime>,
+    /// Matches events with any of the given tags
+    pub tags: Vec<String>,
+    pub text: Option<String>,
+}
+
+pub trait EventIndex {
+    /// Returns the ids of the matching events ordered by relevance,
+    /// skipping the first `offset` events.
+    fn query_events(
+        &self,
+        query: &EventIndexQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Fallible<Vec<String>>;
+}
+
+pub trait EventIndexer: EventIndex {
+    fn add_or_update_event(&mut self, event: &Event) -> Fallible<()>;
+    fn remove_event_by_id(&mut self, id: &str) -> Fallible<()>;
+    /// Removes all events that have been flushed, e.g.
+    /// before all events are indexed again.
+    fn remove_all_events(&mut self) -> Fallible<()>;
+    fn flush_events(&mut self) -> Fallible<()>;
+}
+
+/// Resolves the geographical location of addresses and vice versa.
+pub trait Geocoder {
+    /// Returns `None` if the address could not be resolved.
+    fn resolve_address(&self, address: &Address) -> Fallible<Option<MapPoint>>;
+
+    /// Returns the address nearby a position or `None`
+    /// if unknown. Not all geocoders support this.
+    fn reverse_geocode(&self, _pos: MapPoint) -> Fallible<Option<Address>> {
+        Ok(None)
+    }
+}
+
+pub trait GeocodingGateway {
+    /// Fails with `RepoError::NotFound` if the address has not
+    /// been stored before. Returns `None` if it is known that
+    /// the address could not be resolved, together with the
+    /// time when the result has been stored.
+    fn get_geocoded_address(&self, address: &str) -> Result<(Option<MapPoint>, Timestamp)>;
+    fn store_geocoded_address(&self, address: &str, pos: Option<MapPoint>) -> Result<()>;
 }
@@ -2,7 +2,9 @@ use chrono::prelude::*;
 
 use crate::core::util::{
     geo::{MapBbox, MapPoint},
+    opening_hours::OpeningHours,
     password::Password,
+    recurrence::RecurrenceRule,
     time::Timestamp,
 };
 
@@ -24,6 +26,19 @@ pub struct Entry {
     pub license        : Option<String>,
     pub image_url      : Option<String>,
     pub image_link_url : Option<String>,
+    pub opening_hours  : Option<OpeningHours>,
+}
+
+/// Records who created a new version of an entry
+/// by reverting to an earlier version.
+#[rustfmt::skip]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryRevert {
+    pub entry_id         : String,
+    pub version          : u64,
+    pub reverted_version : u64,
+    pub reverted_by      : String,
+    pub created          : Timestamp,
 }
 
 #[rustfmt::skip]
@@ -80,6 +95,19 @@ pub struct Event {
     pub registration : Option<RegistrationType>,
     pub organizer    : Option<String>,
     pub archived     : Option<Timestamp>,
+    pub rrule        : Option<RecurrenceRule>,
+    pub exdates      : Vec<NaiveDateTime>,
+}
+
+impl Event {
+    /// The start of the last occurrence, `None` if
+    /// a recurring event never ends.
+    pub fn last_start(&self) -> Option<NaiveDateTime> {
+        match self.rrule {
+            Some(ref rrule) => rrule.last_start(self.start),
+            None => Some(self.start),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -471,6 +499,7 @@ pub mod entry_builder {
                     license: None,
                     image_url: None,
                     image_link_url: None,
+                    opening_hours: None,
                 },
             }
         }
@@ -73,6 +73,48 @@ quick_error! {
         InvalidLimit{
             description("Invalid limit")
         }
+        InvalidOffset{
+            description("Invalid offset")
+        }
+        SortOrder{
+            description("Invalid sort order")
+        }
+        Radius{
+            description("Invalid radius")
+        }
+        Area{
+            description("Invalid area")
+        }
+        SuggestionKind{
+            description("Invalid kind of suggestion")
+        }
+        Zoom{
+            description("Invalid zoom level")
+        }
+        TooManyClusters{
+            description("Too many clusters for the zoom level and bounding box")
+        }
+        Tile{
+            description("Invalid tile coordinates")
+        }
+        RevertVersion{
+            description("Only earlier versions can be restored")
+        }
+        OpeningHours{
+            description("Invalid opening hours")
+        }
+        OpenAt{
+            description("Invalid time for opening hours")
+        }
+        UtcOffset{
+            description("Invalid or missing UTC offset")
+        }
+        RecurrenceRule{
+            description("Invalid recurrence rule")
+        }
+        TooManyOccurrences{
+            description("Too many occurrences of recurring events within the time range")
+        }
     }
 }
 
@@ -48,8 +48,14 @@ pub trait RatingRepository {
     fn load_ratings(&self, ids: &[&str]) -> Result<Vec<Rating>>;
     fn load_ratings_of_entry(&self, entry_id: &str) -> Result<Vec<Rating>>;
 
+    // All ratings that had been created before and were not archived yet
+    fn load_ratings_as_of(&self, as_of: Timestamp) -> Result<Vec<Rating>>;
+
     fn archive_ratings(&self, ids: &[&str], archived: Timestamp) -> Result<usize>;
     fn archive_ratings_of_entries(&self, entry_ids: &[&str], archived: Timestamp) -> Result<usize>;
 
     fn load_entry_ids_of_ratings(&self, ids: &[&str]) -> Result<Vec<String>>;
+
+    // Including archived ratings
+    fn load_entry_ids_of_ratings_changed_since(&self, since: Timestamp) -> Result<Vec<String>>;
 }
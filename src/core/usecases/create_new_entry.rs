@@ -1,6 +1,6 @@
 use crate::core::{
     prelude::*,
-    util::{parse::parse_url_param, validate::Validate},
+    util::{opening_hours::OpeningHours, parse::parse_url_param, validate::Validate},
 };
 use uuid::Uuid;
 
@@ -23,6 +23,7 @@ pub struct NewEntry {
     pub license        : String,
     pub image_url      : Option<String>,
     pub image_link_url : Option<String>,
+    pub opening_hours  : Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -34,7 +35,38 @@ impl Storable {
     }
 }
 
-pub fn prepare_new_entry<D: Db>(db: &D, e: NewEntry) -> Result<Storable> {
+/// Looks up the address of a new entry without an address
+/// by reverse geocoding its position.
+///
+/// This may take a while and should be done before acquiring
+/// a database connection for storing the entry.
+pub fn reverse_geocode_new_entry(geocoder: &Geocoder, e: &NewEntry) -> Option<Address> {
+    let address = Address {
+        street: e.street.clone(),
+        zip: e.zip.clone(),
+        city: e.city.clone(),
+        country: e.country.clone(),
+    };
+    if !address.is_empty() {
+        return None;
+    }
+    let pos = MapPoint::try_from_lat_lng_deg(e.lat, e.lng)?;
+    match geocoder.reverse_geocode(pos) {
+        Ok(address) => address,
+        Err(err) => {
+            warn!("Failed to find the address of a new entry: {}", err);
+            None
+        }
+    }
+}
+
+/// Missing addresses are prefilled with the reverse geocoded
+/// address if available.
+pub fn prepare_new_entry<D: Db>(
+    db: &D,
+    geocoded_address: Option<Address>,
+    e: NewEntry,
+) -> Result<Storable> {
     let NewEntry {
         title,
         description,
@@ -63,7 +95,7 @@ pub fn prepare_new_entry<D: Db>(db: &D, e: NewEntry) -> Result<Storable> {
         country,
     };
     let address = if address.is_empty() {
-        None
+        geocoded_address
     } else {
         Some(address)
     };
@@ -85,6 +117,10 @@ pub fn prepare_new_entry<D: Db>(db: &D, e: NewEntry) -> Result<Storable> {
         .image_link_url
         .map(|ref url| parse_url_param(url))
         .transpose()?;
+    let opening_hours = e
+        .opening_hours
+        .map(|ref oh| oh.parse::<OpeningHours>())
+        .transpose()?;
 
     let e = Entry {
         id,
@@ -102,6 +138,7 @@ pub fn prepare_new_entry<D: Db>(db: &D, e: NewEntry) -> Result<Storable> {
         license: Some(e.license),
         image_url,
         image_link_url,
+        opening_hours,
     };
     e.validate()?;
     Ok(Storable(e))
@@ -124,6 +161,7 @@ mod tests {
 
     use super::super::tests::MockDb;
     use super::*;
+    use failure::Fallible;
     use uuid::Uuid;
 
     #[test]
@@ -146,10 +184,11 @@ mod tests {
             license     : "CC0-1.0".into(),
             image_url     : None,
             image_link_url: None,
+            opening_hours : None,
         };
         let mock_db = MockDb::default();
         let now = Timestamp::now();
-        let e = prepare_new_entry(&mock_db, x).unwrap();
+        let e = prepare_new_entry(&mock_db, None, x).unwrap();
         let (e, initial_ratings) = store_new_entry(&mock_db, e).unwrap();
         assert!(initial_ratings.is_empty());
         assert!(Uuid::parse_str(&e.id).is_ok());
@@ -184,9 +223,47 @@ mod tests {
             license     : "CC0-1.0".into(),
             image_url     : None,
             image_link_url: None,
+            opening_hours : None,
         };
         let mut mock_db: MockDb = MockDb::default();
-        assert!(prepare_new_entry(&mut mock_db, x).is_err());
+        assert!(prepare_new_entry(&mut mock_db, None, x).is_err());
+    }
+
+    #[test]
+    fn create_entry_with_opening_hours() {
+        #[rustfmt::skip]
+        let x = NewEntry {
+            title       : "foo".into(),
+            description : "bar".into(),
+            lat         : 0.0,
+            lng         : 0.0,
+            street      : None,
+            zip         : None,
+            city        : None,
+            country     : None,
+            email       : None,
+            telephone   : None,
+            homepage    : None,
+            categories  : vec![],
+            tags        : vec![],
+            license     : "CC0-1.0".into(),
+            image_url     : None,
+            image_link_url: None,
+            opening_hours : Some("Mo-Fr 08:00-18:00".into()),
+        };
+        let mock_db = MockDb::default();
+        let mut y = x.clone();
+        y.opening_hours = Some("Mo-Fr 8-18".into());
+        let e = prepare_new_entry(&mock_db, None, x).unwrap();
+        let (e, _) = store_new_entry(&mock_db, e).unwrap();
+        assert_eq!(
+            "Mo-Fr 08:00-18:00",
+            e.opening_hours.as_ref().unwrap().as_str()
+        );
+        assert!(match prepare_new_entry(&mock_db, None, y) {
+            Err(Error::Parameter(ParameterError::OpeningHours)) => true,
+            _ => false,
+        });
     }
 
     #[test]
@@ -209,12 +286,66 @@ mod tests {
             license     : "CC0-1.0".into(),
             image_url     : None,
             image_link_url: None,
+            opening_hours : None,
         };
         let mock_db = MockDb::default();
-        let e = prepare_new_entry(&mock_db, x).unwrap();
+        let e = prepare_new_entry(&mock_db, None, x).unwrap();
         assert!(store_new_entry(&mock_db, e).is_ok());
         assert_eq!(mock_db.tags.borrow().len(), 2);
         assert_eq!(mock_db.entries.borrow().len(), 1);
     }
 
+    struct FakeGeocoder;
+
+    impl Geocoder for FakeGeocoder {
+        fn resolve_address(&self, _: &Address) -> Fallible<Option<MapPoint>> {
+            Ok(None)
+        }
+
+        fn reverse_geocode(&self, _: MapPoint) -> Fallible<Option<Address>> {
+            Ok(Some(Address {
+                city: Some("Stuttgart".into()),
+                ..Default::default()
+            }))
+        }
+    }
+
+    #[test]
+    fn create_new_entry_with_reverse_geocoded_address() {
+        #[rustfmt::skip]
+        let x = NewEntry {
+            title       : "foo".into(),
+            description : "bar".into(),
+            lat         : 48.7,
+            lng         : 9.1,
+            street      : None,
+            zip         : None,
+            city        : None,
+            country     : None,
+            email       : None,
+            telephone   : None,
+            homepage    : None,
+            categories  : vec![],
+            tags        : vec![],
+            license     : "CC0-1.0".into(),
+            image_url     : None,
+            image_link_url: None,
+            opening_hours : None,
+        };
+        let mock_db = MockDb::default();
+        let mut y = x.clone();
+        y.city = Some("Berlin".into());
+        let geocoded_address = reverse_geocode_new_entry(&FakeGeocoder, &x);
+        assert!(geocoded_address.is_some());
+        let e = prepare_new_entry(&mock_db, geocoded_address.clone(), x).unwrap();
+        let (e, _) = store_new_entry(&mock_db, e).unwrap();
+        let address = e.location.address.unwrap();
+        assert_eq!(Some("Stuttgart".to_string()), address.city);
+        // Explicitly given addresses are never replaced
+        assert!(reverse_geocode_new_entry(&FakeGeocoder, &y).is_none());
+        let e = prepare_new_entry(&mock_db, geocoded_address, y).unwrap();
+        let (e, _) = store_new_entry(&mock_db, e).unwrap();
+        let address = e.location.address.unwrap();
+        assert_eq!(Some("Berlin".to_string()), address.city);
+    }
 }
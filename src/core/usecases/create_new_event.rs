@@ -3,6 +3,7 @@ use crate::core::{
     prelude::*,
     util::{
         parse::parse_url_param,
+        recurrence::RecurrenceRule,
         validate::{AutoCorrect, Validate},
     },
 };
@@ -31,6 +32,8 @@ pub struct NewEvent {
     pub token        : Option<String>,
     pub registration : Option<String>,
     pub organizer    : Option<String>,
+    pub rrule        : Option<String>,
+    pub exdates      : Option<Vec<i64>>,
 }
 
 // TODO: move this into an adapter
@@ -95,6 +98,8 @@ pub fn try_into_new_event<D: Db>(db: &mut D, e: NewEvent) -> Result<Event> {
         registration,
         token,
         organizer,
+        rrule,
+        exdates,
         ..
     } = e;
     let org = if let Some(ref token) = token {
@@ -201,6 +206,18 @@ pub fn try_into_new_event<D: Db>(db: &mut D, e: NewEvent) -> Result<Event> {
     let start = NaiveDateTime::from_timestamp(start, 0);
     let end = end.map(|e| NaiveDateTime::from_timestamp(e, 0));
 
+    let rrule = rrule
+        .filter(|r| !r.trim().is_empty())
+        .map(|ref r| r.parse::<RecurrenceRule>())
+        .transpose()?;
+    let mut exdates: Vec<_> = exdates
+        .unwrap_or_default()
+        .into_iter()
+        .map(|x| NaiveDateTime::from_timestamp(x, 0))
+        .collect();
+    exdates.sort_unstable();
+    exdates.dedup();
+
     let event = Event {
         id,
         title,
@@ -215,6 +232,8 @@ pub fn try_into_new_event<D: Db>(db: &mut D, e: NewEvent) -> Result<Event> {
         registration,
         organizer,
         archived: None,
+        rrule,
+        exdates,
     };
     let event = event.auto_correct();
     event.validate()?;
@@ -266,6 +285,8 @@ mod tests {
             token        : None,
             registration : None,
             organizer    : None,
+            rrule        : None,
+            exdates      : None,
         };
         let mut mock_db = MockDb::default();
         let id = create_new_event(&mut mock_db, x).unwrap();
@@ -303,6 +324,8 @@ mod tests {
             token        : None,
             registration : None,
             organizer    : None,
+            rrule        : None,
+            exdates      : None,
         };
         let mut mock_db: MockDb = MockDb::default();
         assert!(create_new_event(&mut mock_db, x).is_err());
@@ -330,6 +353,8 @@ mod tests {
             token        : None,
             registration : None,
             organizer    : None,
+            rrule        : None,
+            exdates      : None,
         };
         let mut mock_db: MockDb = MockDb::default();
         assert!(create_new_event(&mut mock_db, x).is_ok());
@@ -374,9 +399,56 @@ mod tests {
             token        : None,
             registration : None,
             organizer    : None,
+            rrule        : None,
+            exdates      : None,
         };
         assert!(create_new_event(&mut mock_db, x).is_ok());
         let users = mock_db.all_users().unwrap();
         assert_eq!(users.len(), 1);
     }
+
+    #[test]
+    fn create_recurring_event() {
+        #[rustfmt::skip]
+        let x = NewEvent {
+            title        : "Repair Café".into(),
+            description  : None,
+            start        : 1_559_556_000,
+            end          : None,
+            lat          : None,
+            lng          : None,
+            street       : None,
+            zip          : None,
+            city         : None,
+            country      : None,
+            email        : None,
+            telephone    : None,
+            homepage     : None,
+            tags         : None,
+            created_by   : Some("foo@bar.com".into()),
+            token        : None,
+            registration : None,
+            organizer    : None,
+            rrule        : Some("FREQ=WEEKLY;COUNT=10".into()),
+            exdates      : Some(vec![1_560_765_600, 1_560_160_800, 1_560_765_600]),
+        };
+        let mut mock_db = MockDb::default();
+        assert!(create_new_event(&mut mock_db, x.clone()).is_ok());
+        let e = &mock_db.events.borrow()[0];
+        assert_eq!(e.rrule.as_ref().unwrap().as_str(), "FREQ=WEEKLY;COUNT=10");
+        assert_eq!(
+            e.exdates,
+            vec![
+                NaiveDateTime::from_timestamp(1_560_160_800, 0),
+                NaiveDateTime::from_timestamp(1_560_765_600, 0),
+            ]
+        );
+
+        let mut mock_db = MockDb::default();
+        let x = NewEvent {
+            rrule: Some("FREQ=FORTNIGHTLY".into()),
+            ..x
+        };
+        assert!(create_new_event(&mut mock_db, x).is_err());
+    }
 }
@@ -0,0 +1,178 @@
+use crate::core::{
+    prelude::*,
+    util::{
+        geo::{MapBbox, MapMultiPolygon},
+        sort::Rated,
+    },
+};
+use std::collections::HashMap;
+
+/// The old and new value of a single field, formatted as text.
+/// Missing or empty values are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryFieldChange {
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryHistoryItem {
+    pub version: u64,
+    pub created: Timestamp,
+    pub archived: Option<Timestamp>,
+    /// Set if this version has been created by reverting
+    /// to an earlier version.
+    pub revert: Option<EntryRevert>,
+    /// The changes since the previous version, empty
+    /// for the initial version.
+    pub changes: Vec<EntryFieldChange>,
+}
+
+fn non_empty(val: &Option<String>) -> Option<String> {
+    val.as_ref().filter(|val| !val.is_empty()).cloned()
+}
+
+/// Lists are compared regardless of their order.
+fn join_sorted(vals: &[String]) -> Option<String> {
+    if vals.is_empty() {
+        return None;
+    }
+    let mut vals = vals.to_vec();
+    vals.sort_unstable();
+    Some(vals.join(","))
+}
+
+fn entry_fields(e: &Entry) -> Vec<(&'static str, Option<String>)> {
+    let (lat, lng) = e.location.pos.to_lat_lng_deg();
+    let address = e.location.address.clone().unwrap_or_default();
+    let contact = e.contact.clone().unwrap_or_default();
+    vec![
+        ("title", Some(e.title.clone())),
+        ("description", Some(e.description.clone())),
+        ("lat", Some(lat.to_string())),
+        ("lng", Some(lng.to_string())),
+        ("street", non_empty(&address.street)),
+        ("zip", non_empty(&address.zip)),
+        ("city", non_empty(&address.city)),
+        ("country", non_empty(&address.country)),
+        ("email", non_empty(&contact.email)),
+        ("telephone", non_empty(&contact.telephone)),
+        ("homepage", non_empty(&e.homepage)),
+        ("categories", join_sorted(&e.categories)),
+        ("tags", join_sorted(&e.tags)),
+        ("license", non_empty(&e.license)),
+        ("image_url", non_empty(&e.image_url)),
+        ("image_link_url", non_empty(&e.image_link_url)),
+        ("opening_hours", e.opening_hours.as_ref().map(ToString::to_string)),
+    ]
+}
+
+/// All fields that differ between two versions of an entry.
+pub fn diff_entries(old: &Entry, new: &Entry) -> Vec<EntryFieldChange> {
+    entry_fields(old)
+        .into_iter()
+        .zip(entry_fields(new))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((field, old), (_, new))| EntryFieldChange { field, old, new })
+        .collect()
+}
+
+pub fn get_entry_history<D: Db>(db: &D, id: &str) -> Result<Vec<EntryHistoryItem>> {
+    let versions = db.get_entry_history(id)?;
+    let reverts = db.get_entry_reverts(id)?;
+    let mut history = Vec::with_capacity(versions.len());
+    let mut prev: Option<&Entry> = None;
+    for e in &versions {
+        let changes = prev.map(|prev| diff_entries(prev, e)).unwrap_or_default();
+        history.push(EntryHistoryItem {
+            version: e.version,
+            created: e.created,
+            archived: e.archived,
+            revert: reverts.iter().find(|r| r.version == e.version).cloned(),
+            changes,
+        });
+        prev = Some(e);
+    }
+    Ok(history)
+}
+
+pub fn get_entry_version<D: Db>(db: &D, id: &str, version: u64) -> Result<Entry> {
+    db.get_entry_history(id)?
+        .into_iter()
+        .find(|e| e.version == version)
+        .ok_or_else(|| RepoError::NotFound.into())
+}
+
+/// Rebuilds the state of all entries within the bounding box
+/// and (optionally) area at the given time.
+///
+/// The average ratings only include ratings that had been created
+/// before and were not archived at the given time.
+pub fn query_entries_as_of<D: Db>(
+    db: &D,
+    as_of: Timestamp,
+    bbox: &MapBbox,
+    area: Option<&MapMultiPolygon>,
+) -> Result<Vec<(Entry, AvgRatings)>> {
+    let entries = db.all_entries_as_of(as_of)?;
+    let mut ratings_by_entry: HashMap<String, Vec<Rating>> = HashMap::new();
+    for r in db.load_ratings_as_of(as_of)? {
+        ratings_by_entry
+            .entry(r.entry_id.clone())
+            .or_insert_with(Vec::new)
+            .push(r);
+    }
+    let mut results = Vec::with_capacity(entries.len());
+    for e in entries {
+        let pos = e.location.pos;
+        if !bbox.contains_point(pos) || !area.map(|a| a.contains_point(pos)).unwrap_or(true) {
+            continue;
+        }
+        let ratings = ratings_by_entry.get(&e.id).map(Vec::as_slice).unwrap_or(&[]);
+        let avg_ratings = e.avg_ratings(ratings);
+        results.push((e, avg_ratings));
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_changed_fields() {
+        let old = Entry::build()
+            .title("foo")
+            .tags(vec!["a", "b"])
+            .categories(vec!["x"])
+            .finish();
+        let mut new = old.clone();
+        new.version = 1;
+        new.title = "bar".into();
+        new.tags = vec!["b".into(), "a".into()];
+        new.categories = vec![];
+        new.homepage = Some("https://example.com".into());
+        assert_eq!(
+            vec![
+                EntryFieldChange {
+                    field: "title",
+                    old: Some("foo".into()),
+                    new: Some("bar".into()),
+                },
+                EntryFieldChange {
+                    field: "homepage",
+                    old: None,
+                    new: Some("https://example.com".into()),
+                },
+                EntryFieldChange {
+                    field: "categories",
+                    old: Some("x".into()),
+                    new: None,
+                },
+            ],
+            diff_entries(&old, &new)
+        );
+        assert!(diff_entries(&new, &new).is_empty());
+    }
+}
@@ -2,6 +2,14 @@ use crate::core::{prelude::*, util::sort::Rated};
 
 use failure::Fallible;
 
+/// The maximum number of entries that are loaded
+/// and indexed at once.
+pub const INDEX_ENTRIES_CHUNK_SIZE: usize = 1_000;
+
+/// Changes that have been stored by concurrent requests shortly
+/// before the last commit might not have been indexed yet.
+pub const INDEX_CATCH_UP_OVERLAP_IN_SECONDS: i64 = 60;
+
 pub fn index_entry(
     indexer: &mut EntryIndexer,
     entry: &Entry,
@@ -12,7 +20,70 @@ pub fn index_entry(
     Ok(avg_ratings)
 }
 
+/// Indexes the next chunk of all current entries, i.e. the entries
+/// that follow the given id. Returns the ids of the indexed entries,
+/// which are empty if no more entries are left.
+pub fn index_entries_chunk<D: EntryGateway + RatingRepository>(
+    db: &D,
+    indexer: &mut EntryIndexer,
+    after_id: Option<&str>,
+) -> Result<Vec<String>> {
+    let entries = db.all_entries_chunk(after_id, INDEX_ENTRIES_CHUNK_SIZE)?;
+    let mut ids = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let ratings = db.load_ratings_of_entry(&entry.id)?;
+        if let Err(err) = index_entry(indexer, &entry, &ratings) {
+            error!("Failed to index entry {:?}: {}", entry, err);
+        }
+        ids.push(entry.id);
+    }
+    Ok(ids)
+}
+
+/// Reindexes all entries that have been created, updated, rated,
+/// or archived since the given time. Returns the number of changed
+/// entries.
+pub fn index_changed_entries<D: EntryGateway + RatingRepository>(
+    db: &D,
+    indexer: &mut EntryIndexer,
+    since: Timestamp,
+) -> Result<usize> {
+    let mut entry_ids = db.changed_entry_ids_since(since)?;
+    entry_ids.extend(db.load_entry_ids_of_ratings_changed_since(since)?);
+    entry_ids.sort_unstable();
+    entry_ids.dedup();
+    info!("Indexing {} changed entries", entry_ids.len());
+    let ids: Vec<_> = entry_ids.iter().map(String::as_str).collect();
+    // Only current entries that have not been archived
+    let entries = db.get_entries(&ids)?;
+    for id in &ids {
+        if entries.iter().any(|entry| entry.id == *id) {
+            continue;
+        }
+        if let Err(err) = unindex_entry(indexer, id) {
+            error!("Failed to remove archived entry {} from index: {}", id, err);
+        }
+    }
+    for entry in entries {
+        let ratings = db.load_ratings_of_entry(&entry.id)?;
+        if let Err(err) = index_entry(indexer, &entry, &ratings) {
+            error!("Failed to index entry {:?}: {}", entry, err);
+        }
+    }
+    Ok(ids.len())
+}
+
 pub fn unindex_entry(indexer: &mut EntryIndexer, entry_id: &str) -> Fallible<()> {
     indexer.remove_entry_by_id(entry_id)?;
     Ok(())
 }
+
+pub fn index_event(indexer: &mut EventIndexer, event: &Event) -> Fallible<()> {
+    indexer.add_or_update_event(event)?;
+    Ok(())
+}
+
+pub fn unindex_event(indexer: &mut EventIndexer, event_id: &str) -> Fallible<()> {
+    indexer.remove_event_by_id(event_id)?;
+    Ok(())
+}
@@ -18,13 +18,16 @@ mod create_new_entry;
 mod create_new_event;
 pub mod create_new_user;
 mod delete_event;
+mod entry_history;
 mod find_duplicates;
 mod indexing;
 mod login;
 mod query_events;
 mod rate_entry;
 mod register;
+mod revert_entry;
 mod search;
+mod suggest;
 #[cfg(test)]
 pub mod tests;
 mod update_entry;
@@ -33,8 +36,9 @@ mod update_event;
 pub use self::{
     archive_comments::*, archive_entries::*, archive_events::*, archive_ratings::*,
     confirm_email::*, create_new_entry::*, create_new_event::*, create_new_user::*,
-    delete_event::*, find_duplicates::*, indexing::*, login::*, query_events::*, rate_entry::*,
-    register::*, search::*, update_entry::*, update_event::*,
+    delete_event::*, entry_history::*, find_duplicates::*, indexing::*, login::*, query_events::*,
+    rate_entry::*, register::*, revert_entry::*, search::*, suggest::*, update_entry::*,
+    update_event::*,
 };
 
 pub fn load_ratings_with_comments<D: Db>(
@@ -58,6 +62,22 @@ pub fn get_user<D: Db>(
     Ok((u.username, u.email))
 }
 
+pub fn authorize_admin<D: Db>(db: &D, username: &str) -> Result<()> {
+    let u: User = db.get_user(username)?;
+    if u.role != Role::Admin {
+        return Err(Error::Parameter(ParameterError::Forbidden));
+    }
+    Ok(())
+}
+
+pub fn authorize_scout<D: Db>(db: &D, username: &str) -> Result<()> {
+    let u: User = db.get_user(username)?;
+    match u.role {
+        Role::Scout | Role::Admin => Ok(()),
+        _ => Err(Error::Parameter(ParameterError::Forbidden)),
+    }
+}
+
 pub fn get_event<D: Db>(db: &D, id: &str) -> Result<Event> {
     let mut e: Event = db.get_event(id)?;
     if let Some(ref username) = e.created_by {
@@ -1,21 +1,87 @@
 use crate::core::{
     prelude::*,
-    util::{
-        filter::{self, InBBox},
-        geo::MapBbox,
-    },
+    util::filter::{self, InBBox},
 };
 use chrono::prelude::*;
 
-pub fn query_events<D: Db>(
-    db: &D,
-    tags: Option<Vec<String>>,
-    bbox: Option<MapBbox>,
+/// The number of occurrences per recurring event
+/// if the time range is open-ended.
+const MAX_OCCURRENCES: usize = 100;
+
+/// The total number of occurrences of all recurring events.
+/// Requests for time ranges with more occurrences are rejected.
+const MAX_TOTAL_OCCURRENCES: usize = 10_000;
+
+/// The maximum number of events that are returned
+/// for a text query.
+const MAX_TEXT_RESULTS: usize = 100;
+
+/// Events that match a text query are paged by relevance
+/// within this number of the most relevant events.
+const MAX_TEXT_WINDOW: usize = 10_000;
+
+/// Replaces recurring events by their occurrences within
+/// the given time range. Each occurrence has the duration
+/// of the original event.
+fn expand_occurrences(
+    events: Vec<Event>,
     start_min: Option<NaiveDateTime>,
     start_max: Option<NaiveDateTime>,
+) -> Result<Vec<Event>> {
+    let mut expanded = Vec::with_capacity(events.len());
+    let mut total_occurrences = 0;
+    for e in events {
+        let rrule = match e.rrule {
+            Some(ref rrule) => rrule.clone(),
+            None => {
+                expanded.push(e);
+                continue;
+            }
+        };
+        let duration = e.end.map(|end| end - e.start);
+        let occurrences = rrule
+            .occurrences(e.start)
+            .take_while(|start| start_max.map(|max| *start <= max).unwrap_or(true))
+            .filter(|start| start_min.map(|min| *start >= min).unwrap_or(true))
+            .filter(|start| !e.exdates.contains(start))
+            .take(if start_max.is_some() {
+                usize::max_value()
+            } else {
+                MAX_OCCURRENCES
+            });
+        for start in occurrences {
+            total_occurrences += 1;
+            if total_occurrences > MAX_TOTAL_OCCURRENCES {
+                warn!(
+                    "Too many occurrences of recurring events between {:?} and {:?}",
+                    start_min, start_max
+                );
+                return Err(Error::Parameter(ParameterError::TooManyOccurrences));
+            }
+            expanded.push(Event {
+                start,
+                end: duration.map(|d| start + d),
+                ..e.clone()
+            });
+        }
+    }
+    Ok(expanded)
+}
+
+pub fn query_events<D: Db>(
+    db: &D,
+    index: &EventIndex,
+    query: EventIndexQuery,
+    offset: usize,
+    limit: Option<usize>,
     created_by: Option<String>,
     token: Option<String>,
 ) -> Result<Vec<Event>> {
+    if limit == Some(0) {
+        warn!("Invalid event query limit: 0");
+        return Err(Error::Parameter(ParameterError::InvalidLimit));
+    }
+
     let _org = if let Some(ref token) = token {
         let org = db.get_org_by_api_token(token).map_err(|e| match e {
             RepoError::NotFound => Error::Parameter(ParameterError::Unauthorized),
@@ -26,24 +92,67 @@ pub fn query_events<D: Db>(
         None
     };
 
-    let mut events = db.all_events()?;
+    let EventIndexQuery {
+        bbox,
+        start_min,
+        start_max,
+        tags,
+        text,
+    } = query;
+    let bbox = bbox.as_ref().map(filter::extend_bbox);
+    // Tags are matched case-insensitively like in the event index
+    let tags: Vec<_> = tags.iter().map(|t| t.to_lowercase()).collect();
 
-    if let Some(bbox) = bbox.as_ref().map(filter::extend_bbox) {
-        events = events.into_iter().filter(|x| x.in_bbox(&bbox)).collect();
-    }
+    let mut events = if text.is_some() {
+        let index_query = EventIndexQuery {
+            bbox,
+            start_min,
+            start_max,
+            tags,
+            text,
+        };
+        let limit = limit.unwrap_or(MAX_TEXT_RESULTS).min(MAX_TEXT_RESULTS);
+        match offset.checked_add(limit) {
+            Some(window) if window <= MAX_TEXT_WINDOW => (),
+            _ => {
+                warn!("Invalid event query offset: {}", offset);
+                return Err(Error::Parameter(ParameterError::InvalidOffset));
+            }
+        }
+        let ids = index
+            .query_events(&index_query, offset, limit)
+            .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+        let ids: Vec<_> = ids.iter().map(String::as_str).collect();
+        db.get_events(&ids)?
+    } else {
+        let mut events = db.all_events()?;
 
-    if let Some(min) = start_min {
-        events = events.into_iter().filter(|e| e.start >= min).collect();
-    }
-    if let Some(max) = start_max {
-        events = events.into_iter().filter(|e| e.start <= max).collect();
-    }
-    if let Some(tags) = tags {
-        events = events
+        if let Some(bbox) = bbox {
+            events = events.into_iter().filter(|x| x.in_bbox(&bbox)).collect();
+        }
+
+        if let Some(min) = start_min {
+            events = events
+                .into_iter()
+                .filter(|e| e.last_start().map(|last| last >= min).unwrap_or(true))
+                .collect();
+        }
+        if let Some(max) = start_max {
+            events = events.into_iter().filter(|e| e.start <= max).collect();
+        }
+        if !tags.is_empty() {
+            events = events
+                .into_iter()
+                .filter(|e| tags.iter().any(|t| e.tags.iter().any(|e_t| e_t.to_lowercase() == *t)))
+                .collect();
+        }
+        events.sort_by(|a, b| a.start.cmp(&b.start));
+        events
             .into_iter()
-            .filter(|e| tags.iter().any(|t| e.tags.iter().any(|e_t| e_t == t)))
-            .collect();
-    }
+            .skip(offset)
+            .take(limit.unwrap_or(usize::max_value()))
+            .collect()
+    };
 
     if let Some(email) = created_by {
         let users = db.all_users()?;
@@ -57,6 +166,96 @@ pub fn query_events<D: Db>(
             }
         }
     }
+    let mut events = expand_occurrences(events, start_min, start_max)?;
     events.sort_by(|a, b| a.start.cmp(&b.start));
     Ok(events)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_event(id: &str, start: &str, end: Option<&str>) -> Event {
+        Event {
+            id: id.into(),
+            title: id.into(),
+            description: None,
+            start: start.parse().unwrap(),
+            end: end.map(|end| end.parse().unwrap()),
+            location: None,
+            contact: None,
+            tags: vec![],
+            homepage: None,
+            created_by: None,
+            registration: None,
+            organizer: None,
+            archived: None,
+            rrule: None,
+            exdates: vec![],
+        }
+    }
+
+    fn dt(s: &str) -> NaiveDateTime {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn expand_weekly_occurrences_within_range() {
+        // 2019-06-01 is a Saturday
+        let mut e = new_event("market", "2019-06-01T10:00:00", Some("2019-06-01T14:00:00"));
+        e.rrule = Some("FREQ=WEEKLY".parse().unwrap());
+        e.exdates = vec![dt("2019-06-15T10:00:00")];
+        let once = new_event("once", "2019-06-10T18:00:00", None);
+        let events = expand_occurrences(
+            vec![e, once],
+            Some(dt("2019-06-05T00:00:00")),
+            Some(dt("2019-06-30T00:00:00")),
+        )
+        .unwrap();
+        let occurrences: Vec<_> = events
+            .iter()
+            .filter(|e| e.id == "market")
+            .map(|e| (e.start, e.end.unwrap()))
+            .collect();
+        assert_eq!(
+            vec![
+                (dt("2019-06-08T10:00:00"), dt("2019-06-08T14:00:00")),
+                (dt("2019-06-22T10:00:00"), dt("2019-06-22T14:00:00")),
+                (dt("2019-06-29T10:00:00"), dt("2019-06-29T14:00:00")),
+            ],
+            occurrences
+        );
+        assert!(events.iter().any(|e| e.id == "once"));
+    }
+
+    #[test]
+    fn expand_limited_occurrences_without_end_of_range() {
+        let mut e = new_event("daily", "2019-06-01T10:00:00", None);
+        e.rrule = Some("FREQ=DAILY".parse().unwrap());
+        let events = expand_occurrences(vec![e], Some(dt("2019-06-03T00:00:00")), None).unwrap();
+        assert_eq!(MAX_OCCURRENCES, events.len());
+        assert_eq!(dt("2019-06-03T10:00:00"), events[0].start);
+        assert!(events[0].end.is_none());
+    }
+
+    #[test]
+    fn reject_too_many_occurrences_within_range() {
+        let mut e = new_event("daily", "2019-06-01T10:00:00", None);
+        e.rrule = Some("FREQ=DAILY".parse().unwrap());
+        let events = expand_occurrences(
+            vec![e.clone()],
+            Some(dt("2019-06-01T00:00:00")),
+            Some(dt("2020-06-01T00:00:00")),
+        )
+        .unwrap();
+        assert_eq!(366, events.len());
+        assert!(match expand_occurrences(
+            vec![e],
+            Some(dt("2019-06-01T00:00:00")),
+            Some(dt("2100-01-01T00:00:00")),
+        ) {
+            Err(Error::Parameter(ParameterError::TooManyOccurrences)) => true,
+            _ => false,
+        });
+    }
+}
@@ -26,6 +26,9 @@ impl Storable {
     pub fn comment_id(&self) -> &str {
         &self.2.id
     }
+    pub fn created(&self) -> Timestamp {
+        self.1.created
+    }
 }
 
 pub fn prepare_new_rating<D: Db>(db: &D, r: RateEntry) -> Result<Storable> {
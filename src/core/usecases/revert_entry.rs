@@ -0,0 +1,105 @@
+use super::UpdateEntry;
+use crate::core::prelude::*;
+
+/// Prepares an update of an entry with the contents of an
+/// earlier version. The update then follows the regular path
+/// of updating entries.
+pub fn revert_entry<D: Db>(
+    db: &D,
+    username: &str,
+    id: &str,
+    version: u64,
+) -> Result<UpdateEntry> {
+    super::authorize_scout(db, username)?;
+    let current = db.get_entry(id)?;
+    if version >= current.version {
+        return Err(ParameterError::RevertVersion.into());
+    }
+    let Entry {
+        osm_node,
+        title,
+        description,
+        location,
+        contact,
+        homepage,
+        categories,
+        tags,
+        image_url,
+        image_link_url,
+        opening_hours,
+        ..
+    } = super::get_entry_version(db, id, version)?;
+    info!(
+        "Reverting entry {} from version {} to version {} on behalf of {}",
+        id, current.version, version, username
+    );
+    let Location { pos, address } = location;
+    let (lat, lng) = pos.to_lat_lng_deg();
+    let Address {
+        street,
+        zip,
+        city,
+        country,
+    } = address.unwrap_or_default();
+    let Contact { email, telephone } = contact.unwrap_or_default();
+    Ok(UpdateEntry {
+        osm_node,
+        version: current.version + 1,
+        title,
+        description,
+        lat,
+        lng,
+        street,
+        zip,
+        city,
+        country,
+        email,
+        telephone,
+        homepage,
+        categories,
+        tags,
+        image_url,
+        image_link_url,
+        opening_hours: opening_hours.map(|oh| oh.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::MockDb;
+    use super::*;
+
+    fn create_user(db: &MockDb, username: &str, role: Role) {
+        db.create_user(User {
+            id: username.into(),
+            username: username.into(),
+            password: "secret".parse::<Password>().unwrap(),
+            email: format!("{}@example.com", username),
+            email_confirmed: true,
+            role,
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn revert_entry_as_scout_only() {
+        let db = MockDb::default();
+        create_user(&db, "user", Role::User);
+        create_user(&db, "scout", Role::Scout);
+        let e = Entry::build().id("foo").version(1).finish();
+        db.entries.borrow_mut().push(e);
+        assert!(match revert_entry(&db, "user", "foo", 0) {
+            Err(Error::Parameter(ParameterError::Forbidden)) => true,
+            _ => false,
+        });
+        assert!(match revert_entry(&db, "scout", "foo", 1) {
+            Err(Error::Parameter(ParameterError::RevertVersion)) => true,
+            _ => false,
+        });
+        // The mock database only stores the current version
+        assert!(match revert_entry(&db, "scout", "foo", 0) {
+            Err(Error::Repo(RepoError::NotFound)) => true,
+            _ => false,
+        });
+    }
+}
@@ -1,36 +1,90 @@
 use crate::core::prelude::*;
-use crate::core::util::{self, filter, geo::MapBbox};
+use crate::core::util::{
+    self, filter,
+    geo::{MapBbox, MapMultiPolygon},
+    query::SearchText,
+};
+use chrono::NaiveDateTime;
 
 #[rustfmt::skip]
 #[derive(Debug, Clone)]
 pub struct SearchRequest<'a, 'b, 'c, 'd> {
     pub bbox       : MapBbox,
+    /// Only entries within the distance around the center
+    pub radius     : Option<(MapPoint, Distance)>,
+    /// Only entries inside of the area
+    pub area       : Option<MapMultiPolygon>,
     pub ids        : Vec<&'b str>,
     pub categories : Vec<&'a str>,
     pub hash_tags  : Vec<&'c str>,
     pub text       : Option<&'d str>,
+    /// Only entries with at least this average total rating
+    pub min_rating : Option<AvgRatingValue>,
+    /// Only entries with at least these average ratings per context
+    pub min_context_ratings: Vec<(RatingContext, AvgRatingValue)>,
+    /// Only entries that are open at this (local) time
+    pub open_at    : Option<NaiveDateTime>,
+    pub fuzzy      : bool,
+    pub sort       : EntrySortOrder,
+    /// Mark the matched terms in snippets of the results
+    pub highlight  : bool,
+    /// Request facet counts with up to the given number of tags
+    pub facets     : Option<usize>,
 }
 
-pub fn search(
-    index: &EntryIndex,
-    req: SearchRequest,
-    limit: usize,
-) -> Result<(Vec<IndexedEntry>, Vec<IndexedEntry>)> {
-    let visible_bbox: MapBbox = req.bbox;
+#[derive(Debug, Default, Clone)]
+pub struct SearchResults {
+    pub visible: Vec<IndexedEntry>,
+    pub invisible: Vec<IndexedEntry>,
+    /// The total number of visible entries
+    pub visible_count: usize,
+    /// The offset of the next page of visible entries (if any)
+    pub next_offset: Option<usize>,
+    /// Facet counts of all visible entries (if requested)
+    pub facets: Option<EntryFacets>,
+}
 
-    let mut hash_tags = req.text.map(util::extract_hash_tags).unwrap_or_default();
-    hash_tags.reserve(hash_tags.len() + req.hash_tags.len());
+/// Validates the request and builds the query for all
+/// visible entries. Categories might be borrowed from
+/// the parsed search text.
+fn build_entries_query<'a, 'b>(
+    req: SearchRequest<'a, 'b, '_, '_>,
+    search_text: &'a SearchText,
+) -> Result<EntryIndexQuery<'a, 'b>> {
+    let min_ratings = req
+        .min_rating
+        .iter()
+        .chain(req.min_context_ratings.iter().map(|(_, value)| value));
+    for min_rating in min_ratings {
+        if !min_rating.is_valid() {
+            return Err(Error::Parameter(ParameterError::RatingValue));
+        }
+    }
+
+    let mut hash_tags = search_text.hash_tags.clone();
+    hash_tags.reserve(req.hash_tags.len());
     for hashtag in req.hash_tags {
         hash_tags.push(hashtag.to_owned());
     }
 
-    let text = req.text.map(util::remove_hash_tags).and_then(|text| {
-        if text.trim().is_empty() {
-            None
-        } else {
-            Some(text)
-        }
-    });
+    // Categories from the search text narrow down
+    // the requested categories
+    let required_categories = search_text
+        .categories
+        .iter()
+        .map(String::as_str)
+        .collect();
+    let excluded_categories = search_text
+        .excluded_categories
+        .iter()
+        .map(String::as_str)
+        .collect();
+
+    let text = if search_text.text.trim().is_empty() {
+        None
+    } else {
+        Some(search_text.text.clone())
+    };
 
     let text_tags = text
         .as_ref()
@@ -38,28 +92,77 @@ pub fn search(
         .map(filter::split_text_to_words)
         .unwrap_or_default();
 
-    let visible_entries_query = EntryIndexQuery {
-        include_bbox: Some(visible_bbox),
+    Ok(EntryIndexQuery {
+        include_bbox: Some(req.bbox),
         exclude_bbox: None,
+        radius: req.radius,
+        area: req.area,
         categories: req.categories,
+        required_categories,
+        excluded_categories,
         ids: req.ids,
         hash_tags,
+        hash_tag_alternatives: search_text.hash_tag_alternatives.clone(),
+        excluded_hash_tags: search_text.excluded_hash_tags.clone(),
+        phrases: search_text.phrases.clone(),
         text_tags,
         text,
-    };
+        min_rating: req.min_rating,
+        min_context_ratings: req.min_context_ratings,
+        open_at: req.open_at,
+        fuzzy: req.fuzzy,
+        sort: req.sort,
+        highlight: req.highlight,
+    })
+}
+
+pub fn search(
+    index: &EntryIndex,
+    req: SearchRequest,
+    offset: usize,
+    limit: usize,
+) -> Result<SearchResults> {
+    let visible_bbox: MapBbox = req.bbox;
+    let max_facet_tags = req.facets;
+
+    let search_text = req
+        .text
+        .map(util::query::parse_search_text)
+        .unwrap_or_default();
+    let visible_entries_query = build_entries_query(req, &search_text)?;
 
     // 1st query: Search for visible results only
     // This is required to reliably retrieve all available results!
     // See also: https://github.com/slowtec/openfairdb/issues/183
-    let visible_entries = index
-        .query_entries(&visible_entries_query, limit)
+    let IndexedEntriesPage {
+        entries: visible_entries,
+        total_count: visible_count,
+    } = index
+        .query_entries_page(&visible_entries_query, offset, limit)
         .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
     debug_assert!(visible_entries
         .iter()
         .all(|e| visible_bbox.contains_point(e.pos)));
 
+    let facets = if let Some(max_tags) = max_facet_tags {
+        Some(
+            index
+                .query_facets(&visible_entries_query, max_tags)
+                .map_err(|err| RepoError::Other(Box::new(err.compat())))?,
+        )
+    } else {
+        None
+    };
+
+    let next_offset = if offset + visible_entries.len() < visible_count {
+        Some(offset + visible_entries.len())
+    } else {
+        None
+    };
+
     // 2nd query: Search for remaining invisible results
-    let invisible_entries = if visible_entries.len() < limit {
+    // after all visible results have been delivered
+    let invisible_entries = if next_offset.is_none() && visible_entries.len() < limit {
         let invisible_entries_query = EntryIndexQuery {
             include_bbox: Some(filter::extend_bbox(&visible_bbox)),
             exclude_bbox: visible_entries_query.include_bbox,
@@ -75,7 +178,98 @@ pub fn search(
         .iter()
         .any(|e| visible_bbox.contains_point(e.pos)));
 
-    Ok((visible_entries, invisible_entries))
+    Ok(SearchResults {
+        visible: visible_entries,
+        invisible: invisible_entries,
+        visible_count,
+        next_offset,
+        facets,
+    })
+}
+
+/// All visible entries that match the search request up to
+/// the given limit, i.e. without any invisible entries.
+pub fn search_all(
+    index: &EntryIndex,
+    req: SearchRequest,
+    limit: usize,
+) -> Result<Vec<IndexedEntry>> {
+    let search_text = req
+        .text
+        .map(util::query::parse_search_text)
+        .unwrap_or_default();
+    let query = build_entries_query(req, &search_text)?;
+
+    let entries = index
+        .query_entries(&query, limit)
+        .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+
+    Ok(entries)
+}
+
+/// The maximum zoom level of web maps.
+pub const MAX_CLUSTER_ZOOM: u8 = 22;
+
+/// The number of grid cells per map tile in each direction,
+/// i.e. each cell covers 64 x 64 pixels of a 256 x 256 tile.
+const CLUSTER_CELLS_PER_TILE: f64 = 4.0;
+
+/// Each cluster requires to load its top entry. The limit
+/// is sufficient for a full-screen map with 4K resolution.
+const MAX_CLUSTER_CELLS: f64 = 4_096.0;
+
+/// An upper bound for the number of grid cells that
+/// overlap with the bounding box.
+fn count_cluster_cells(grid: &ClusterGrid, bbox: &MapBbox) -> f64 {
+    let (sw, ne) = (bbox.south_west(), bbox.north_east());
+    let lat_span = ne.lat().to_deg() - sw.lat().to_deg();
+    let mut lng_span = ne.lng().to_deg() - sw.lng().to_deg();
+    if lng_span < 0.0 {
+        // Crosses the antimeridian
+        lng_span += 360.0;
+    }
+    ((lat_span / grid.lat_deg).ceil() + 1.0) * ((lng_span / grid.lng_deg).ceil() + 1.0)
+}
+
+/// A grid with roughly square cells on a web map at the given
+/// zoom level around the center of the bounding box.
+fn cluster_grid(zoom: u8, bbox: &MapBbox) -> ClusterGrid {
+    let tile_width_deg = 360.0 / 2f64.powi(i32::from(zoom));
+    let lng_deg = tile_width_deg / CLUSTER_CELLS_PER_TILE;
+    // The Mercator projection stretches latitudes
+    // proportional to 1 / cos(lat)
+    let center_lat_deg =
+        (bbox.south_west().lat().to_deg() + bbox.north_east().lat().to_deg()) / 2.0;
+    let lat_deg = (lng_deg * center_lat_deg.to_radians().cos()).max(std::f64::EPSILON);
+    ClusterGrid { lat_deg, lng_deg }
+}
+
+/// Groups all entries that match the search request into
+/// the cells of a grid that depends on the zoom level.
+pub fn search_clusters(
+    index: &EntryIndex,
+    req: SearchRequest,
+    zoom: u8,
+) -> Result<Vec<EntryCluster>> {
+    if zoom > MAX_CLUSTER_ZOOM {
+        return Err(Error::Parameter(ParameterError::Zoom));
+    }
+    let grid = cluster_grid(zoom, &req.bbox);
+    if count_cluster_cells(&grid, &req.bbox) > MAX_CLUSTER_CELLS {
+        return Err(Error::Parameter(ParameterError::TooManyClusters));
+    }
+
+    let search_text = req
+        .text
+        .map(util::query::parse_search_text)
+        .unwrap_or_default();
+    let query = build_entries_query(req, &search_text)?;
+
+    let clusters = index
+        .query_clusters(&query, &grid)
+        .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+
+    Ok(clusters)
 }
 
 /// The global search usecase is like the one
@@ -83,9 +277,15 @@ pub fn search(
 /// of only one single search input.
 /// So here we don't care about tags, categories etc.
 /// We also ignore the rating of an entry for now.
-pub fn global_search(index: &EntryIndex, txt: &str, limit: usize) -> Result<Vec<IndexedEntry>> {
+pub fn global_search(
+    index: &EntryIndex,
+    txt: &str,
+    limit: usize,
+    highlight: bool,
+) -> Result<Vec<IndexedEntry>> {
     let index_query = EntryIndexQuery {
         text: Some(txt.into()),
+        highlight,
         ..Default::default()
     };
 
@@ -0,0 +1,20 @@
+use crate::core::{prelude::*, util::geo::MapBbox};
+
+pub fn suggest(
+    index: &EntryIndex,
+    kind: SuggestionKind,
+    prefix: &str,
+    bbox: Option<MapBbox>,
+    limit: usize,
+) -> Result<Vec<(String, usize)>> {
+    let prefix = prefix.trim();
+    let prefix = match kind {
+        // Tags might be typed as hash tags
+        SuggestionKind::Tag => prefix.trim_start_matches('#'),
+        SuggestionKind::Title => prefix,
+    };
+    let suggestions = index
+        .suggest(kind, prefix, bbox.as_ref(), limit)
+        .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+    Ok(suggestions)
+}
@@ -100,14 +100,45 @@ impl EntryIndexer for MockDb {
     fn flush(&mut self) -> Fallible<()> {
         Ok(())
     }
+
+    fn flush_with_high_water_mark(&mut self, _high_water_mark: Timestamp) -> Fallible<()> {
+        Ok(())
+    }
+
+    fn high_water_mark(&self) -> Fallible<Option<Timestamp>> {
+        Ok(None)
+    }
 }
 
 impl EntryIndex for MockDb {
-    fn query_entries(
+    fn query_entries_page(
         &self,
         _query: &EntryIndexQuery,
+        _offset: usize,
         _limit: usize,
-    ) -> Fallible<Vec<IndexedEntry>> {
+    ) -> Fallible<IndexedEntriesPage> {
+        unimplemented!();
+    }
+
+    fn query_facets(&self, _query: &EntryIndexQuery, _max_tags: usize) -> Fallible<EntryFacets> {
+        unimplemented!();
+    }
+
+    fn query_clusters(
+        &self,
+        _query: &EntryIndexQuery,
+        _grid: &ClusterGrid,
+    ) -> Fallible<Vec<EntryCluster>> {
+        unimplemented!();
+    }
+
+    fn suggest(
+        &self,
+        _kind: SuggestionKind,
+        _prefix: &str,
+        _bbox: Option<&geo::MapBbox>,
+        _limit: usize,
+    ) -> Fallible<Vec<(String, usize)>> {
         unimplemented!();
     }
 }
@@ -168,6 +199,10 @@ impl EntryGateway for MockDb {
             .cloned()
             .collect())
     }
+    fn get_entry_history(&self, id: &str) -> RepoResult<Vec<Entry>> {
+        // Only the latest version is stored
+        get(&self.entries.borrow(), id).map(|e| vec![e])
+    }
     fn all_entries(&self) -> RepoResult<Vec<Entry>> {
         Ok(self
             .entries
@@ -177,10 +212,38 @@ impl EntryGateway for MockDb {
             .cloned()
             .collect())
     }
+    fn all_entries_as_of(&self, _as_of: Timestamp) -> RepoResult<Vec<Entry>> {
+        unimplemented!();
+    }
     fn count_entries(&self) -> RepoResult<usize> {
         self.all_entries().map(|v| v.len())
     }
 
+    fn all_entries_chunk(&self, after_id: Option<&str>, limit: usize) -> RepoResult<Vec<Entry>> {
+        let mut entries: Vec<_> = self
+            .entries
+            .borrow()
+            .iter()
+            .filter(|e| {
+                e.archived.is_none() && after_id.map(|id| e.id.as_str() > id).unwrap_or(true)
+            })
+            .cloned()
+            .collect();
+        entries.sort_by(|lhs, rhs| lhs.id.cmp(&rhs.id));
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    fn changed_entry_ids_since(&self, since: Timestamp) -> RepoResult<Vec<String>> {
+        Ok(self
+            .entries
+            .borrow()
+            .iter()
+            .filter(|e| e.created >= since || e.archived.map(|t| t >= since).unwrap_or(false))
+            .map(|e| e.id.clone())
+            .collect())
+    }
+
     fn update_entry(&self, e: &Entry) -> RepoResult<()> {
         update(&mut self.entries.borrow_mut(), e)
     }
@@ -192,6 +255,14 @@ impl EntryGateway for MockDb {
     fn archive_entries(&self, _ids: &[&str], _archived: Timestamp) -> RepoResult<usize> {
         unimplemented!();
     }
+
+    fn create_entry_revert(&self, _revert: &EntryRevert) -> RepoResult<()> {
+        unimplemented!();
+    }
+
+    fn get_entry_reverts(&self, _entry_id: &str) -> RepoResult<Vec<EntryRevert>> {
+        Ok(vec![])
+    }
 }
 
 impl EventGateway for MockDb {
@@ -209,6 +280,16 @@ impl EventGateway for MockDb {
         })
     }
 
+    fn get_events(&self, ids: &[&str]) -> RepoResult<Vec<Event>> {
+        Ok(self
+            .events
+            .borrow()
+            .iter()
+            .filter(|e| e.archived.is_none() && ids.iter().any(|id| &e.id == id))
+            .cloned()
+            .collect())
+    }
+
     fn all_events(&self) -> RepoResult<Vec<Event>> {
         Ok(self
             .events
@@ -398,9 +479,25 @@ impl RatingRepository for MockDb {
             .collect())
     }
 
+    fn load_ratings_as_of(&self, as_of: Timestamp) -> RepoResult<Vec<Rating>> {
+        Ok(self
+            .ratings
+            .borrow()
+            .iter()
+            .filter(|r| r.created <= as_of && r.archived.map(|a| a > as_of).unwrap_or(true))
+            .cloned()
+            .collect())
+    }
+
     fn load_entry_ids_of_ratings(&self, _ids: &[&str]) -> RepoResult<Vec<String>> {
         unimplemented!();
     }
+    fn load_entry_ids_of_ratings_changed_since(
+        &self,
+        _since: Timestamp,
+    ) -> RepoResult<Vec<String>> {
+        unimplemented!();
+    }
     fn archive_ratings(&self, _ids: &[&str], _archived: Timestamp) -> RepoResult<usize> {
         unimplemented!();
     }
@@ -723,6 +820,8 @@ mod tests {
             registration: None,
             organizer: None,
             archived: None,
+            rrule: None,
+            exdates: vec![],
         })
         .unwrap();
         let e = usecases::get_event(&mut db, "x").unwrap();
@@ -1,6 +1,6 @@
 use crate::core::{
     prelude::*,
-    util::{parse::parse_url_param, validate::Validate},
+    util::{opening_hours::OpeningHours, parse::parse_url_param, validate::Validate},
 };
 
 #[rustfmt::skip]
@@ -23,6 +23,7 @@ pub struct UpdateEntry {
     pub tags           : Vec<String>,
     pub image_url      : Option<String>,
     pub image_link_url : Option<String>,
+    pub opening_hours  : Option<String>,
 }
 
 pub struct Storable(Entry);
@@ -93,6 +94,10 @@ pub fn prepare_updated_entry<D: Db>(db: &D, id: String, e: UpdateEntry) -> Resul
             .image_link_url
             .map(|ref url| parse_url_param(url))
             .transpose()?,
+        opening_hours: e
+            .opening_hours
+            .map(|ref oh| oh.parse::<OpeningHours>())
+            .transpose()?,
     };
     e.validate()?;
     Ok(Storable(e))
@@ -148,6 +153,7 @@ mod tests {
             tags        : vec![],
             image_url     : Some("img2".into()),
             image_link_url: old.image_link_url.clone(),
+            opening_hours : Some("Mo-Sa 09:00-19:00".into()),
         };
         let mut mock_db = MockDb::default();
         mock_db.entries = vec![old].into();
@@ -173,6 +179,10 @@ mod tests {
         assert!(Uuid::parse_str(&x.id).is_ok());
         assert_eq!("https://www.img2/", x.image_url.as_ref().unwrap());
         assert_eq!("http://imglink/", x.image_link_url.as_ref().unwrap());
+        assert_eq!(
+            "Mo-Sa 09:00-19:00",
+            x.opening_hours.as_ref().unwrap().as_str()
+        );
     }
 
     #[test]
@@ -204,6 +214,7 @@ mod tests {
             tags        : vec![],
             image_url     : None,
             image_link_url: None,
+            opening_hours : None,
         };
         let mut mock_db = MockDb::default();
         mock_db.entries = vec![old].into();
@@ -245,6 +256,7 @@ mod tests {
             tags        : vec![],
             image_url     : None,
             image_link_url: None,
+            opening_hours : None,
         };
         let mut mock_db = MockDb::default();
         mock_db.entries = vec![].into();
@@ -292,6 +304,7 @@ mod tests {
             tags        : vec!["vegan".into()],
             image_url     : None,
             image_link_url: None,
+            opening_hours : None,
         };
         let mut mock_db = MockDb::default();
         mock_db.entries = vec![old].into();
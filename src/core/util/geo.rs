@@ -401,6 +401,47 @@ impl MapBbox {
         self.sw.lat() >= self.ne.lat() || self.sw.lng() == self.ne.lng()
     }
 
+    /// The smallest bounding box that contains all points
+    /// within the great-circle distance around the center.
+    /// Reference: http://janmatuschek.de/LatitudeLongitudeBoundingCoordinates
+    pub fn centered_around(center: MapPoint, radius: Distance) -> Self {
+        debug_assert!(center.is_valid());
+        debug_assert!(radius.is_valid());
+        let (lat_rad, lng_rad) = center.to_lat_lng_rad();
+        let angular_radius = radius.to_meters() / MEAN_EARTH_RADIUS.to_meters();
+        let lat_min = (lat_rad - angular_radius).to_degrees();
+        let lat_max = (lat_rad + angular_radius).to_degrees();
+        if lat_min <= LatCoord::DEG_MIN || lat_max >= LatCoord::DEG_MAX {
+            // The circle covers a pole and all longitudes
+            return Self::new(
+                MapPoint::from_lat_lng_deg(lat_min.max(LatCoord::DEG_MIN), LngCoord::DEG_MIN),
+                MapPoint::from_lat_lng_deg(lat_max.min(LatCoord::DEG_MAX), LngCoord::DEG_MAX),
+            );
+        }
+        let delta_lng_sin = angular_radius.sin() / lat_rad.cos();
+        if delta_lng_sin >= 1.0 {
+            return Self::new(
+                MapPoint::from_lat_lng_deg(lat_min, LngCoord::DEG_MIN),
+                MapPoint::from_lat_lng_deg(lat_max, LngCoord::DEG_MAX),
+            );
+        }
+        let delta_lng = delta_lng_sin.asin();
+        let mut lng_min = (lng_rad - delta_lng).to_degrees();
+        if lng_min < LngCoord::DEG_MIN {
+            lng_min += 360.0;
+        }
+        let mut lng_max = (lng_rad + delta_lng).to_degrees();
+        if lng_max > LngCoord::DEG_MAX {
+            lng_max -= 360.0;
+        }
+        // The bounding box wraps around the antimeridian
+        // if lng_min > lng_max
+        Self::new(
+            MapPoint::from_lat_lng_deg(lat_min, lng_min),
+            MapPoint::from_lat_lng_deg(lat_max, lng_max),
+        )
+    }
+
     pub fn contains_point(&self, pt: MapPoint) -> bool {
         debug_assert!(self.is_valid());
         debug_assert!(pt.is_valid());
@@ -443,6 +484,105 @@ impl std::str::FromStr for MapBbox {
     }
 }
 
+/// A simple polygon on a (flat) map with an exterior ring and
+/// optional interior rings (holes). Rings are implicitly closed,
+/// i.e. the last point is connected with the first point.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapPolygon {
+    exterior: Vec<MapPoint>,
+    holes: Vec<Vec<MapPoint>>,
+}
+
+fn is_valid_ring(ring: &[MapPoint]) -> bool {
+    ring.len() >= 3 && ring.iter().all(|pt| pt.is_valid())
+}
+
+fn bbox_of_points<'a>(points: impl Iterator<Item = &'a MapPoint>) -> MapBbox {
+    let (mut lat_min, mut lng_min) = (RAW_COORD_MAX, RAW_COORD_MAX);
+    let (mut lat_max, mut lng_max) = (RAW_COORD_MIN, RAW_COORD_MIN);
+    for pt in points {
+        debug_assert!(pt.is_valid());
+        lat_min = lat_min.min(pt.lat().to_raw());
+        lat_max = lat_max.max(pt.lat().to_raw());
+        lng_min = lng_min.min(pt.lng().to_raw());
+        lng_max = lng_max.max(pt.lng().to_raw());
+    }
+    MapBbox::new(
+        MapPoint::new(LatCoord::from_raw(lat_min), LngCoord::from_raw(lng_min)),
+        MapPoint::new(LatCoord::from_raw(lat_max), LngCoord::from_raw(lng_max)),
+    )
+}
+
+/// Point-in-polygon test using the even-odd rule (ray casting).
+fn ring_contains_point(ring: &[MapPoint], pt: MapPoint) -> bool {
+    let (lat, lng) = pt.to_lat_lng_deg();
+    let mut inside = false;
+    let mut prev = match ring.last() {
+        Some(last) => last.to_lat_lng_deg(),
+        None => return false,
+    };
+    for next in ring.iter().map(|pt| pt.to_lat_lng_deg()) {
+        let ((lat1, lng1), (lat2, lng2)) = (prev, next);
+        if (lat1 > lat) != (lat2 > lat)
+            && lng < (lng2 - lng1) * (lat - lat1) / (lat2 - lat1) + lng1
+        {
+            inside = !inside;
+        }
+        prev = next;
+    }
+    inside
+}
+
+impl MapPolygon {
+    pub fn new(exterior: Vec<MapPoint>, holes: Vec<Vec<MapPoint>>) -> Self {
+        Self { exterior, holes }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        is_valid_ring(&self.exterior) && self.holes.iter().all(|hole| is_valid_ring(hole))
+    }
+
+    /// The smallest bounding box that contains all points of the
+    /// exterior ring. Polygons that cross the antimeridian are not
+    /// supported and must be split, as required by GeoJSON.
+    pub fn bbox(&self) -> MapBbox {
+        debug_assert!(self.is_valid());
+        bbox_of_points(self.exterior.iter())
+    }
+
+    pub fn contains_point(&self, pt: MapPoint) -> bool {
+        debug_assert!(self.is_valid());
+        debug_assert!(pt.is_valid());
+        ring_contains_point(&self.exterior, pt)
+            && !self.holes.iter().any(|hole| ring_contains_point(hole, pt))
+    }
+}
+
+/// An area on a (flat) map that consists of one
+/// or more disjoint polygons.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MapMultiPolygon(Vec<MapPolygon>);
+
+impl MapMultiPolygon {
+    pub fn new(polygons: Vec<MapPolygon>) -> Self {
+        MapMultiPolygon(polygons)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        !self.0.is_empty() && self.0.iter().all(MapPolygon::is_valid)
+    }
+
+    /// The smallest bounding box that contains all polygons.
+    pub fn bbox(&self) -> MapBbox {
+        debug_assert!(self.is_valid());
+        bbox_of_points(self.0.iter().flat_map(|polygon| polygon.exterior.iter()))
+    }
+
+    pub fn contains_point(&self, pt: MapPoint) -> bool {
+        self.0.iter().any(|polygon| polygon.contains_point(pt))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -653,6 +793,91 @@ mod tests {
         assert!(bbox4.contains_point(MapPoint::from_lat_lng_deg(lat4, lng4)));
     }
 
+    #[test]
+    fn bbox_centered_around_point() {
+        let stuttgart = MapPoint::from_lat_lng_deg(48.7755, 9.1827);
+        let radius = Distance::from_meters(10_000.0);
+        let bbox = MapBbox::centered_around(stuttgart, radius);
+        assert!(bbox.is_valid());
+        assert!(bbox.contains_point(stuttgart));
+        let inside = &[(48.86, 9.1827), (48.69, 9.1827), (48.7755, 9.31), (48.7755, 9.05)];
+        for &(lat, lng) in inside {
+            let pt = MapPoint::from_lat_lng_deg(lat, lng);
+            assert!(MapPoint::distance(stuttgart, pt).unwrap() < radius);
+            assert!(bbox.contains_point(pt));
+        }
+        assert!(!bbox.contains_point(MapPoint::from_lat_lng_deg(48.9, 9.1827)));
+        assert!(!bbox.contains_point(MapPoint::from_lat_lng_deg(48.7755, 9.35)));
+
+        // Wrap around the antimeridian
+        let bbox = MapBbox::centered_around(MapPoint::from_lat_lng_deg(0.0, 179.99), radius);
+        assert!(bbox.south_west().lng() > bbox.north_east().lng());
+        assert!(bbox.contains_point(MapPoint::from_lat_lng_deg(0.0, -179.99)));
+
+        // Covering the north pole
+        let bbox = MapBbox::centered_around(MapPoint::from_lat_lng_deg(89.99, 0.0), radius);
+        assert!(bbox.contains_point(MapPoint::from_lat_lng_deg(89.99, 180.0)));
+    }
+
+    fn map_points(lat_lng_degs: &[(f64, f64)]) -> Vec<MapPoint> {
+        lat_lng_degs
+            .iter()
+            .map(|&(lat, lng)| MapPoint::from_lat_lng_deg(lat, lng))
+            .collect()
+    }
+
+    #[test]
+    fn polygon_contains_point() {
+        // A square with a square hole
+        let polygon = MapPolygon::new(
+            map_points(&[(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0), (0.0, 0.0)]),
+            vec![map_points(&[(4.0, 4.0), (4.0, 6.0), (6.0, 6.0), (6.0, 4.0)])],
+        );
+        assert!(polygon.is_valid());
+        assert!(polygon.contains_point(MapPoint::from_lat_lng_deg(1.0, 1.0)));
+        assert!(polygon.contains_point(MapPoint::from_lat_lng_deg(9.0, 5.0)));
+        assert!(!polygon.contains_point(MapPoint::from_lat_lng_deg(5.0, 5.0)));
+        assert!(!polygon.contains_point(MapPoint::from_lat_lng_deg(11.0, 5.0)));
+        assert!(!polygon.contains_point(MapPoint::from_lat_lng_deg(5.0, -1.0)));
+        assert_eq!(
+            MapBbox::new(
+                MapPoint::from_lat_lng_deg(0.0, 0.0),
+                MapPoint::from_lat_lng_deg(10.0, 10.0)
+            ),
+            polygon.bbox()
+        );
+
+        // A concave polygon with a notch
+        let polygon = MapPolygon::new(
+            map_points(&[(0.0, 0.0), (10.0, 5.0), (0.0, 10.0), (5.0, 5.0)]),
+            vec![],
+        );
+        assert!(polygon.contains_point(MapPoint::from_lat_lng_deg(4.0, 3.0)));
+        assert!(!polygon.contains_point(MapPoint::from_lat_lng_deg(2.0, 5.0)));
+
+        assert!(!MapPolygon::new(map_points(&[(0.0, 0.0), (1.0, 1.0)]), vec![]).is_valid());
+    }
+
+    #[test]
+    fn multi_polygon_contains_point() {
+        let area = MapMultiPolygon::new(vec![
+            MapPolygon::new(map_points(&[(0.0, 0.0), (0.0, 1.0), (1.0, 1.0)]), vec![]),
+            MapPolygon::new(map_points(&[(-5.0, 5.0), (-5.0, 6.0), (-4.0, 6.0)]), vec![]),
+        ]);
+        assert!(area.is_valid());
+        assert!(area.contains_point(MapPoint::from_lat_lng_deg(0.2, 0.8)));
+        assert!(area.contains_point(MapPoint::from_lat_lng_deg(-4.8, 5.8)));
+        assert!(!area.contains_point(MapPoint::from_lat_lng_deg(-2.0, 3.0)));
+        assert_eq!(
+            MapBbox::new(
+                MapPoint::from_lat_lng_deg(-5.0, 0.0),
+                MapPoint::from_lat_lng_deg(1.0, 6.0)
+            ),
+            area.bbox()
+        );
+        assert!(!MapMultiPolygon::default().is_valid());
+    }
+
     use crate::test::Bencher;
 
     fn random_map_point<T: rand::Rng>(rng: &mut T) -> MapPoint {
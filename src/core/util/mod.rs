@@ -1,7 +1,10 @@
 pub mod filter;
 pub mod geo;
+pub mod opening_hours;
 pub mod parse;
 pub mod password;
+pub mod query;
+pub mod recurrence;
 pub mod sort;
 pub mod time;
 pub mod validate;
@@ -0,0 +1,644 @@
+use crate::core::error::ParameterError;
+
+use chrono::{Datelike, NaiveDateTime, Timelike, Weekday};
+use std::{fmt, str::FromStr};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// The length of the slots of the week in minutes
+/// that are used for indexing opening hours.
+const SLOT_MINUTES: u32 = 5;
+
+const SLOTS_PER_HOUR: u32 = 60 / SLOT_MINUTES;
+
+/// The periods of the week are numbered consecutively, starting
+/// with the days from Monday, followed by the hours and the slots
+/// of the week.
+const FIRST_HOUR_PERIOD: u32 = 7;
+const FIRST_SLOT_PERIOD: u32 = FIRST_HOUR_PERIOD + 7 * 24;
+
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Keywords of the full syntax besides weekdays and months
+const KEYWORDS: [&str; 13] = [
+    "PH", "SH", "easter", "day", "days", "sunrise", "sunset", "dawn", "dusk", "open", "off",
+    "closed", "unknown",
+];
+
+/// Time spans in minutes since midnight. The end of spans
+/// that extend into the next day exceeds `MINUTES_PER_DAY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TimeSpan {
+    start: u32,
+    end: u32,
+}
+
+impl TimeSpan {
+    const ALL_DAY: TimeSpan = TimeSpan {
+        start: 0,
+        end: MINUTES_PER_DAY,
+    };
+
+    fn contains(self, minute: u32) -> bool {
+        self.start <= minute && minute < self.end
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    /// Indexed by the number of days from Monday,
+    /// `None` if the rule applies to every day
+    weekdays: Option<[bool; 7]>,
+    spans: Vec<TimeSpan>,
+    closed: bool,
+}
+
+impl Rule {
+    fn applies_to(&self, day: Weekday) -> bool {
+        self.weekdays
+            .map(|days| days[day.num_days_from_monday() as usize])
+            .unwrap_or(true)
+    }
+}
+
+/// Opening hours in the syntax of OpenStreetMap:
+/// <https://wiki.openstreetmap.org/wiki/Key:opening_hours>
+///
+/// Only the commonly used subset can be evaluated, i.e. `24/7` and
+/// rules like `Mo-Fr 08:00-12:00,13:00-18:00; Sa 10:00-14:00; Su off`.
+/// Rules are separated by `;` and later rules override earlier
+/// rules for the same weekdays. Public holidays (`PH`) are
+/// accepted, but unknown and therefore never apply.
+///
+/// Other values that only consist of the tokens of the full
+/// syntax, e.g. month ranges, `sunrise-sunset`, `Mo[1]`, open
+/// ends like `10:00+`, or quoted comments, are accepted as well.
+/// Whether they are open at a certain time is unknown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpeningHours {
+    text: String,
+    /// `None` if the opening hours cannot be evaluated
+    rules: Option<Vec<Rule>>,
+}
+
+impl OpeningHours {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Opening hours that use the full syntax cannot be evaluated.
+    pub fn is_supported(&self) -> bool {
+        self.rules.is_some()
+    }
+
+    /// Checks if the opening hours include the given local time.
+    /// Unsupported opening hours are never considered as open.
+    pub fn is_open_at(&self, at: NaiveDateTime) -> bool {
+        self.is_open_at_minute(at.weekday(), at.hour() * 60 + at.minute())
+    }
+
+    /// The periods of the week (see `periods_of_week`) that are
+    /// open at the start of all their slots. Whole days and hours
+    /// are preferred over single slots to keep the number of
+    /// periods small. Empty for unsupported opening hours.
+    pub fn open_periods_of_week(&self) -> Vec<u32> {
+        let mut periods = Vec::new();
+        if !self.is_supported() {
+            return periods;
+        }
+        let mut day = Weekday::Mon;
+        for days_from_monday in 0..7 {
+            let open_slots: Vec<_> = (0..MINUTES_PER_DAY)
+                .step_by(SLOT_MINUTES as usize)
+                .map(|minute| self.is_open_at_minute(day, minute))
+                .collect();
+            if open_slots.iter().all(|open| *open) {
+                periods.push(days_from_monday);
+            } else {
+                for (hour, hour_slots) in open_slots.chunks(SLOTS_PER_HOUR as usize).enumerate() {
+                    let hour_of_week = days_from_monday * 24 + hour as u32;
+                    if hour_slots.iter().all(|open| *open) {
+                        periods.push(FIRST_HOUR_PERIOD + hour_of_week);
+                        continue;
+                    }
+                    let first_slot = FIRST_SLOT_PERIOD + hour_of_week * SLOTS_PER_HOUR;
+                    for (slot, open) in hour_slots.iter().enumerate() {
+                        if *open {
+                            periods.push(first_slot + slot as u32);
+                        }
+                    }
+                }
+            }
+            day = day.succ();
+        }
+        periods
+    }
+
+    fn is_open_at_minute(&self, day: Weekday, minute: u32) -> bool {
+        // Spans that started on the previous day might extend
+        // into the requested day
+        self.is_open_on(day, minute) || self.is_open_on(day.pred(), minute + MINUTES_PER_DAY)
+    }
+
+    fn is_open_on(&self, day: Weekday, minute: u32) -> bool {
+        self.rules
+            .iter()
+            .flatten()
+            .filter(|rule| rule.applies_to(day))
+            .fold(false, |open, rule| {
+                let contained = rule.spans.iter().any(|span| span.contains(minute));
+                if rule.closed {
+                    // Closed rules without time spans close the whole day
+                    open && !(rule.spans.is_empty() || contained)
+                } else {
+                    contained
+                }
+            })
+    }
+}
+
+/// The periods of the week that contain the given local time,
+/// i.e. the day, the hour, and the slot counted from Monday at
+/// midnight.
+pub fn periods_of_week(at: NaiveDateTime) -> [u32; 3] {
+    let day = at.weekday().num_days_from_monday();
+    let hour = day * 24 + at.hour();
+    let slot = hour * SLOTS_PER_HOUR + at.minute() / SLOT_MINUTES;
+    [day, FIRST_HOUR_PERIOD + hour, FIRST_SLOT_PERIOD + slot]
+}
+
+fn parse_weekday(s: &str) -> Option<usize> {
+    WEEKDAYS.iter().position(|day| *day == s)
+}
+
+fn parse_weekdays(s: &str) -> Result<[bool; 7], ParameterError> {
+    let mut days = [false; 7];
+    for item in s.split(',') {
+        if item == "PH" {
+            continue;
+        }
+        let mut range = item.splitn(2, '-');
+        let first = range
+            .next()
+            .and_then(parse_weekday)
+            .ok_or(ParameterError::OpeningHours)?;
+        let last = match range.next() {
+            Some(last) => parse_weekday(last).ok_or(ParameterError::OpeningHours)?,
+            None => first,
+        };
+        // Ranges like `Sa-Mo` wrap around the end of the week
+        let mut day = first;
+        loop {
+            days[day] = true;
+            if day == last {
+                break;
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    Ok(days)
+}
+
+/// Times beyond 24:00 are allowed for the end of time spans.
+fn parse_time(s: &str, max_time: u32) -> Result<u32, ParameterError> {
+    let mut parts = s.splitn(2, ':');
+    let (hours, minutes) = match (parts.next(), parts.next()) {
+        (Some(hours), Some(minutes)) if hours.len() == 2 && minutes.len() == 2 => (
+            hours.parse::<u32>().map_err(|_| ParameterError::OpeningHours)?,
+            minutes.parse::<u32>().map_err(|_| ParameterError::OpeningHours)?,
+        ),
+        _ => return Err(ParameterError::OpeningHours),
+    };
+    let time = hours * 60 + minutes;
+    if minutes >= 60 || time > max_time {
+        return Err(ParameterError::OpeningHours);
+    }
+    Ok(time)
+}
+
+fn parse_time_span(s: &str) -> Result<TimeSpan, ParameterError> {
+    let mut parts = s.splitn(2, '-');
+    let (start, end) = match (parts.next(), parts.next()) {
+        (Some(start), Some(end)) => (
+            parse_time(start, MINUTES_PER_DAY - 1)?,
+            parse_time(end, 2 * MINUTES_PER_DAY)?,
+        ),
+        _ => return Err(ParameterError::OpeningHours),
+    };
+    if start == end {
+        return Err(ParameterError::OpeningHours);
+    }
+    let end = if end < start {
+        // Extends into the next day, e.g. `22:00-02:00`
+        end + MINUTES_PER_DAY
+    } else {
+        end
+    };
+    if end - start > MINUTES_PER_DAY {
+        return Err(ParameterError::OpeningHours);
+    }
+    Ok(TimeSpan { start, end })
+}
+
+fn parse_rule(s: &str) -> Result<Rule, ParameterError> {
+    // Ignore whitespace around list and range separators,
+    // e.g. `Mo, We` or `08:00 - 18:00`
+    let s = s.split(',').map(str::trim).collect::<Vec<_>>().join(",");
+    let s = s.split('-').map(str::trim).collect::<Vec<_>>().join("-");
+    let mut tokens = s.split_whitespace().peekable();
+    let mut rule = Rule {
+        weekdays: None,
+        spans: vec![],
+        closed: false,
+    };
+    if tokens.peek() == Some(&"24/7") {
+        tokens.next();
+        rule.spans.push(TimeSpan::ALL_DAY);
+    } else {
+        if let Some(token) = tokens.peek() {
+            let is_modifier = ["open", "off", "closed"].contains(token);
+            if !is_modifier && token.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                rule.weekdays = Some(parse_weekdays(token)?);
+                tokens.next();
+            }
+        }
+        if let Some(token) = tokens.peek() {
+            if token.starts_with(|c: char| c.is_ascii_digit()) {
+                for span in token.split(',') {
+                    rule.spans.push(parse_time_span(span)?);
+                }
+                tokens.next();
+            }
+        }
+    }
+    match tokens.next() {
+        Some("off") | Some("closed") => {
+            rule.closed = true;
+        }
+        Some("open") | None => {
+            if rule.weekdays.is_none() && rule.spans.is_empty() {
+                // Neither weekdays nor time spans
+                return Err(ParameterError::OpeningHours);
+            }
+            if rule.spans.is_empty() {
+                rule.spans.push(TimeSpan::ALL_DAY);
+            }
+        }
+        Some(_) => return Err(ParameterError::OpeningHours),
+    }
+    if tokens.next().is_some() {
+        return Err(ParameterError::OpeningHours);
+    }
+    Ok(rule)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Word(&'a str),
+    Number(&'a str),
+    /// Minutes since midnight
+    Time(u32),
+    Comment,
+    Symbol(char),
+}
+
+fn tokenize(s: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with("24/7") {
+            tokens.push(Token::Word(&rest[..4]));
+            4
+        } else if c == '"' {
+            // Comments must not contain any quotes
+            let len = rest[1..].find('"')? + 2;
+            tokens.push(Token::Comment);
+            len
+        } else if c.is_ascii_alphabetic() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(&rest[..len]));
+            len
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let number = &rest[..len];
+            let minutes = &rest[len..];
+            if minutes.starts_with(':') {
+                let len = len + 3;
+                let time = rest.get(..len)?;
+                tokens.push(Token::Time(parse_time(time, 2 * MINUTES_PER_DAY).ok()?));
+                len
+            } else {
+                tokens.push(Token::Number(number));
+                len
+            }
+        } else if "-,;:[]()+/|".contains(c) {
+            tokens.push(Token::Symbol(c));
+            1
+        } else {
+            return None;
+        };
+        rest = rest[len..].trim_start();
+    }
+    Some(tokens)
+}
+
+/// Checks that the opening hours only consist of the tokens
+/// of the full syntax without evaluating them.
+fn is_valid_syntax(s: &str) -> bool {
+    let tokens = match tokenize(s) {
+        Some(tokens) => tokens,
+        None => return false,
+    };
+    let has_content = tokens.iter().any(|token| match token {
+        Token::Word(_) | Token::Number(_) | Token::Time(_) => true,
+        _ => false,
+    });
+    if !has_content {
+        return false;
+    }
+    // Plain numbers are only allowed for years and within
+    // dates, weeks, and ordinals, e.g. `Dec 24-26`, `week 01-10`,
+    // or `Mo[1]`
+    let mut numbers_allowed = false;
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = i.checked_sub(1).and_then(|i| tokens.get(i));
+        let next = tokens.get(i + 1);
+        match *token {
+            Token::Word(word) => {
+                if MONTHS.contains(&word) || word == "week" {
+                    numbers_allowed = true;
+                } else if parse_weekday(word).is_some()
+                    || KEYWORDS.contains(&word)
+                    || word == "24/7"
+                {
+                    numbers_allowed = false;
+                } else {
+                    return false;
+                }
+            }
+            Token::Number(number) => {
+                if !numbers_allowed && number.len() != 4 {
+                    return false;
+                }
+            }
+            Token::Time(time) => {
+                numbers_allowed = false;
+                match (prev, next) {
+                    // The end of a span or an offset,
+                    // e.g. `(sunrise+01:00)`
+                    (Some(Token::Symbol('-')), _) | (Some(Token::Symbol('+')), _) => {
+                        if let (Some(Token::Symbol('-')), Some(Token::Time(start))) =
+                            (prev, i.checked_sub(2).and_then(|i| tokens.get(i)))
+                        {
+                            if *start == time {
+                                return false;
+                            }
+                        }
+                    }
+                    // The start of a span
+                    (_, Some(Token::Symbol('-'))) => {
+                        if time >= MINUTES_PER_DAY {
+                            return false;
+                        }
+                    }
+                    // An open end, e.g. `10:00+`
+                    (_, Some(Token::Symbol('+'))) => {}
+                    _ => return false,
+                }
+            }
+            Token::Symbol('[') => {
+                numbers_allowed = true;
+            }
+            Token::Symbol(']') | Token::Symbol(';') | Token::Symbol('|') => {
+                numbers_allowed = false;
+            }
+            Token::Symbol(_) | Token::Comment => {}
+        }
+    }
+    true
+}
+
+impl FromStr for OpeningHours {
+    type Err = ParameterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        let rules = text
+            .split(';')
+            .map(str::trim)
+            // Tolerate a trailing separator
+            .filter(|rule| !rule.is_empty())
+            .map(parse_rule)
+            .collect::<Result<Vec<_>, _>>();
+        let rules = match rules {
+            Ok(rules) if !rules.is_empty() => Some(rules),
+            _ if is_valid_syntax(text) => None,
+            _ => return Err(ParameterError::OpeningHours),
+        };
+        Ok(Self {
+            text: text.to_string(),
+            rules,
+        })
+    }
+}
+
+impl fmt::Display for OpeningHours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    // 2019-06-03 is a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2019, 6, day).and_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn parse_valid_opening_hours() {
+        for s in &[
+            "24/7",
+            "Mo-Fr 08:00-18:00",
+            "Mo-Fr 08:00-12:00,13:00-18:00; Sa 10:00-14:00; Su off",
+            "Mo, We 08:00-12:00, 14:00-18:00",
+            "Fr-Mo 20:00-02:00",
+            "Mo-Su 00:00-24:00; PH off",
+            "10:00-20:00",
+            "Sa-Su",
+            "Mo-Fr 09:00-17:00; We 12:00-13:00 off;",
+            "Mo-Fr 08:00 - 18:00",
+            "Mo - Fr 08:00 - 12:00 , 13:00 - 18:00",
+        ] {
+            let oh = s.parse::<OpeningHours>().unwrap();
+            assert!(oh.is_supported(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_unsupported_opening_hours() {
+        for s in &[
+            "sunrise-sunset",
+            "(sunrise+01:00)-sunset",
+            "Apr-Sep Mo-Fr 08:00-18:00; Oct-Mar Mo-Fr 09:00-17:00",
+            "Dec 24-26 off",
+            "2019 Jun 01-Aug 31 10:00-18:00",
+            "week 01-10 Mo 09:00-12:00",
+            "Sa[1] 10:00-14:00",
+            "Mo-Fr 10:00+",
+            "Mo-Fr 08:00-18:00 \"by appointment\"",
+            "24/7; PH off; easter off",
+            "Mo-Fr 08:00-18:00 || \"on request\"",
+        ] {
+            let oh = s.parse::<OpeningHours>().unwrap();
+            assert!(!oh.is_supported(), "{}", s);
+            assert_eq!(*s, oh.as_str());
+            assert!(!oh.is_open_at(at(3, 12, 0)), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_invalid_opening_hours() {
+        for s in &[
+            "",
+            ";",
+            "always",
+            "Mo-Fr 8-18",
+            "Mo-Fr 08:00",
+            "Mo-Fr 08:00-25:61",
+            "Xy 08:00-18:00",
+            "Mo-Fr 08:00-18:00 open please",
+            "24:00-08:00",
+            "10:00-10:00",
+            "Mo-Fr 8:00-18:00",
+            "Mo-Fr 08:00-18:00 \"unterminated",
+            "Jan-Mar Mo-Fr 8-18",
+        ] {
+            assert!(s.parse::<OpeningHours>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn keep_original_text() {
+        let s = "Mo-Fr 08:00-18:00; Sa 10:00-14:00";
+        let oh = format!(" {} ", s).parse::<OpeningHours>().unwrap();
+        assert_eq!(s, oh.as_str());
+        assert_eq!(s, oh.to_string());
+    }
+
+    #[test]
+    fn open_around_the_clock() {
+        let oh = "24/7".parse::<OpeningHours>().unwrap();
+        assert!(oh.is_open_at(at(3, 0, 0)));
+        assert!(oh.is_open_at(at(9, 23, 59)));
+    }
+
+    #[test]
+    fn open_on_weekdays_and_times() {
+        let oh = "Mo-Fr 08:00-12:00,13:00-18:00; Sa 10:00-14:00; Su off"
+            .parse::<OpeningHours>()
+            .unwrap();
+        assert!(!oh.is_open_at(at(3, 7, 59)));
+        assert!(oh.is_open_at(at(3, 8, 0)));
+        assert!(!oh.is_open_at(at(4, 12, 30)));
+        assert!(oh.is_open_at(at(7, 17, 59)));
+        assert!(!oh.is_open_at(at(7, 18, 0)));
+        assert!(oh.is_open_at(at(8, 10, 0)));
+        assert!(!oh.is_open_at(at(8, 15, 0)));
+        assert!(!oh.is_open_at(at(9, 10, 0)));
+    }
+
+    #[test]
+    fn open_with_spaced_time_spans() {
+        let oh = "Mo - Fr 08:00 - 12:00, 13:00 - 18:00"
+            .parse::<OpeningHours>()
+            .unwrap();
+        assert!(oh.is_open_at(at(3, 8, 0)));
+        assert!(!oh.is_open_at(at(3, 12, 30)));
+        assert!(!oh.is_open_at(at(8, 10, 0)));
+    }
+
+    #[test]
+    fn later_rules_override_earlier_rules() {
+        let oh = "Mo-Fr 08:00-18:00; We 10:00-12:00".parse::<OpeningHours>().unwrap();
+        assert!(oh.is_open_at(at(4, 9, 0)));
+        assert!(!oh.is_open_at(at(5, 9, 0)));
+        assert!(oh.is_open_at(at(5, 11, 0)));
+        let oh = "Mo-Fr 08:00-18:00; We 12:00-14:00 off".parse::<OpeningHours>().unwrap();
+        assert!(oh.is_open_at(at(5, 11, 0)));
+        assert!(!oh.is_open_at(at(5, 13, 0)));
+        assert!(oh.is_open_at(at(5, 15, 0)));
+        let oh = "Mo-Su 08:00-18:00; PH off".parse::<OpeningHours>().unwrap();
+        assert!(oh.is_open_at(at(9, 12, 0)));
+    }
+
+    #[test]
+    fn open_past_midnight() {
+        let oh = "Fr-Sa 20:00-02:00".parse::<OpeningHours>().unwrap();
+        assert!(oh.is_open_at(at(7, 23, 0)));
+        assert!(oh.is_open_at(at(8, 1, 59)));
+        assert!(!oh.is_open_at(at(8, 2, 0)));
+        assert!(oh.is_open_at(at(9, 1, 0)));
+        assert!(!oh.is_open_at(at(7, 1, 0)));
+    }
+
+    #[test]
+    fn open_periods_of_the_week() {
+        let oh = "Mo 08:00-08:10; Su 23:00-00:30".parse::<OpeningHours>().unwrap();
+        let mut expected: Vec<_> = (0..6).map(|slot| FIRST_SLOT_PERIOD + slot).collect();
+        expected.extend(vec![FIRST_SLOT_PERIOD + 96, FIRST_SLOT_PERIOD + 97]);
+        expected.push(FIRST_HOUR_PERIOD + 6 * 24 + 23);
+        assert_eq!(expected, oh.open_periods_of_week());
+        let oh = "24/7".parse::<OpeningHours>().unwrap();
+        assert_eq!((0..7).collect::<Vec<_>>(), oh.open_periods_of_week());
+        let oh = "Mo-Fr 08:00-18:00".parse::<OpeningHours>().unwrap();
+        assert_eq!(50, oh.open_periods_of_week().len());
+        assert_eq!(
+            [0, FIRST_HOUR_PERIOD + 8, FIRST_SLOT_PERIOD + 97],
+            periods_of_week(at(3, 8, 7))
+        );
+        assert_eq!(
+            [6, FIRST_HOUR_PERIOD + 167, FIRST_SLOT_PERIOD + 2015],
+            periods_of_week(at(9, 23, 59))
+        );
+        assert!("sunrise-sunset"
+            .parse::<OpeningHours>()
+            .unwrap()
+            .open_periods_of_week()
+            .is_empty());
+    }
+
+    #[test]
+    fn open_periods_match_opening_hours() {
+        let oh = "Mo-Fr 08:00-12:00,13:00-18:10; Sa 22:00-02:00; Su off"
+            .parse::<OpeningHours>()
+            .unwrap();
+        let periods = oh.open_periods_of_week();
+        for day in 3..10 {
+            for hour in 0..24 {
+                for minute in (0..60).step_by(SLOT_MINUTES as usize) {
+                    let time = at(day, hour, minute);
+                    assert_eq!(
+                        oh.is_open_at(time),
+                        periods_of_week(time)
+                            .iter()
+                            .any(|period| periods.contains(period)),
+                        "{}",
+                        time
+                    );
+                }
+            }
+        }
+    }
+}
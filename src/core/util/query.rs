@@ -0,0 +1,177 @@
+//! A small query language for the search text.
+//!
+//! - `#tag`: Only entries with this tag
+//! - `#a|#b`: Only entries with at least one of these tags
+//! - `-#tag`: Only entries without this tag
+//! - `"some phrase"`: Only entries that contain this phrase
+//! - `category:<id>`: Only entries of this category
+//! - `-category:<id>`: Only entries not of this category
+//!
+//! All remaining words are passed through as free text.
+
+use super::{extract_hash_tags, remove_hash_tags};
+
+const CATEGORY_PREFIX: &str = "category:";
+
+const TAG_ALTERNATIVES_SEPARATOR: char = '|';
+
+const EXCLUDE_PREFIX: char = '-';
+
+const PHRASE_DELIMITER: char = '"';
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchText {
+    /// Mandatory tags
+    pub hash_tags: Vec<String>,
+    /// Groups of alternative tags, i.e. at least one
+    /// tag of each group is mandatory
+    pub hash_tag_alternatives: Vec<Vec<String>>,
+    /// Tags that must not match
+    pub excluded_hash_tags: Vec<String>,
+    /// Mandatory phrases
+    pub phrases: Vec<String>,
+    /// Mandatory categories
+    pub categories: Vec<String>,
+    /// Categories that must not match
+    pub excluded_categories: Vec<String>,
+    /// The remaining text without any operators, might be empty
+    pub text: String,
+}
+
+fn is_word_separator(c: char) -> bool {
+    c.is_whitespace() || c == ','
+}
+
+/// Splits the text into words and (unquoted) phrases.
+fn split_words_and_phrases(text: &str) -> (Vec<&str>, Vec<&str>) {
+    let mut words = Vec::new();
+    let mut phrases = Vec::new();
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start_matches(is_word_separator);
+        if rest.is_empty() {
+            break;
+        }
+        if rest.starts_with(PHRASE_DELIMITER) {
+            rest = &rest[PHRASE_DELIMITER.len_utf8()..];
+            // An unterminated phrase extends until the end
+            let end = rest.find(PHRASE_DELIMITER).unwrap_or_else(|| rest.len());
+            let phrase = rest[..end].trim();
+            if !phrase.is_empty() {
+                phrases.push(phrase);
+            }
+            rest = &rest[(end + PHRASE_DELIMITER.len_utf8()).min(rest.len())..];
+        } else {
+            let end = rest
+                .find(|c| is_word_separator(c) || c == PHRASE_DELIMITER)
+                .unwrap_or_else(|| rest.len());
+            words.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+    (words, phrases)
+}
+
+/// Extracts the id from `category:<id>`, might be empty.
+fn strip_category_prefix(word: &str) -> Option<&str> {
+    word.get(..CATEGORY_PREFIX.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(CATEGORY_PREFIX))
+        .map(|_| word[CATEGORY_PREFIX.len()..].trim())
+}
+
+/// Extracts all tags from `#a|#b` (the hash of
+/// the alternatives is optional).
+fn extract_hash_tag_alternatives(word: &str) -> Vec<String> {
+    word.split(TAG_ALTERNATIVES_SEPARATOR)
+        .flat_map(|tag| extract_hash_tags(&format!("#{}", tag.trim_start_matches('#'))))
+        .collect()
+}
+
+pub fn parse_search_text(text: &str) -> SearchText {
+    let mut search_text = SearchText::default();
+    let (words, phrases) = split_words_and_phrases(text);
+    search_text.phrases = phrases.into_iter().map(Into::into).collect();
+    let mut text_words = Vec::with_capacity(words.len());
+    for word in words {
+        let excluded_word = if word.starts_with(EXCLUDE_PREFIX) {
+            Some(&word[EXCLUDE_PREFIX.len_utf8()..])
+        } else {
+            None
+        };
+        if word.starts_with(EXCLUDE_PREFIX) && word[1..].starts_with('#') {
+            search_text
+                .excluded_hash_tags
+                .extend(extract_hash_tag_alternatives(&word[1..]));
+        } else if word.starts_with('#') && word.contains(TAG_ALTERNATIVES_SEPARATOR) {
+            let mut tags = extract_hash_tag_alternatives(word);
+            match tags.len() {
+                0 => (),
+                1 => search_text.hash_tags.append(&mut tags),
+                _ => search_text.hash_tag_alternatives.push(tags),
+            }
+        } else if let Some(category) = strip_category_prefix(word) {
+            if !category.is_empty() {
+                search_text.categories.push(category.into());
+            }
+        } else if let Some(category) = excluded_word.and_then(strip_category_prefix) {
+            if !category.is_empty() {
+                search_text.excluded_categories.push(category.into());
+            }
+        } else if word.contains('#') {
+            // Tags might also be written without separators,
+            // e.g. "#foo#bar" or "foo#bar"
+            search_text.hash_tags.extend(extract_hash_tags(word));
+            let word = remove_hash_tags(word);
+            if !word.is_empty() {
+                text_words.push(word);
+            }
+        } else {
+            text_words.push(word.into());
+        }
+    }
+    search_text.text = text_words.join(" ");
+    search_text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_text_without_operators() {
+        assert_eq!(
+            SearchText {
+                text: "some text".into(),
+                ..Default::default()
+            },
+            parse_search_text(" some, text ")
+        );
+    }
+
+    #[test]
+    fn parse_hash_tags() {
+        let search_text = parse_search_text("#eins, #zwei foo#bar #a-b|#c|d -#no -#x|#y");
+        assert_eq!(vec!["eins", "zwei", "bar"], search_text.hash_tags);
+        assert_eq!(
+            vec![vec!["a-b".to_string(), "c".to_string(), "d".to_string()]],
+            search_text.hash_tag_alternatives
+        );
+        assert_eq!(vec!["no", "x", "y"], search_text.excluded_hash_tags);
+        assert_eq!("foo", search_text.text);
+    }
+
+    #[test]
+    fn parse_phrases() {
+        let search_text = parse_search_text(r#"foo "bar baz"bla "" "unterminated "#);
+        assert_eq!(vec!["bar baz", "unterminated"], search_text.phrases);
+        assert_eq!("foo bla", search_text.text);
+    }
+
+    #[test]
+    fn parse_categories() {
+        let search_text = parse_search_text("Category:abc category: def -category:xyz -category:");
+        assert_eq!(vec!["abc"], search_text.categories);
+        assert_eq!(vec!["xyz"], search_text.excluded_categories);
+        assert_eq!("def", search_text.text);
+    }
+}
@@ -0,0 +1,602 @@
+use crate::core::error::ParameterError;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use std::{fmt, str::FromStr};
+
+/// Protects against rules that never produce any
+/// further occurrence, e.g. the 31st of every
+/// 12th month starting in February. Only consecutive
+/// periods without any occurrence are counted.
+const MAX_EMPTY_PERIODS: u32 = 10_000;
+
+/// Rules with more occurrences are considered as
+/// never ending when looking for the last occurrence.
+const MAX_COUNT_OF_LAST_START: u32 = 100_000;
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A recurrence rule (`RRULE`) as defined by RFC 5545:
+/// <https://tools.ietf.org/html/rfc5545#section-3.3.10>
+///
+/// Only the rule parts `FREQ` (except for sub-daily frequencies),
+/// `INTERVAL`, `COUNT`, `UNTIL`, `BYDAY`, `BYMONTHDAY`, and
+/// `WKST=MO` are supported. Weekdays with an ordinal (`BYDAY=1SA`)
+/// and `BYMONTHDAY` are only supported for monthly rules.
+///
+/// All date-times are expected in UTC, i.e. the local time
+/// of recurring events might shift when the daylight saving
+/// time changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    text: String,
+    freq: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<NaiveDateTime>,
+    /// Weekdays with an optional ordinal within the month
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+}
+
+/// Parses an RFC 5545 date-time like `20190603T103000Z` or a date
+/// like `20190603`. The optional suffix `Z` for UTC is ignored.
+pub fn parse_date_time(s: &str) -> Result<NaiveDateTime, ParameterError> {
+    let s = s.trim();
+    let s = if s.ends_with('Z') { &s[..s.len() - 1] } else { s };
+    NaiveDateTime::parse_from_str(s, DATE_TIME_FORMAT)
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y%m%d").map(|d| d.and_hms(0, 0, 0)))
+        .map_err(|_| ParameterError::RecurrenceRule)
+}
+
+/// Formats a date-time like `20190603T103000`.
+pub fn format_date_time(dt: NaiveDateTime) -> String {
+    dt.format(DATE_TIME_FORMAT).to_string()
+}
+
+fn parse_weekday(s: &str) -> Result<Weekday, ParameterError> {
+    WEEKDAYS
+        .iter()
+        .find(|(name, _)| *name == s)
+        .map(|(_, weekday)| *weekday)
+        .ok_or(ParameterError::RecurrenceRule)
+}
+
+fn parse_by_day(s: &str) -> Result<(Option<i32>, Weekday), ParameterError> {
+    if s.len() < 2 {
+        return Err(ParameterError::RecurrenceRule);
+    }
+    let (ordinal, weekday) = s.split_at(s.len() - 2);
+    let ordinal = if ordinal.is_empty() {
+        None
+    } else {
+        let ordinal = ordinal
+            .parse::<i32>()
+            .map_err(|_| ParameterError::RecurrenceRule)?;
+        if ordinal == 0 || ordinal.abs() > 5 {
+            return Err(ParameterError::RecurrenceRule);
+        }
+        Some(ordinal)
+    };
+    Ok((ordinal, parse_weekday(weekday)?))
+}
+
+fn parse_number<T: FromStr>(s: &str) -> Result<T, ParameterError> {
+    s.parse().map_err(|_| ParameterError::RecurrenceRule)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .map(|d| d.day())
+        .unwrap_or(28)
+}
+
+/// Resolves negative days that are counted from the end.
+fn resolve_day(day: i32, len: u32) -> Option<u32> {
+    let day = if day < 0 { len as i32 + 1 + day } else { day };
+    if day >= 1 && day <= len as i32 {
+        Some(day as u32)
+    } else {
+        None
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = ParameterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        let text = if text.to_uppercase().starts_with("RRULE:") {
+            &text[6..]
+        } else {
+            text
+        };
+        let mut freq = None;
+        let mut interval = None;
+        let mut count = None;
+        let mut until = None;
+        let mut by_day = None;
+        let mut by_month_day = None;
+        for part in text.split(';').filter(|part| !part.is_empty()) {
+            let mut key_value = part.splitn(2, '=');
+            let (key, value) = match (key_value.next(), key_value.next()) {
+                (Some(key), Some(value)) => {
+                    (key.trim().to_uppercase(), value.trim().to_uppercase())
+                }
+                _ => return Err(ParameterError::RecurrenceRule),
+            };
+            let duplicate = match key.as_str() {
+                "FREQ" => freq
+                    .replace(match value.as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(ParameterError::RecurrenceRule),
+                    })
+                    .is_some(),
+                "INTERVAL" => interval.replace(parse_number::<u32>(&value)?).is_some(),
+                "COUNT" => count.replace(parse_number::<u32>(&value)?).is_some(),
+                "UNTIL" => {
+                    let mut dt = parse_date_time(&value)?;
+                    if value.len() == 8 {
+                        // A date includes the whole day
+                        dt = dt.date().and_hms(23, 59, 59);
+                    }
+                    until.replace(dt).is_some()
+                }
+                "BYDAY" => by_day
+                    .replace(
+                        value
+                            .split(',')
+                            .map(parse_by_day)
+                            .collect::<Result<Vec<_>, _>>()?,
+                    )
+                    .is_some(),
+                "BYMONTHDAY" => by_month_day
+                    .replace(
+                        value
+                            .split(',')
+                            .map(parse_number::<i32>)
+                            .collect::<Result<Vec<_>, _>>()?,
+                    )
+                    .is_some(),
+                // Weeks always start on Monday
+                "WKST" if value == "MO" => false,
+                _ => return Err(ParameterError::RecurrenceRule),
+            };
+            if duplicate {
+                return Err(ParameterError::RecurrenceRule);
+            }
+        }
+        let freq = freq.ok_or(ParameterError::RecurrenceRule)?;
+        let interval = interval.unwrap_or(1);
+        let by_day = by_day.unwrap_or_default();
+        let by_month_day = by_month_day.unwrap_or_default();
+        let invalid = interval == 0
+            || count == Some(0)
+            // COUNT and UNTIL must not occur both
+            || (count.is_some() && until.is_some())
+            || (freq == Frequency::Yearly && !by_day.is_empty())
+            || (freq != Frequency::Monthly && by_day.iter().any(|(n, _)| n.is_some()))
+            || (freq != Frequency::Monthly && !by_month_day.is_empty())
+            || by_month_day.iter().any(|day| *day == 0 || day.abs() > 31);
+        if invalid {
+            return Err(ParameterError::RecurrenceRule);
+        }
+        Ok(Self {
+            text: text.to_string(),
+            freq,
+            interval,
+            count,
+            until,
+            by_day,
+            by_month_day,
+        })
+    }
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl RecurrenceRule {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// All occurrences in chronological order. The start itself
+    /// is always the first occurrence, even if it doesn't match
+    /// the rule.
+    pub fn occurrences(&self, start: NaiveDateTime) -> Occurrences {
+        Occurrences {
+            rule: self,
+            start,
+            period: 0,
+            empty_periods: 0,
+            pending: vec![],
+            emitted: 0,
+            done: false,
+        }
+    }
+
+    /// The start of the last occurrence or an upper bound for it,
+    /// `None` if the occurrences never end or are too many.
+    pub fn last_start(&self, start: NaiveDateTime) -> Option<NaiveDateTime> {
+        if let Some(count) = self.count {
+            if count > MAX_COUNT_OF_LAST_START {
+                return None;
+            }
+            self.occurrences(start).last()
+        } else {
+            self.until.map(|until| until.max(start))
+        }
+    }
+
+    fn weekdays_or(&self, default: Weekday) -> Vec<Weekday> {
+        if self.by_day.is_empty() {
+            vec![default]
+        } else {
+            self.by_day.iter().map(|(_, weekday)| *weekday).collect()
+        }
+    }
+
+    fn monthly_days(&self, year: i32, month: u32, default: u32) -> Vec<u32> {
+        let len = days_in_month(year, month);
+        if self.by_day.is_empty() && self.by_month_day.is_empty() {
+            return resolve_day(default as i32, len).into_iter().collect();
+        }
+        let by_month_day: Vec<_> = self
+            .by_month_day
+            .iter()
+            .filter_map(|day| resolve_day(*day, len))
+            .collect();
+        let mut by_day = Vec::with_capacity(self.by_day.len() * 5);
+        for (ordinal, weekday) in &self.by_day {
+            let days: Vec<_> = (1..=len)
+                .filter(|day| NaiveDate::from_ymd(year, month, *day).weekday() == *weekday)
+                .collect();
+            match ordinal {
+                Some(ordinal) => {
+                    by_day.extend(
+                        resolve_day(*ordinal, days.len() as u32).map(|i| days[i as usize - 1]),
+                    );
+                }
+                None => by_day.extend(days),
+            }
+        }
+        let mut days = if self.by_day.is_empty() {
+            by_month_day
+        } else if self.by_month_day.is_empty() {
+            by_day
+        } else {
+            by_day
+                .into_iter()
+                .filter(|day| by_month_day.contains(day))
+                .collect()
+        };
+        days.sort_unstable();
+        days.dedup();
+        days
+    }
+
+    /// All candidates within the n-th period after the start,
+    /// `None` if the dates are out of range.
+    fn candidates(&self, start: NaiveDateTime, period: u32) -> Option<Vec<NaiveDateTime>> {
+        let n = i64::from(period) * i64::from(self.interval);
+        let date = start.date();
+        let time = start.time();
+        let dates = match self.freq {
+            Frequency::Daily => {
+                let date = date.checked_add_signed(Duration::days(n))?;
+                if self.weekdays_or(date.weekday()).contains(&date.weekday()) {
+                    vec![date]
+                } else {
+                    vec![]
+                }
+            }
+            Frequency::Weekly => {
+                let days_from_monday = date.weekday().num_days_from_monday();
+                let monday = date
+                    .checked_sub_signed(Duration::days(days_from_monday.into()))?
+                    .checked_add_signed(Duration::weeks(n))?;
+                let mut dates: Vec<_> = self
+                    .weekdays_or(date.weekday())
+                    .into_iter()
+                    .map(|weekday| monday + Duration::days(weekday.num_days_from_monday().into()))
+                    .collect();
+                dates.sort_unstable();
+                dates.dedup();
+                dates
+            }
+            Frequency::Monthly => {
+                let months = i64::from(date.month0()) + n;
+                let year = i64::from(date.year()) + months / 12;
+                if year > i64::from(std::i32::MAX) {
+                    return None;
+                }
+                let (year, month) = (year as i32, (months % 12) as u32 + 1);
+                // Validates the year
+                NaiveDate::from_ymd_opt(year, month, 1)?;
+                self.monthly_days(year, month, date.day())
+                    .into_iter()
+                    .map(|day| NaiveDate::from_ymd(year, month, day))
+                    .collect()
+            }
+            Frequency::Yearly => {
+                let year = i64::from(date.year()) + n;
+                if year > i64::from(std::i32::MAX) {
+                    return None;
+                }
+                let year = year as i32;
+                NaiveDate::from_ymd_opt(year, 1, 1)?;
+                // The 29th of February only occurs in leap years
+                NaiveDate::from_ymd_opt(year, date.month(), date.day())
+                    .into_iter()
+                    .collect()
+            }
+        };
+        Some(dates.into_iter().map(|date| date.and_time(time)).collect())
+    }
+}
+
+/// Iterates over the occurrences of a recurrence rule.
+pub struct Occurrences<'a> {
+    rule: &'a RecurrenceRule,
+    start: NaiveDateTime,
+    period: u32,
+    /// Consecutive periods without any occurrence
+    empty_periods: u32,
+    /// In reverse order
+    pending: Vec<NaiveDateTime>,
+    emitted: u32,
+    done: bool,
+}
+
+impl<'a> Iterator for Occurrences<'a> {
+    type Item = NaiveDateTime;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.emitted == 0 {
+            self.emitted = 1;
+            return Some(self.start);
+        }
+        loop {
+            if self.rule.count.map(|count| self.emitted >= count).unwrap_or(false) {
+                self.done = true;
+                return None;
+            }
+            if let Some(next) = self.pending.pop() {
+                if self.rule.until.map(|until| next > until).unwrap_or(false) {
+                    self.done = true;
+                    return None;
+                }
+                self.emitted += 1;
+                return Some(next);
+            }
+            if self.empty_periods >= MAX_EMPTY_PERIODS {
+                self.done = true;
+                return None;
+            }
+            let start = self.start;
+            match self.rule.candidates(start, self.period) {
+                Some(mut candidates) => {
+                    candidates.retain(|candidate| *candidate > start);
+                    if candidates.is_empty() {
+                        self.empty_periods += 1;
+                    } else {
+                        self.empty_periods = 0;
+                    }
+                    candidates.reverse();
+                    self.pending = candidates;
+                }
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            }
+            self.period += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(s: &str) -> NaiveDateTime {
+        parse_date_time(s).unwrap()
+    }
+
+    fn occurrences(rule: &str, start: &str, max: usize) -> Vec<String> {
+        rule.parse::<RecurrenceRule>()
+            .unwrap()
+            .occurrences(dt(start))
+            .take(max)
+            .map(format_date_time)
+            .collect()
+    }
+
+    #[test]
+    fn parse_valid_rules() {
+        for s in &[
+            "FREQ=DAILY",
+            "RRULE:FREQ=WEEKLY;BYDAY=SA",
+            "freq=weekly;interval=2;byday=mo,we;wkst=mo",
+            "FREQ=MONTHLY;BYDAY=1SA,-1FR;COUNT=10",
+            "FREQ=MONTHLY;BYMONTHDAY=1,-1;UNTIL=20191231T235959Z",
+            "FREQ=YEARLY;UNTIL=20250101",
+        ] {
+            assert!(s.parse::<RecurrenceRule>().is_ok(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_invalid_rules() {
+        for s in &[
+            "",
+            "WEEKLY",
+            "FREQ=HOURLY",
+            "FREQ=WEEKLY;FREQ=DAILY",
+            "FREQ=WEEKLY;INTERVAL=0",
+            "FREQ=WEEKLY;COUNT=0",
+            "FREQ=WEEKLY;COUNT=3;UNTIL=20191231",
+            "FREQ=WEEKLY;BYDAY=XY",
+            "FREQ=WEEKLY;BYDAY=1SA",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=MONTHLY;BYDAY=6SA",
+            "FREQ=YEARLY;BYDAY=SA",
+            "FREQ=WEEKLY;WKST=SU",
+            "FREQ=WEEKLY;BYSETPOS=1",
+        ] {
+            assert!(s.parse::<RecurrenceRule>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn keep_original_text() {
+        let rule = " RRULE:FREQ=WEEKLY;BYDAY=SA ".parse::<RecurrenceRule>().unwrap();
+        assert_eq!("FREQ=WEEKLY;BYDAY=SA", rule.as_str());
+        assert_eq!("FREQ=WEEKLY;BYDAY=SA", rule.to_string());
+    }
+
+    #[test]
+    fn daily_with_count() {
+        assert_eq!(
+            vec!["20190603T100000", "20190605T100000", "20190607T100000"],
+            occurrences("FREQ=DAILY;INTERVAL=2;COUNT=3", "20190603T100000", 10)
+        );
+    }
+
+    #[test]
+    fn weekly_on_multiple_days_until() {
+        // 2019-06-05 is a Wednesday
+        assert_eq!(
+            vec![
+                "20190605T180000",
+                "20190607T180000",
+                "20190617T180000",
+                "20190619T180000",
+                "20190621T180000",
+            ],
+            occurrences(
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE,FR;UNTIL=20190621T180000Z",
+                "20190605T180000",
+                10
+            )
+        );
+    }
+
+    #[test]
+    fn weekly_without_end() {
+        assert_eq!(
+            vec!["20190608T090000", "20190615T090000", "20190622T090000"],
+            occurrences("FREQ=WEEKLY", "20190608T090000", 3)
+        );
+    }
+
+    #[test]
+    fn monthly_by_weekday_ordinal() {
+        // The first Saturday and the last Friday of each month
+        assert_eq!(
+            vec![
+                "20190601T100000",
+                "20190628T100000",
+                "20190706T100000",
+                "20190726T100000",
+            ],
+            occurrences("FREQ=MONTHLY;BYDAY=1SA,-1FR", "20190601T100000", 4)
+        );
+    }
+
+    #[test]
+    fn monthly_skips_invalid_days() {
+        assert_eq!(
+            vec!["20190131T100000", "20190331T100000", "20190531T100000"],
+            occurrences("FREQ=MONTHLY;COUNT=3", "20190131T100000", 10)
+        );
+        assert_eq!(
+            vec!["20190131T100000", "20190228T100000", "20190331T100000"],
+            occurrences("FREQ=MONTHLY;BYMONTHDAY=-1", "20190131T100000", 3)
+        );
+    }
+
+    #[test]
+    fn yearly_on_leap_day() {
+        assert_eq!(
+            vec!["20200229T120000", "20240229T120000"],
+            occurrences("FREQ=YEARLY;UNTIL=20250101", "20200229T120000", 10)
+        );
+    }
+
+    #[test]
+    fn start_is_always_the_first_occurrence() {
+        // 2019-06-05 is a Wednesday
+        assert_eq!(
+            vec!["20190605T100000", "20190608T100000"],
+            occurrences("FREQ=WEEKLY;BYDAY=SA;COUNT=2", "20190605T100000", 10)
+        );
+    }
+
+    #[test]
+    fn never_ending_without_matches() {
+        assert_eq!(
+            vec!["20190201T100000"],
+            occurrences("FREQ=MONTHLY;INTERVAL=12;BYMONTHDAY=31", "20190201T100000", 10)
+        );
+    }
+
+    #[test]
+    fn last_start_of_occurrences() {
+        let start = dt("20190603T100000");
+        let rule = "FREQ=DAILY;COUNT=3".parse::<RecurrenceRule>().unwrap();
+        assert_eq!(Some(dt("20190605T100000")), rule.last_start(start));
+        let rule = "FREQ=DAILY;UNTIL=20190610".parse::<RecurrenceRule>().unwrap();
+        assert_eq!(Some(dt("20190610T235959")), rule.last_start(start));
+        let rule = "FREQ=DAILY".parse::<RecurrenceRule>().unwrap();
+        assert_eq!(None, rule.last_start(start));
+        let rule = "FREQ=DAILY;COUNT=20000".parse::<RecurrenceRule>().unwrap();
+        assert_eq!(Some(start + Duration::days(19_999)), rule.last_start(start));
+        let rule = "FREQ=DAILY;COUNT=1000000".parse::<RecurrenceRule>().unwrap();
+        assert_eq!(None, rule.last_start(start));
+    }
+
+    #[test]
+    fn open_ended_beyond_many_periods() {
+        let start = dt("20190603T100000");
+        let rule = "FREQ=DAILY".parse::<RecurrenceRule>().unwrap();
+        assert_eq!(Some(start + Duration::days(20_000)), rule.occurrences(start).nth(20_000));
+        let rule = "FREQ=YEARLY".parse::<RecurrenceRule>().unwrap();
+        let leap_day = dt("20000229T100000");
+        assert_eq!(Some(dt("20400229T100000")), rule.occurrences(leap_day).nth(10));
+    }
+}
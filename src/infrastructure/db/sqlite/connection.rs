@@ -11,6 +11,19 @@ use std::result;
 
 type Result<T> = result::Result<T, RepoError>;
 
+/// Matches only the latest version of each entry that
+/// has been created until the given time.
+fn latest_entry_version_as_of(
+    id_column: &str,
+    version_column: &str,
+    as_of: i64,
+) -> diesel::expression::SqlLiteral<diesel::sql_types::Bool> {
+    diesel::dsl::sql(&format!(
+        "{} = (SELECT MAX(v.version) FROM entries v WHERE v.id = {} AND v.created <= {})",
+        version_column, id_column, as_of
+    ))
+}
+
 fn reset_current_entry_before_insert_new_version(conn: &SqliteConnection, id: &str) -> Result<()> {
     use self::schema::entries::dsl;
     let count = diesel::update(
@@ -55,6 +68,7 @@ fn load_entry(conn: &SqliteConnection, entry: models::Entry) -> Result<Entry> {
         license,
         image_url,
         image_link_url,
+        opening_hours,
         ..
     } = entry;
 
@@ -100,6 +114,7 @@ fn load_entry(conn: &SqliteConnection, entry: models::Entry) -> Result<Entry> {
         license,
         image_url,
         image_link_url,
+        opening_hours: opening_hours.and_then(|oh| super::util::parse_opening_hours(&oh)),
     })
 }
 
@@ -169,6 +184,24 @@ impl EntryGateway for SqliteConnection {
         Ok(results)
     }
 
+    fn get_entry_history(&self, id: &str) -> Result<Vec<Entry>> {
+        use self::schema::entries::dsl as e_dsl;
+
+        let entries = e_dsl::entries
+            .filter(e_dsl::id.eq(id))
+            .order_by(e_dsl::version)
+            .load::<models::Entry>(self)?;
+        if entries.is_empty() {
+            return Err(RepoError::NotFound);
+        }
+
+        let mut results = Vec::with_capacity(entries.len());
+        for entry in entries {
+            results.push(load_entry(self, entry)?);
+        }
+        Ok(results)
+    }
+
     fn all_entries(&self) -> Result<Vec<Entry>> {
         use self::schema::{
             entries::dsl as e_dsl, entry_category_relations::dsl as e_c_dsl,
@@ -244,6 +277,73 @@ impl EntryGateway for SqliteConnection {
         Ok(res_entries)
     }
 
+    fn all_entries_as_of(&self, as_of: Timestamp) -> Result<Vec<Entry>> {
+        use self::schema::{
+            entries::dsl as e_dsl, entry_category_relations::dsl as e_c_dsl,
+            entry_tag_relations::dsl as e_t_dsl,
+        };
+        let as_of = i64::from(as_of);
+        let entries: Vec<models::Entry> = e_dsl::entries
+            .filter(latest_entry_version_as_of("entries.id", "entries.version", as_of))
+            // Entries are archived together with their latest version
+            .filter(e_dsl::archived.is_null().or(e_dsl::archived.gt(as_of)))
+            .order_by((e_dsl::id, e_dsl::version))
+            .load(self)?;
+        let mut cat_rels = e_c_dsl::entry_category_relations
+            .filter(latest_entry_version_as_of(
+                "entry_category_relations.entry_id",
+                "entry_category_relations.entry_version",
+                as_of,
+            ))
+            .order_by((e_c_dsl::entry_id, e_c_dsl::entry_version))
+            .load(self)?
+            .into_iter()
+            .peekable();
+        let mut tag_rels = e_t_dsl::entry_tag_relations
+            .filter(latest_entry_version_as_of(
+                "entry_tag_relations.entry_id",
+                "entry_tag_relations.entry_version",
+                as_of,
+            ))
+            .order_by((e_t_dsl::entry_id, e_t_dsl::entry_version))
+            .load(self)?
+            .into_iter()
+            .peekable();
+        let mut res_entries = Vec::with_capacity(entries.len());
+        // Both entries and relations are sorted by entry id and version
+        for mut entry in entries.into_iter() {
+            // The entry has not been archived at that time
+            entry.archived = None;
+            let key = (entry.id.as_str(), entry.version);
+            let mut categories = Vec::with_capacity(10);
+            let mut tags = Vec::with_capacity(100);
+            // Skip relations of other entries and versions
+            while let Some(true) = cat_rels.peek().map(|ec: &models::EntryCategoryRelation| {
+                (ec.entry_id.as_str(), ec.entry_version) < key
+            }) {
+                let _ = cat_rels.next();
+            }
+            while let Some(true) = tag_rels.peek().map(|et: &models::EntryTagRelation| {
+                (et.entry_id.as_str(), et.entry_version) < key
+            }) {
+                let _ = tag_rels.next();
+            }
+            // Collect categories and tags of this version
+            while let Some(true) = cat_rels.peek().map(|ec: &models::EntryCategoryRelation| {
+                (ec.entry_id.as_str(), ec.entry_version) == key
+            }) {
+                categories.push(cat_rels.next().unwrap().category_id);
+            }
+            while let Some(true) = tag_rels.peek().map(|et: &models::EntryTagRelation| {
+                (et.entry_id.as_str(), et.entry_version) == key
+            }) {
+                tags.push(tag_rels.next().unwrap().tag_id);
+            }
+            res_entries.push((entry, categories, tags).into());
+        }
+        Ok(res_entries)
+    }
+
     fn count_entries(&self) -> Result<usize> {
         use self::schema::entries::dsl as e_dsl;
         Ok(e_dsl::entries
@@ -253,6 +353,38 @@ impl EntryGateway for SqliteConnection {
             .first::<i64>(self)? as usize)
     }
 
+    fn all_entries_chunk(&self, after_id: Option<&str>, limit: usize) -> Result<Vec<Entry>> {
+        use self::schema::entries::dsl as e_dsl;
+
+        let mut query = e_dsl::entries
+            .filter(e_dsl::current.eq(true))
+            .filter(e_dsl::archived.is_null())
+            .order_by(e_dsl::id)
+            .limit(limit as i64)
+            .into_boxed();
+        if let Some(after_id) = after_id {
+            query = query.filter(e_dsl::id.gt(after_id));
+        }
+        let entries = query.load::<models::Entry>(self)?;
+
+        let mut results = Vec::with_capacity(entries.len());
+        for entry in entries {
+            results.push(load_entry(self, entry)?);
+        }
+        Ok(results)
+    }
+
+    fn changed_entry_ids_since(&self, since: Timestamp) -> Result<Vec<String>> {
+        use self::schema::entries::dsl as e_dsl;
+        let since = i64::from(since);
+        Ok(e_dsl::entries
+            .select(e_dsl::id)
+            .distinct()
+            .filter(e_dsl::created.ge(since))
+            .or_filter(e_dsl::archived.ge(since))
+            .load::<String>(self)?)
+    }
+
     fn update_entry(&self, entry: &Entry) -> Result<()> {
         let e = models::Entry::from(entry.clone());
 
@@ -313,6 +445,37 @@ impl EntryGateway for SqliteConnection {
         Ok(count)
     }
 
+    fn create_entry_revert(&self, revert: &EntryRevert) -> Result<()> {
+        let revert = models::EntryRevert {
+            entry_id: revert.entry_id.clone(),
+            entry_version: revert.version as i64,
+            reverted_version: revert.reverted_version as i64,
+            reverted_by: revert.reverted_by.clone(),
+            created: revert.created.into(),
+        };
+        diesel::insert_into(schema::entry_reverts::table)
+            .values(&revert)
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn get_entry_reverts(&self, entry_id: &str) -> Result<Vec<EntryRevert>> {
+        use self::schema::entry_reverts::dsl;
+        Ok(dsl::entry_reverts
+            .filter(dsl::entry_id.eq(entry_id))
+            .order_by(dsl::entry_version)
+            .load::<models::EntryRevert>(self)?
+            .into_iter()
+            .map(|r| EntryRevert {
+                entry_id: r.entry_id,
+                version: r.entry_version as u64,
+                reverted_version: r.reverted_version as u64,
+                reverted_by: r.reverted_by,
+                created: r.created.into(),
+            })
+            .collect())
+    }
+
     fn import_multiple_entries(&mut self, new_entries: &[Entry]) -> Result<()> {
         let imports: Vec<_> = new_entries
             .iter()
@@ -437,6 +600,8 @@ impl EventGateway for SqliteConnection {
             registration,
             organizer,
             archived,
+            rrule,
+            exdates,
         } = e_dsl::events
             .filter(e_dsl::id.eq(e_id))
             .filter(e_dsl::archived.is_null())
@@ -497,9 +662,27 @@ impl EventGateway for SqliteConnection {
             registration,
             organizer,
             archived: archived.map(Into::into),
+            rrule: rrule.and_then(|r| super::util::parse_rrule(&r)),
+            exdates: exdates
+                .map(|x| super::util::parse_exdates(&x))
+                .unwrap_or_default(),
         })
     }
 
+    fn get_events(&self, ids: &[&str]) -> Result<Vec<Event>> {
+        use self::schema::{event_tag_relations::dsl as e_t_dsl, events::dsl as e_dsl};
+        // TODO: Split loading into chunks of fixed size
+        info!("Loading multiple ({}) events at once", ids.len());
+        let events: Vec<models::Event> = e_dsl::events
+            .filter(e_dsl::id.eq_any(ids))
+            .filter(e_dsl::archived.is_null())
+            .load(self)?;
+        let tag_rels = e_t_dsl::event_tag_relations
+            .filter(e_t_dsl::event_id.eq_any(ids))
+            .load(self)?;
+        Ok(events.into_iter().map(|e| (e, &tag_rels).into()).collect())
+    }
+
     fn all_events(&self) -> Result<Vec<Event>> {
         use self::schema::{event_tag_relations::dsl as e_t_dsl, events::dsl as e_dsl};
         let events: Vec<models::Event> =
@@ -560,6 +743,12 @@ impl EventGateway for SqliteConnection {
                 .set(&e)
                 .execute(self)?;
 
+            // Changesets ignore missing values, but an event
+            // might not recur anymore after the update.
+            diesel::update(e_dsl::events.filter(e_dsl::id.eq(&e.id)))
+                .set((e_dsl::rrule.eq(&e.rrule), e_dsl::exdates.eq(&e.exdates)))
+                .execute(self)?;
+
             Ok(())
         })?;
         Ok(())
@@ -685,6 +874,18 @@ impl RatingRepository for SqliteConnection {
             .collect())
     }
 
+    fn load_ratings_as_of(&self, as_of: Timestamp) -> Result<Vec<Rating>> {
+        use self::schema::ratings::dsl;
+        let as_of = i64::from(as_of);
+        Ok(dsl::ratings
+            .filter(dsl::created.le(as_of))
+            .filter(dsl::archived.is_null().or(dsl::archived.gt(as_of)))
+            .load::<models::Rating>(self)?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
     fn load_entry_ids_of_ratings(&self, ids: &[&str]) -> Result<Vec<String>> {
         use self::schema::ratings::dsl;
         Ok(dsl::ratings
@@ -694,6 +895,17 @@ impl RatingRepository for SqliteConnection {
             .load::<String>(self)?)
     }
 
+    fn load_entry_ids_of_ratings_changed_since(&self, since: Timestamp) -> Result<Vec<String>> {
+        use self::schema::ratings::dsl;
+        let since = i64::from(since);
+        Ok(dsl::ratings
+            .select(dsl::entry_id)
+            .distinct()
+            .filter(dsl::created.ge(since))
+            .or_filter(dsl::archived.ge(since))
+            .load::<String>(self)?)
+    }
+
     fn archive_ratings(&self, ids: &[&str], archived: Timestamp) -> Result<usize> {
         use self::schema::ratings::dsl as r_dsl;
         let count = diesel::update(
@@ -912,6 +1124,36 @@ impl Db for SqliteConnection {
     }
 }
 
+impl GeocodingGateway for SqliteConnection {
+    fn get_geocoded_address(&self, address: &str) -> Result<(Option<MapPoint>, Timestamp)> {
+        use self::schema::geocoding_cache::dsl;
+        let geocoded = dsl::geocoding_cache
+            .find(address)
+            .first::<models::GeocodedAddress>(self)?;
+        let pos = match (geocoded.lat, geocoded.lng) {
+            (Some(lat), Some(lng)) => MapPoint::try_from_lat_lng_deg(lat, lng),
+            _ => None,
+        };
+        Ok((pos, geocoded.created.into()))
+    }
+
+    fn store_geocoded_address(&self, address: &str, pos: Option<MapPoint>) -> Result<()> {
+        let (lat, lng) = match pos.map(MapPoint::to_lat_lng_deg) {
+            Some((lat, lng)) => (Some(lat), Some(lng)),
+            None => (None, None),
+        };
+        diesel::replace_into(schema::geocoding_cache::table)
+            .values(&models::GeocodedAddress {
+                address: address.to_owned(),
+                created: Timestamp::now().into(),
+                lat,
+                lng,
+            })
+            .execute(self)?;
+        Ok(())
+    }
+}
+
 impl OrganizationGateway for SqliteConnection {
     fn create_org(&mut self, o: Organization) -> Result<()> {
         let tag_rels: Vec<_> = o
@@ -23,6 +23,7 @@ pub struct Entry {
     pub license: Option<String>,
     pub image_url: Option<String>,
     pub image_link_url: Option<String>,
+    pub opening_hours: Option<String>,
 }
 
 #[derive(Queryable, Insertable, AsChangeset)]
@@ -46,6 +47,8 @@ pub struct Event {
     pub registration: Option<i16>,
     pub organizer: Option<String>,
     pub archived: Option<i64>,
+    pub rrule: Option<String>,
+    pub exdates: Option<String>,
 }
 
 #[derive(Queryable, Insertable)]
@@ -171,3 +174,22 @@ pub struct BboxSubscription {
     pub north_east_lng: f64,
     pub username: String,
 }
+
+#[derive(Queryable, Insertable)]
+#[table_name = "geocoding_cache"]
+pub struct GeocodedAddress {
+    pub address: String,
+    pub created: i64,
+    pub lat: Option<f64>,
+    pub lng: Option<f64>,
+}
+
+#[derive(Queryable, Insertable)]
+#[table_name = "entry_reverts"]
+pub struct EntryRevert {
+    pub entry_id: String,
+    pub entry_version: i64,
+    pub reverted_version: i64,
+    pub reverted_by: String,
+    pub created: i64,
+}
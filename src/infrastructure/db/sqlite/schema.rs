@@ -50,6 +50,7 @@ table! {
         license -> Nullable<Text>,
         image_url -> Nullable<Text>,
         image_link_url -> Nullable<Text>,
+        opening_hours -> Nullable<Text>,
     }
 }
 
@@ -61,6 +62,16 @@ table! {
     }
 }
 
+table! {
+    entry_reverts (entry_id, entry_version) {
+        entry_id -> Text,
+        entry_version -> BigInt,
+        reverted_version -> BigInt,
+        reverted_by -> Text,
+        created -> BigInt,
+    }
+}
+
 table! {
     entry_tag_relations (entry_id, entry_version, tag_id) {
         entry_id -> Text,
@@ -103,6 +114,17 @@ table! {
         registration -> Nullable<SmallInt>,
         organizer -> Nullable<Text>,
         archived -> Nullable<BigInt>,
+        rrule -> Nullable<Text>,
+        exdates -> Nullable<Text>,
+    }
+}
+
+table! {
+    geocoding_cache (address) {
+        address -> Text,
+        created -> BigInt,
+        lat -> Nullable<Double>,
+        lng -> Nullable<Double>,
     }
 }
 
@@ -147,6 +169,7 @@ table! {
 joinable!(bbox_subscriptions -> users (username));
 joinable!(comments -> ratings (rating_id));
 joinable!(entry_category_relations -> categories (category_id));
+joinable!(entry_reverts -> users (reverted_by));
 joinable!(entry_tag_relations -> tags (tag_id));
 joinable!(event_tag_relations -> events (event_id));
 joinable!(event_tag_relations -> tags (tag_id));
@@ -160,9 +183,11 @@ allow_tables_to_appear_in_same_query!(
     comments,
     entries,
     entry_category_relations,
+    entry_reverts,
     entry_tag_relations,
     event_tag_relations,
     events,
+    geocoding_cache,
     org_tag_relations,
     organizations,
     ratings,
@@ -2,7 +2,11 @@ use super::models::*;
 use crate::core::{
     entities as e,
     prelude::{Error, ParameterError, Result},
-    util::geo::{MapBbox, MapPoint},
+    util::{
+        geo::{MapBbox, MapPoint},
+        opening_hours::OpeningHours,
+        recurrence::{self, RecurrenceRule},
+    },
 };
 use chrono::prelude::*;
 use std::str::FromStr;
@@ -23,6 +27,7 @@ impl From<e::Entry> for Entry {
             license,
             image_url,
             image_link_url,
+            opening_hours,
             ..
         } = e;
 
@@ -67,6 +72,7 @@ impl From<e::Entry> for Entry {
             license,
             image_url,
             image_link_url,
+            opening_hours: opening_hours.map(|oh| oh.to_string()),
         }
     }
 }
@@ -138,6 +144,8 @@ impl From<e::Event> for Event {
             registration,
             organizer,
             archived,
+            rrule,
+            exdates,
             ..
         } = e;
 
@@ -185,10 +193,54 @@ impl From<e::Event> for Event {
             registration,
             organizer,
             archived: archived.map(Into::into),
+            rrule: rrule.map(|r| r.to_string()),
+            exdates: format_exdates(&exdates),
         }
     }
 }
 
+/// Excluded dates are stored as a comma-separated list in
+/// the date-time format of RFC 5545.
+pub fn format_exdates(exdates: &[NaiveDateTime]) -> Option<String> {
+    if exdates.is_empty() {
+        return None;
+    }
+    let exdates: Vec<_> = exdates
+        .iter()
+        .cloned()
+        .map(recurrence::format_date_time)
+        .collect();
+    Some(exdates.join(","))
+}
+
+pub fn parse_exdates(exdates: &str) -> Vec<NaiveDateTime> {
+    exdates
+        .split(',')
+        .filter(|exdate| !exdate.is_empty())
+        .filter_map(|exdate| {
+            recurrence::parse_date_time(exdate)
+                .map_err(|_| warn!("Invalid excluded date in database: {}", exdate))
+                .ok()
+        })
+        .collect()
+}
+
+/// Recurrence rules have been validated before they were stored.
+pub fn parse_rrule(rrule: &str) -> Option<RecurrenceRule> {
+    rrule
+        .parse::<RecurrenceRule>()
+        .map_err(|_| warn!("Invalid recurrence rule in database: {}", rrule))
+        .ok()
+}
+
+/// Opening hours have been validated before they were stored.
+pub fn parse_opening_hours(opening_hours: &str) -> Option<OpeningHours> {
+    opening_hours
+        .parse::<OpeningHours>()
+        .map_err(|_| warn!("Invalid opening hours in database: {}", opening_hours))
+        .ok()
+}
+
 impl From<(Entry, Vec<String>, Vec<String>)> for e::Entry {
     fn from(d: (Entry, Vec<String>, Vec<String>)) -> Self {
         let (e, categories, tags) = d;
@@ -211,6 +263,7 @@ impl From<(Entry, Vec<String>, Vec<String>)> for e::Entry {
             homepage,
             image_url,
             image_link_url,
+            opening_hours,
             ..
         } = e;
         let location = e::Location {
@@ -247,6 +300,7 @@ impl From<(Entry, Vec<String>, Vec<String>)> for e::Entry {
             license,
             image_url,
             image_link_url,
+            opening_hours: opening_hours.and_then(|oh| parse_opening_hours(&oh)),
         }
     }
 }
@@ -273,6 +327,8 @@ impl From<(Event, &Vec<EventTagRelation>)> for e::Event {
             registration,
             organizer,
             archived,
+            rrule,
+            exdates,
             ..
         } = e;
         let tags = tag_rels
@@ -326,6 +382,8 @@ impl From<(Event, &Vec<EventTagRelation>)> for e::Event {
             registration,
             organizer,
             archived: archived.map(Into::into),
+            rrule: rrule.and_then(|r| parse_rrule(&r)),
+            exdates: exdates.map(|x| parse_exdates(&x)).unwrap_or_default(),
         }
     }
 }
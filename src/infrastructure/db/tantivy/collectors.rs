@@ -0,0 +1,390 @@
+use crate::core::util::geo::{
+    Distance, LatCoord, LngCoord, MapMultiPolygon, MapPoint, RawCoord,
+};
+
+use std::{cmp::Ordering, collections::HashMap, sync::Arc};
+use tantivy::{
+    collector::{Collector, SegmentCollector},
+    fastfield::FastFieldReader,
+    schema::Field,
+    DocAddress, DocId, Score, SegmentLocalId, SegmentReader,
+};
+
+fn read_map_point(
+    lat_reader: &FastFieldReader<i64>,
+    lng_reader: &FastFieldReader<i64>,
+    doc: DocId,
+) -> MapPoint {
+    MapPoint::new(
+        LatCoord::from_raw(lat_reader.get(doc) as RawCoord),
+        LngCoord::from_raw(lng_reader.get(doc) as RawCoord),
+    )
+}
+
+fn cmp_by_distance(lhs: &(f64, DocAddress), rhs: &(f64, DocAddress)) -> Ordering {
+    lhs.0
+        .partial_cmp(&rhs.0)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| lhs.1.cmp(&rhs.1))
+}
+
+/// Collects the documents that are closest to a
+/// reference point, i.e. ordered by ascending
+/// great-circle distance in meters.
+pub(crate) struct TopDocsByDistance {
+    center: MapPoint,
+    limit: usize,
+    lat: Field,
+    lng: Field,
+}
+
+impl TopDocsByDistance {
+    pub fn new(center: MapPoint, limit: usize, lat: Field, lng: Field) -> Self {
+        debug_assert!(center.is_valid());
+        debug_assert!(limit > 0);
+        Self {
+            center,
+            limit,
+            lat,
+            lng,
+        }
+    }
+}
+
+impl Collector for TopDocsByDistance {
+    type Fruit = Vec<(f64, DocAddress)>;
+
+    type Child = TopDocsByDistanceSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentLocalId,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(TopDocsByDistanceSegmentCollector {
+            center: self.center,
+            limit: self.limit,
+            segment_local_id,
+            lat_reader: segment.fast_field_reader::<i64>(self.lat)?,
+            lng_reader: segment.fast_field_reader::<i64>(self.lng)?,
+            top_docs: Vec::with_capacity(2 * self.limit),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        let mut top_docs: Vec<_> = segment_fruits.into_iter().flatten().collect();
+        top_docs.sort_by(cmp_by_distance);
+        top_docs.truncate(self.limit);
+        Ok(top_docs)
+    }
+}
+
+pub(crate) struct TopDocsByDistanceSegmentCollector {
+    center: MapPoint,
+    limit: usize,
+    segment_local_id: SegmentLocalId,
+    lat_reader: FastFieldReader<i64>,
+    lng_reader: FastFieldReader<i64>,
+    top_docs: Vec<(f64, DocAddress)>,
+}
+
+impl SegmentCollector for TopDocsByDistanceSegmentCollector {
+    type Fruit = Vec<(f64, DocAddress)>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let pos = read_map_point(&self.lat_reader, &self.lng_reader, doc);
+        let distance = MapPoint::distance(self.center, pos)
+            .map(|d| d.to_meters())
+            .unwrap_or(std::f64::INFINITY);
+        self.top_docs
+            .push((distance, DocAddress(self.segment_local_id, doc)));
+        // Prune the candidates periodically instead of
+        // maintaining a heap for every single document
+        if self.top_docs.len() >= 2 * self.limit {
+            self.top_docs.sort_by(cmp_by_distance);
+            self.top_docs.truncate(self.limit);
+        }
+    }
+
+    fn harvest(mut self) -> Self::Fruit {
+        self.top_docs.sort_by(cmp_by_distance);
+        self.top_docs.truncate(self.limit);
+        self.top_docs
+    }
+}
+
+/// A constraint for the position of documents.
+#[derive(Debug, Clone)]
+pub(crate) enum PositionFilter {
+    /// Within the great-circle distance around a center point
+    WithinRadius(MapPoint, Distance),
+    /// Inside of the polygon(s)
+    WithinArea(Arc<MapMultiPolygon>),
+}
+
+impl PositionFilter {
+    fn matches(&self, pos: MapPoint) -> bool {
+        match self {
+            PositionFilter::WithinRadius(center, radius) => MapPoint::distance(*center, pos)
+                .map(|distance| distance <= *radius)
+                .unwrap_or(false),
+            PositionFilter::WithinArea(area) => pos.is_valid() && area.contains_point(pos),
+        }
+    }
+}
+
+/// Restricts the documents that are passed to the
+/// wrapped collector to those with a position that
+/// matches all filters.
+pub(crate) struct FilterByPosition<C> {
+    filters: Arc<Vec<PositionFilter>>,
+    lat: Field,
+    lng: Field,
+    collector: C,
+}
+
+impl<C> FilterByPosition<C> {
+    pub fn new(filters: Vec<PositionFilter>, lat: Field, lng: Field, collector: C) -> Self {
+        debug_assert!(!filters.is_empty());
+        Self {
+            filters: Arc::new(filters),
+            lat,
+            lng,
+            collector,
+        }
+    }
+}
+
+impl<C: Collector> Collector for FilterByPosition<C> {
+    type Fruit = C::Fruit;
+
+    type Child = FilterByPositionSegmentCollector<C::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentLocalId,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(FilterByPositionSegmentCollector {
+            filters: Arc::clone(&self.filters),
+            lat_reader: segment.fast_field_reader::<i64>(self.lat)?,
+            lng_reader: segment.fast_field_reader::<i64>(self.lng)?,
+            collector: self.collector.for_segment(segment_local_id, segment)?,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        self.collector.merge_fruits(segment_fruits)
+    }
+}
+
+pub(crate) struct FilterByPositionSegmentCollector<C> {
+    filters: Arc<Vec<PositionFilter>>,
+    lat_reader: FastFieldReader<i64>,
+    lng_reader: FastFieldReader<i64>,
+    collector: C,
+}
+
+impl<C: SegmentCollector> SegmentCollector for FilterByPositionSegmentCollector<C> {
+    type Fruit = C::Fruit;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let pos = read_map_point(&self.lat_reader, &self.lng_reader, doc);
+        if self.filters.iter().all(|filter| filter.matches(pos)) {
+            self.collector.collect(doc, score);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.collector.harvest()
+    }
+}
+
+/// The index of a grid cell, i.e. the rounded down
+/// quotient of a coordinate and the cell size.
+fn grid_cell_index(raw_coord: i64, cell_size: i64) -> i64 {
+    debug_assert!(cell_size > 0);
+    if raw_coord >= 0 {
+        raw_coord / cell_size
+    } else {
+        (raw_coord + 1) / cell_size - 1
+    }
+}
+
+/// The higher rating wins, ties are resolved
+/// deterministically by the document address.
+fn is_better_top_doc(lhs: &(u64, DocAddress), rhs: &(u64, DocAddress)) -> bool {
+    lhs.0 > rhs.0 || (lhs.0 == rhs.0 && lhs.1 < rhs.1)
+}
+
+/// All collected documents within a single grid cell.
+#[derive(Debug, Clone)]
+pub(crate) struct GridCell {
+    pub count: usize,
+    lat_sum: i64,
+    lng_sum: i64,
+    /// The rating and address of the document with
+    /// the highest rating
+    pub top_doc: (u64, DocAddress),
+}
+
+impl GridCell {
+    fn new(pos: MapPoint, top_doc: (u64, DocAddress)) -> Self {
+        Self {
+            count: 1,
+            lat_sum: i64::from(pos.lat().to_raw()),
+            lng_sum: i64::from(pos.lng().to_raw()),
+            top_doc,
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.count += other.count;
+        self.lat_sum += other.lat_sum;
+        self.lng_sum += other.lng_sum;
+        if is_better_top_doc(&other.top_doc, &self.top_doc) {
+            self.top_doc = other.top_doc;
+        }
+    }
+
+    /// The centroid of all collected documents.
+    pub fn center(&self) -> MapPoint {
+        debug_assert!(self.count > 0);
+        let count = self.count as i64;
+        MapPoint::new(
+            LatCoord::from_raw((self.lat_sum / count) as RawCoord),
+            LngCoord::from_raw((self.lng_sum / count) as RawCoord),
+        )
+    }
+}
+
+type GridCells = HashMap<(i64, i64), GridCell>;
+
+fn merge_grid_cells(cells: &mut GridCells, cell_index: (i64, i64), cell: GridCell) {
+    if let Some(merged_cell) = cells.get_mut(&cell_index) {
+        merged_cell.merge(cell);
+        return;
+    }
+    cells.insert(cell_index, cell);
+}
+
+/// Groups the documents by the cells of a grid with
+/// a fixed cell size in raw coordinates. Documents
+/// without a valid position are ignored.
+pub(crate) struct GroupByGridCell {
+    lat_cell_size: i64,
+    lng_cell_size: i64,
+    lat: Field,
+    lng: Field,
+    rating: Field,
+}
+
+impl GroupByGridCell {
+    pub fn new(
+        (lat_cell_size, lng_cell_size): (i64, i64),
+        lat: Field,
+        lng: Field,
+        rating: Field,
+    ) -> Self {
+        debug_assert!(lat_cell_size > 0);
+        debug_assert!(lng_cell_size > 0);
+        Self {
+            lat_cell_size,
+            lng_cell_size,
+            lat,
+            lng,
+            rating,
+        }
+    }
+}
+
+impl Collector for GroupByGridCell {
+    type Fruit = GridCells;
+
+    type Child = GroupByGridCellSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentLocalId,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(GroupByGridCellSegmentCollector {
+            lat_cell_size: self.lat_cell_size,
+            lng_cell_size: self.lng_cell_size,
+            segment_local_id,
+            lat_reader: segment.fast_field_reader::<i64>(self.lat)?,
+            lng_reader: segment.fast_field_reader::<i64>(self.lng)?,
+            rating_reader: segment.fast_field_reader::<u64>(self.rating)?,
+            cells: Default::default(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        let mut cells = GridCells::new();
+        for (cell_index, cell) in segment_fruits.into_iter().flatten() {
+            merge_grid_cells(&mut cells, cell_index, cell);
+        }
+        Ok(cells)
+    }
+}
+
+pub(crate) struct GroupByGridCellSegmentCollector {
+    lat_cell_size: i64,
+    lng_cell_size: i64,
+    segment_local_id: SegmentLocalId,
+    lat_reader: FastFieldReader<i64>,
+    lng_reader: FastFieldReader<i64>,
+    rating_reader: FastFieldReader<u64>,
+    cells: GridCells,
+}
+
+impl SegmentCollector for GroupByGridCellSegmentCollector {
+    type Fruit = GridCells;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let pos = read_map_point(&self.lat_reader, &self.lng_reader, doc);
+        if !pos.is_valid() {
+            return;
+        }
+        let cell_index = (
+            grid_cell_index(i64::from(pos.lat().to_raw()), self.lat_cell_size),
+            grid_cell_index(i64::from(pos.lng().to_raw()), self.lng_cell_size),
+        );
+        let top_doc = (
+            self.rating_reader.get(doc),
+            DocAddress(self.segment_local_id, doc),
+        );
+        merge_grid_cells(&mut self.cells, cell_index, GridCell::new(pos, top_doc));
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_cell_index_rounds_down() {
+        assert_eq!(0, grid_cell_index(0, 10));
+        assert_eq!(0, grid_cell_index(9, 10));
+        assert_eq!(1, grid_cell_index(10, 10));
+        assert_eq!(-1, grid_cell_index(-1, 10));
+        assert_eq!(-1, grid_cell_index(-10, 10));
+        assert_eq!(-2, grid_cell_index(-11, 10));
+    }
+}
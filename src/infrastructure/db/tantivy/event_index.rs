@@ -0,0 +1,406 @@
+use super::{
+    bbox_sub_queries, commit_with_metadata, open_or_recreate_index_in_dir, register_tokenizers,
+    IndexMetadata, TextLanguage, ID_TOKENIZER, OVERALL_INDEX_HEAP_SIZE_IN_BYTES, TAG_TOKENIZER,
+};
+
+use crate::core::{
+    db::{EventIndex, EventIndexQuery, EventIndexer},
+    entities::Event,
+};
+
+use failure::{bail, Fallible};
+use std::{ops::Bound, path::Path};
+use tantivy::{
+    collector::TopDocs,
+    query::{AllQuery, BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery},
+    schema::*,
+    Document, Index, IndexReader, IndexWriter, ReloadPolicy,
+};
+
+/// Must be incremented whenever the schema or the text analysis
+/// changes, i.e. when the existing index needs to be rebuilt.
+///
+/// 1: Initial schema
+/// 2: Indexed the last start of recurring events
+const EVENT_INDEX_SCHEMA_VERSION: u32 = 2;
+
+struct IndexedEventFields {
+    id: Field,
+    lat: Field,
+    lng: Field,
+    start: Field,
+    end: Field,
+    last_start: Field,
+    title: Field,
+    description: Field,
+    organizer: Field,
+    tag: Field,
+}
+
+impl IndexedEventFields {
+    fn build_schema(text_language: TextLanguage) -> (Self, Schema) {
+        let id_options = TextOptions::default()
+            .set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(ID_TOKENIZER)
+                    .set_index_option(IndexRecordOption::Basic),
+            )
+            .set_stored();
+        let tag_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(TAG_TOKENIZER)
+                .set_index_option(IndexRecordOption::WithFreqs),
+        );
+        let text_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(text_language.tokenizer_name())
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        // Only the id is stored, all other event
+        // properties are loaded from the database
+        let mut schema_builder = SchemaBuilder::default();
+        let fields = Self {
+            id: schema_builder.add_text_field("id", id_options),
+            lat: schema_builder.add_i64_field("lat", INDEXED),
+            lng: schema_builder.add_i64_field("lng", INDEXED),
+            start: schema_builder.add_i64_field("start", INDEXED | FAST),
+            end: schema_builder.add_i64_field("end", INDEXED),
+            last_start: schema_builder.add_i64_field("last_start", INDEXED),
+            title: schema_builder.add_text_field("title", text_options.clone()),
+            description: schema_builder.add_text_field("description", text_options.clone()),
+            organizer: schema_builder.add_text_field("organizer", text_options),
+            tag: schema_builder.add_text_field("tag", tag_options),
+        };
+        (fields, schema_builder.build())
+    }
+}
+
+pub(crate) struct TantivyEventIndex {
+    fields: IndexedEventFields,
+    text_language: TextLanguage,
+    index_reader: IndexReader,
+    index_writer: IndexWriter,
+    text_query_parser: QueryParser,
+}
+
+impl TantivyEventIndex {
+    pub fn create_in_ram() -> Fallible<Self> {
+        let no_path: Option<&Path> = None;
+        Self::create(no_path, Default::default())
+    }
+
+    pub fn create<P: AsRef<Path>>(path: Option<P>, text_language: TextLanguage) -> Fallible<Self> {
+        let (fields, schema) = IndexedEventFields::build_schema(text_language);
+
+        let index = if let Some(path) = path {
+            open_or_recreate_index_in_dir(
+                path.as_ref(),
+                schema,
+                &IndexMetadata::new(EVENT_INDEX_SCHEMA_VERSION, text_language),
+            )?
+        } else {
+            warn!("Creating full-text event index in RAM");
+            Index::create_in_ram(schema)
+        };
+
+        register_tokenizers(&index, text_language);
+
+        // See also: TantivyEntryIndex
+        let index_reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let index_writer = index.writer(OVERALL_INDEX_HEAP_SIZE_IN_BYTES)?;
+        let text_query_parser = QueryParser::for_index(
+            &index,
+            vec![
+                fields.title,
+                fields.description,
+                fields.organizer,
+                fields.tag,
+            ],
+        );
+        Ok(Self {
+            fields,
+            text_language,
+            index_reader,
+            index_writer,
+            text_query_parser,
+        })
+    }
+
+    fn build_query(&self, query: &EventIndexQuery) -> Box<Query> {
+        let mut sub_queries: Vec<(Occur, Box<Query>)> = Vec::with_capacity(2 + 2 + 1 + 1);
+
+        // Bbox
+        if let Some(ref bbox) = query.bbox {
+            debug!("Query bbox: {}", bbox);
+            sub_queries.extend(bbox_sub_queries(self.fields.lat, self.fields.lng, bbox));
+        }
+
+        // Start
+        if query.start_min.is_some() || query.start_max.is_some() {
+            debug!(
+                "Query start: min = {:?}, max = {:?}",
+                query.start_min, query.start_max
+            );
+            // Recurring events match if any of their
+            // occurrences might start within the range
+            if let Some(min) = query.start_min {
+                let last_start_query = RangeQuery::new_i64_bounds(
+                    self.fields.last_start,
+                    Bound::Included(min.timestamp()),
+                    Bound::Unbounded,
+                );
+                sub_queries.push((Occur::Must, Box::new(last_start_query)));
+            }
+            if let Some(max) = query.start_max {
+                let start_query = RangeQuery::new_i64_bounds(
+                    self.fields.start,
+                    Bound::Unbounded,
+                    Bound::Included(max.timestamp()),
+                );
+                sub_queries.push((Occur::Must, Box::new(start_query)));
+            }
+        }
+
+        // Tags (any of)
+        if !query.tags.is_empty() {
+            debug!("Query tags: {:?}", query.tags);
+            let mut tag_queries: Vec<(Occur, Box<Query>)> = Vec::with_capacity(query.tags.len());
+            for tag in &query.tags {
+                debug_assert!(!tag.trim().is_empty());
+                let tag_term = Term::from_field_text(self.fields.tag, &tag.to_lowercase());
+                let tag_query = TermQuery::new(tag_term, IndexRecordOption::Basic);
+                tag_queries.push((Occur::Should, Box::new(tag_query)));
+            }
+            sub_queries.push((Occur::Must, Box::new(BooleanQuery::from(tag_queries))));
+        }
+
+        // Text
+        if let Some(text) = &query.text {
+            debug!("Query text: {}", text);
+            debug_assert!(!text.trim().is_empty());
+            match self.text_query_parser.parse_query(&text.to_lowercase()) {
+                Ok(text_query) => {
+                    sub_queries.push((Occur::Must, text_query));
+                }
+                Err(err) => {
+                    warn!("Failed to parse query text '{}': {:?}", text, err);
+                }
+            }
+        }
+
+        if sub_queries.is_empty() {
+            Box::new(AllQuery)
+        } else {
+            Box::new(BooleanQuery::from(sub_queries))
+        }
+    }
+}
+
+impl EventIndexer for TantivyEventIndex {
+    fn add_or_update_event(&mut self, event: &Event) -> Fallible<()> {
+        let id_term = Term::from_field_text(self.fields.id, &event.id);
+        self.index_writer.delete_term(id_term);
+        let mut doc = Document::default();
+        doc.add_text(self.fields.id, &event.id);
+        if let Some(ref location) = event.location {
+            doc.add_i64(self.fields.lat, i64::from(location.pos.lat().to_raw()));
+            doc.add_i64(self.fields.lng, i64::from(location.pos.lng().to_raw()));
+        }
+        doc.add_i64(self.fields.start, event.start.timestamp());
+        if let Some(end) = event.end {
+            doc.add_i64(self.fields.end, end.timestamp());
+        }
+        let last_start = event
+            .last_start()
+            .map(|last_start| last_start.timestamp())
+            .unwrap_or(i64::max_value());
+        doc.add_i64(self.fields.last_start, last_start);
+        doc.add_text(self.fields.title, &event.title);
+        if let Some(ref description) = event.description {
+            doc.add_text(self.fields.description, description);
+        }
+        if let Some(ref organizer) = event.organizer {
+            doc.add_text(self.fields.organizer, organizer);
+        }
+        for tag in &event.tags {
+            doc.add_text(self.fields.tag, tag);
+        }
+        self.index_writer.add_document(doc);
+        Ok(())
+    }
+
+    fn remove_event_by_id(&mut self, id: &str) -> Fallible<()> {
+        let id_term = Term::from_field_text(self.fields.id, id);
+        self.index_writer.delete_term(id_term);
+        Ok(())
+    }
+
+    fn remove_all_events(&mut self) -> Fallible<()> {
+        let searcher = self.index_reader.searcher();
+        let num_docs = searcher.num_docs() as usize;
+        if num_docs == 0 {
+            return Ok(());
+        }
+        let top_docs = searcher.search(&AllQuery, &TopDocs::with_limit(num_docs))?;
+        for (_, doc_addr) in top_docs {
+            let doc = searcher.doc(doc_addr)?;
+            if let Some(id) = doc.get_first(self.fields.id).and_then(Value::text) {
+                let id_term = Term::from_field_text(self.fields.id, id);
+                self.index_writer.delete_term(id_term);
+            } else {
+                error!("Missing id of event document {:?}", doc_addr);
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_events(&mut self) -> Fallible<()> {
+        commit_with_metadata(
+            &mut self.index_writer,
+            &IndexMetadata::new(EVENT_INDEX_SCHEMA_VERSION, self.text_language),
+        )?;
+        self.index_reader.reload()?;
+        Ok(())
+    }
+}
+
+impl EventIndex for TantivyEventIndex {
+    fn query_events(
+        &self,
+        query: &EventIndexQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Fallible<Vec<String>> {
+        if limit == 0 {
+            bail!("Invalid limit: {}", limit);
+        }
+        let top_limit = match offset.checked_add(limit) {
+            Some(top_limit) => top_limit,
+            None => bail!("Invalid offset: {}", offset),
+        };
+        let searcher = self.index_reader.searcher();
+        let top_docs =
+            searcher.search(&*self.build_query(query), &TopDocs::with_limit(top_limit))?;
+        let mut ids = Vec::with_capacity(top_docs.len().saturating_sub(offset));
+        for (_, doc_addr) in top_docs.into_iter().skip(offset) {
+            match searcher.doc(doc_addr) {
+                Ok(doc) => {
+                    if let Some(id) = doc.get_first(self.fields.id).and_then(Value::text) {
+                        ids.push(id.to_owned());
+                    } else {
+                        error!("Missing id of event document {:?}", doc_addr);
+                    }
+                }
+                Err(err) => {
+                    warn!("Failed to load document {:?}: {}", doc_addr, err);
+                }
+            }
+        }
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{entities::Location, util::geo::MapPoint};
+    use chrono::NaiveDateTime;
+
+    fn new_event(id: &str, title: &str, start: i64) -> Event {
+        Event {
+            id: id.into(),
+            title: title.into(),
+            description: None,
+            start: NaiveDateTime::from_timestamp(start, 0),
+            end: None,
+            location: None,
+            contact: None,
+            tags: vec![],
+            homepage: None,
+            created_by: None,
+            registration: None,
+            organizer: None,
+            archived: None,
+            rrule: None,
+            exdates: vec![],
+        }
+    }
+
+    fn query_sorted_ids(index: &TantivyEventIndex, query: &EventIndexQuery) -> Vec<String> {
+        let mut ids = index.query_events(query, 0, 10).unwrap();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn query_events_by_text_start_and_position() {
+        let mut index = TantivyEventIndex::create_in_ram().unwrap();
+        let mut e1 = new_event("e1", "Repair Café", 100);
+        e1.location = Some(Location {
+            pos: MapPoint::from_lat_lng_deg(48.7, 9.1),
+            address: None,
+        });
+        let mut e2 = new_event("e2", "Kleidertausch", 200);
+        e2.organizer = Some("Repair-Initiative".into());
+        let mut e3 = new_event("e3", "Gartentreffen", 300);
+        e3.tags = vec!["repair".into()];
+        for e in &[&e1, &e2, &e3] {
+            index.add_or_update_event(e).unwrap();
+        }
+        index.flush_events().unwrap();
+
+        let mut query = EventIndexQuery::default();
+        query.text = Some("repair".into());
+        assert_eq!(vec!["e1", "e2", "e3"], query_sorted_ids(&index, &query));
+
+        query.start_min = Some(NaiveDateTime::from_timestamp(150, 0));
+        assert_eq!(vec!["e2", "e3"], query_sorted_ids(&index, &query));
+
+        let first_page = index.query_events(&query, 0, 1).unwrap();
+        let second_page = index.query_events(&query, 1, 1).unwrap();
+        assert_eq!(1, first_page.len());
+        assert_eq!(1, second_page.len());
+        assert_ne!(first_page, second_page);
+        assert!(index.query_events(&query, 2, 1).unwrap().is_empty());
+
+        query.start_min = None;
+        query.bbox = Some("48,9,49,10".parse().unwrap());
+        assert_eq!(vec!["e1"], query_sorted_ids(&index, &query));
+
+        index.remove_event_by_id("e1").unwrap();
+        index.flush_events().unwrap();
+        assert!(query_sorted_ids(&index, &query).is_empty());
+
+        index.remove_all_events().unwrap();
+        index.add_or_update_event(&e3).unwrap();
+        index.flush_events().unwrap();
+        assert_eq!(vec!["e3"], query_sorted_ids(&index, &EventIndexQuery::default()));
+    }
+
+    #[test]
+    fn query_recurring_events_by_start() {
+        let mut index = TantivyEventIndex::create_in_ram().unwrap();
+        let e1 = new_event("e1", "Once", 100);
+        let mut e2 = new_event("e2", "Daily", 100);
+        e2.rrule = Some("FREQ=DAILY;COUNT=3".parse().unwrap());
+        let mut e3 = new_event("e3", "Weekly", 100);
+        e3.rrule = Some("FREQ=WEEKLY".parse().unwrap());
+        for e in &[&e1, &e2, &e3] {
+            index.add_or_update_event(e).unwrap();
+        }
+        index.flush_events().unwrap();
+
+        let mut query = EventIndexQuery::default();
+        query.start_min = Some(NaiveDateTime::from_timestamp(150, 0));
+        assert_eq!(vec!["e2", "e3"], query_sorted_ids(&index, &query));
+
+        query.start_min = Some(NaiveDateTime::from_timestamp(3 * 86_400, 0));
+        assert_eq!(vec!["e3"], query_sorted_ids(&index, &query));
+
+        query.start_max = Some(NaiveDateTime::from_timestamp(50, 0));
+        assert!(query_sorted_ids(&index, &query).is_empty());
+    }
+}
@@ -1,29 +1,133 @@
+mod collectors;
+mod event_index;
+mod text_analyzer;
+
+pub use self::text_analyzer::TextLanguage;
+
+use self::{
+    collectors::{FilterByPosition, GroupByGridCell, PositionFilter, TopDocsByDistance},
+    event_index::TantivyEventIndex,
+    text_analyzer::{fold_to_ascii, register_text_analyzer},
+};
+
 use crate::core::{
-    db::{EntryIndex, EntryIndexQuery, EntryIndexer, IndexedEntry},
-    entities::{AvgRatingValue, AvgRatings, Entry},
-    util::geo::{LatCoord, LngCoord, MapPoint, RawCoord},
+    db::{
+        ClusterGrid, EntryCluster, EntryFacets, EntryIndex, EntryIndexQuery, EntryIndexer,
+        EntrySnippets, EntrySortOrder, EventIndex, EventIndexQuery, EventIndexer,
+        IndexedEntriesPage, IndexedEntry, SuggestionKind,
+    },
+    entities::{Address, AvgRatingValue, AvgRatings, Entry, Event, RatingContext},
+    util::{
+        geo::{LatCoord, LngCoord, MapBbox, MapPoint, RawCoord},
+        opening_hours,
+        time::Timestamp,
+    },
 };
 
 use failure::{bail, Fallible};
 use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs,
+    io::Write,
+    mem,
     ops::Bound,
-    path::Path,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
 };
 use tantivy::{
-    collector::TopDocs,
-    query::{BooleanQuery, Occur, Query, QueryParser, RangeQuery, TermQuery},
+    collector::{Collector, Count, FacetCollector, TopDocs},
+    query::{
+        AllQuery, BooleanQuery, FuzzyTermQuery, Occur, PhraseQuery, Query, QueryParser, RangeQuery,
+        TermQuery,
+    },
     schema::*,
-    tokenizer::{LowerCaser, RawTokenizer, Tokenizer},
-    DocAddress, Document, Index, IndexReader, IndexWriter, ReloadPolicy,
+    tokenizer::{LowerCaser, RawTokenizer, TokenStream, Tokenizer},
+    DocAddress, DocSet, Document, Index, IndexReader, IndexWriter, ReloadPolicy, Score, Searcher,
+    Snippet, SnippetGenerator,
 };
+use uuid::Uuid;
 
 const OVERALL_INDEX_HEAP_SIZE_IN_BYTES: usize = 50_000_000;
 
+/// The maximum length of highlighted snippets
+const TITLE_SNIPPET_MAX_CHARS: usize = 150;
+const DESCRIPTION_SNIPPET_MAX_CHARS: usize = 300;
+
+/// The entry index is stored in a subdirectory with this name or
+/// with this name as a prefix after the index has been rebuilt.
+const ENTRY_INDEX_DIR_NAME: &str = "entries";
+
+/// Contains the name of the subdirectory of the current entry index.
+/// Rebuilt indexes are activated by atomically replacing this file.
+const CURRENT_ENTRY_INDEX_FILE_NAME: &str = "entries.current";
+
+const EVENT_INDEX_DIR_NAME: &str = "events";
+
+/// The index is unreadable without this file.
+const INDEX_META_FILE_NAME: &str = "meta.json";
+
+/// Must be incremented whenever the schema or the text analysis
+/// changes, i.e. when the existing index needs to be rebuilt.
+///
+/// 1: Initial schema with the default text tokenizer
+/// 2: Language-aware text analysis with folding and stemming
+/// 3: Title facets for suggestions
+/// 4: Stored address fields that can be searched by name
+/// 5: Indexed ratings for filtering by a minimum rating
+/// 6: Stored opening hours for filtering by time
+/// 7: Indexed slots of the week instead of stored opening hours
+/// 8: Suggestion keys for tags and titles instead of title facets
+/// 9: Indexed days and hours of the week besides slots
+const ENTRY_INDEX_SCHEMA_VERSION: u32 = 9;
+
+/// Stored as the payload of each commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct IndexMetadata {
+    schema_version: u32,
+    text_language: String,
+    /// All changes that have been stored before this
+    /// time (in seconds) have been indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    high_water_mark: Option<i64>,
+}
+
+impl IndexMetadata {
+    fn new(schema_version: u32, text_language: TextLanguage) -> Self {
+        Self {
+            schema_version,
+            text_language: text_language.code().to_owned(),
+            high_water_mark: None,
+        }
+    }
+
+    fn with_high_water_mark(self, high_water_mark: Timestamp) -> Self {
+        Self {
+            high_water_mark: Some(high_water_mark.into()),
+            ..self
+        }
+    }
+
+    /// An existing index can be reused if both the schema
+    /// and the text analysis didn't change.
+    fn is_compatible_with(&self, other: &Self) -> bool {
+        self.schema_version == other.schema_version && self.text_language == other.text_language
+    }
+
+    fn load(index: &Index) -> Fallible<Option<Self>> {
+        let payload = index.load_metas()?.payload;
+        Ok(payload
+            .as_ref()
+            .map(|payload| serde_json::from_str(payload))
+            .transpose()?)
+    }
+}
+
 struct IndexedEntryFields {
     id: Field,
     lat: Field,
     lng: Field,
+    created: Field,
     title: Field,
     description: Field,
     address_street: Field,
@@ -32,6 +136,9 @@ struct IndexedEntryFields {
     address_country: Field,
     category: Field,
     tag: Field,
+    facet: Field,
+    tag_suggestion: Field,
+    title_suggestion: Field,
     ratings_diversity: Field,
     ratings_fairness: Field,
     ratings_humanity: Field,
@@ -39,10 +146,11 @@ struct IndexedEntryFields {
     ratings_solidarity: Field,
     ratings_transparency: Field,
     total_rating: Field,
+    open_period: Field,
 }
 
 impl IndexedEntryFields {
-    fn build_schema() -> (Self, Schema) {
+    fn build_schema(text_language: TextLanguage) -> (Self, Schema) {
         let id_options = TextOptions::default()
             .set_indexing_options(
                 TextFieldIndexing::default()
@@ -67,49 +175,81 @@ impl IndexedEntryFields {
         let address_options = TextOptions::default()
             .set_indexing_options(
                 TextFieldIndexing::default()
-                    .set_tokenizer(TEXT_TOKENIZER)
+                    .set_tokenizer(text_language.tokenizer_name())
                     .set_index_option(IndexRecordOption::WithFreqs),
             )
-            // Address fields currently don't need to be stored
-            //.set_stored()
-            ;
+            .set_stored();
         let text_options = TextOptions::default()
             .set_indexing_options(
                 TextFieldIndexing::default()
-                    .set_tokenizer(TEXT_TOKENIZER)
+                    .set_tokenizer(text_language.tokenizer_name())
                     .set_index_option(IndexRecordOption::WithFreqsAndPositions),
             )
             .set_stored();
+        // Only the terms are needed for looking up suggestions
+        let suggestion_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(ID_TOKENIZER)
+                .set_index_option(IndexRecordOption::Basic),
+        );
+        // Ratings can be used both for filtering and sorting
+        let rating_options = INDEXED | STORED | FAST;
         let mut schema_builder = SchemaBuilder::default();
         let fields = Self {
             id: schema_builder.add_text_field("id", id_options),
-            lat: schema_builder.add_i64_field("lat", INDEXED | STORED),
-            lng: schema_builder.add_i64_field("lng", INDEXED | STORED),
+            lat: schema_builder.add_i64_field("lat", INDEXED | STORED | FAST),
+            lng: schema_builder.add_i64_field("lng", INDEXED | STORED | FAST),
+            created: schema_builder.add_i64_field("created", INDEXED | FAST),
             title: schema_builder.add_text_field("title", text_options.clone()),
             description: schema_builder.add_text_field("description", text_options.clone()),
-            address_street: schema_builder
-                .add_text_field("address_street", address_options.clone()),
-            address_city: schema_builder.add_text_field("address_city", address_options.clone()),
-            address_zip: schema_builder.add_text_field("address_zip", address_options.clone()),
-            address_country: schema_builder
-                .add_text_field("address_country", address_options.clone()),
+            // The names of the address fields are used for
+            // field-scoped text queries, e.g. "city:stuttgart"
+            address_street: schema_builder.add_text_field("street", address_options.clone()),
+            address_city: schema_builder.add_text_field("city", address_options.clone()),
+            address_zip: schema_builder.add_text_field("zip", address_options.clone()),
+            address_country: schema_builder.add_text_field("country", address_options.clone()),
             category: schema_builder.add_text_field("category", category_options.clone()),
             tag: schema_builder.add_text_field("tag", tag_options.clone()),
-            ratings_diversity: schema_builder.add_u64_field("ratings_diversity", STORED),
-            ratings_fairness: schema_builder.add_u64_field("ratings_fairness", STORED),
-            ratings_humanity: schema_builder.add_u64_field("ratings_humanity", STORED),
-            ratings_renewable: schema_builder.add_u64_field("ratings_renewable", STORED),
-            ratings_solidarity: schema_builder.add_u64_field("ratings_solidarity", STORED),
-            ratings_transparency: schema_builder.add_u64_field("ratings_transparency", STORED),
-            total_rating: schema_builder.add_u64_field("total_rating", STORED | FAST),
+            facet: schema_builder.add_facet_field("facet"),
+            tag_suggestion: schema_builder
+                .add_text_field("tag_suggestion", suggestion_options.clone()),
+            title_suggestion: schema_builder
+                .add_text_field("title_suggestion", suggestion_options.clone()),
+            ratings_diversity: schema_builder
+                .add_u64_field("ratings_diversity", rating_options.clone()),
+            ratings_fairness: schema_builder
+                .add_u64_field("ratings_fairness", rating_options.clone()),
+            ratings_humanity: schema_builder
+                .add_u64_field("ratings_humanity", rating_options.clone()),
+            ratings_renewable: schema_builder
+                .add_u64_field("ratings_renewable", rating_options.clone()),
+            ratings_solidarity: schema_builder
+                .add_u64_field("ratings_solidarity", rating_options.clone()),
+            ratings_transparency: schema_builder
+                .add_u64_field("ratings_transparency", rating_options.clone()),
+            total_rating: schema_builder.add_u64_field("total_rating", rating_options.clone()),
+            // The periods of the week in which an entry is open
+            open_period: schema_builder.add_u64_field("open_period", INDEXED),
         };
         (fields, schema_builder.build())
     }
 
+    fn rating_field(&self, context: RatingContext) -> Field {
+        match context {
+            RatingContext::Diversity => self.ratings_diversity,
+            RatingContext::Fairness => self.ratings_fairness,
+            RatingContext::Humanity => self.ratings_humanity,
+            RatingContext::Renewable => self.ratings_renewable,
+            RatingContext::Solidarity => self.ratings_solidarity,
+            RatingContext::Transparency => self.ratings_transparency,
+        }
+    }
+
     fn read_document(&self, doc: &Document) -> IndexedEntry {
         let mut lat: Option<LatCoord> = Default::default();
         let mut lng: Option<LngCoord> = Default::default();
         let mut entry = IndexedEntry::default();
+        let mut address = Address::default();
         entry.categories.reserve(4);
         entry.tags.reserve(32);
         for field_value in doc.field_values() {
@@ -152,6 +292,38 @@ impl IndexedEntryFields {
                         error!("Invalid description value: {:?}", fv.value());
                     }
                 }
+                fv if fv.field() == self.address_street => {
+                    debug_assert!(address.street.is_none());
+                    if let Some(street) = fv.value().text() {
+                        address.street = Some(street.into());
+                    } else {
+                        error!("Invalid street value: {:?}", fv.value());
+                    }
+                }
+                fv if fv.field() == self.address_city => {
+                    debug_assert!(address.city.is_none());
+                    if let Some(city) = fv.value().text() {
+                        address.city = Some(city.into());
+                    } else {
+                        error!("Invalid city value: {:?}", fv.value());
+                    }
+                }
+                fv if fv.field() == self.address_zip => {
+                    debug_assert!(address.zip.is_none());
+                    if let Some(zip) = fv.value().text() {
+                        address.zip = Some(zip.into());
+                    } else {
+                        error!("Invalid zip value: {:?}", fv.value());
+                    }
+                }
+                fv if fv.field() == self.address_country => {
+                    debug_assert!(address.country.is_none());
+                    if let Some(country) = fv.value().text() {
+                        address.country = Some(country.into());
+                    } else {
+                        error!("Invalid country value: {:?}", fv.value());
+                    }
+                }
                 fv if fv.field() == self.category => {
                     if let Some(category) = fv.value().text() {
                         entry.categories.push(category.into());
@@ -191,11 +363,7 @@ impl IndexedEntryFields {
                     entry.ratings.transparency = u64_to_avg_rating(fv.value().u64_value());
                 }
                 fv if fv.field() == self.total_rating => (),
-                // Address fields are currently not stored
-                //fv if fv.field() == self.address_street => (),
-                //fv if fv.field() == self.address_city => (),
-                //fv if fv.field() == self.address_zip => (),
-                //fv if fv.field() == self.address_country => (),
+                fv if fv.field() == self.facet => (),
                 fv => {
                     error!("Unexpected field value: {:?}", fv);
                 }
@@ -206,31 +374,95 @@ impl IndexedEntryFields {
         } else {
             error!("Invalid position: lat = {:?}, lng = {:?}", lat, lng);
         }
+        if !address.is_empty() {
+            entry.address = Some(address);
+        }
         entry
     }
 }
 
 pub(crate) struct TantivyEntryIndex {
     fields: IndexedEntryFields,
+    text_language: TextLanguage,
     index: Index,
     index_reader: IndexReader,
     index_writer: IndexWriter,
     text_query_parser: QueryParser,
 }
 
+const CATEGORY_FACET: &str = "category";
+const TAG_FACET: &str = "tag";
+
+fn facet_root(name: &str) -> Facet {
+    Facet::from_path(vec![name])
+}
+
+/// Extracts the child path of a facet, e.g. "foo" from "/tag/foo".
+fn facet_child(root: &str, facet: &Facet) -> String {
+    let path = facet.to_string();
+    let prefix_len = 1 + root.len() + 1;
+    debug_assert!(path.len() > prefix_len);
+    // Slashes within the path components are escaped
+    path[prefix_len.min(path.len())..].replace("\\/", "/")
+}
+
+/// The term for suggesting a value, i.e. the lowercase value
+/// without umlauts and accents for prefix matching followed by
+/// the original value.
+fn suggestion_key(value: &str) -> String {
+    format!("{}\0{}", fold_to_ascii(&value.to_lowercase()), value)
+}
+
+/// Extracts the original value from the term for suggesting it.
+fn suggestion_value(key: &[u8]) -> Fallible<String> {
+    let key = std::str::from_utf8(key)?;
+    match key.find('\0') {
+        Some(pos) => Ok(key[pos + 1..].to_string()),
+        None => bail!("Invalid suggestion key: {}", key),
+    }
+}
+
+/// The smallest key that is greater than all keys that
+/// start with the prefix. `None` if there is no such key,
+/// i.e. if all keys start with the prefix.
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut upper_bound = prefix.to_vec();
+    while let Some(last) = upper_bound.pop() {
+        if last < u8::max_value() {
+            upper_bound.push(last + 1);
+            return Some(upper_bound);
+        }
+    }
+    None
+}
+
 const ID_TOKENIZER: &str = "raw";
 const TAG_TOKENIZER: &str = "tag";
-const TEXT_TOKENIZER: &str = "default";
 
-fn register_tokenizers(index: &Index) {
+fn register_tokenizers(index: &Index, text_language: TextLanguage) {
     // Predefined tokenizers
     debug_assert!(index.tokenizers().get(ID_TOKENIZER).is_some());
-    debug_assert!(index.tokenizers().get(TEXT_TOKENIZER).is_some());
     // Custom tokenizer(s)
     debug_assert!(index.tokenizers().get(TAG_TOKENIZER).is_none());
     index
         .tokenizers()
         .register(TAG_TOKENIZER, RawTokenizer.filter(LowerCaser));
+    debug_assert!(index
+        .tokenizers()
+        .get(text_language.tokenizer_name())
+        .is_none());
+    register_text_analyzer(index.tokenizers(), text_language);
+}
+
+/// The maximum Levenshtein distance for fuzzy matching
+/// of a single word depending on its length. Short words
+/// are only matched exactly.
+fn fuzzy_distance(word: &str) -> Option<u8> {
+    match word.chars().count() {
+        0..=3 => None,
+        4..=6 => Some(1),
+        _ => Some(2),
+    }
 }
 
 fn f64_to_u64(val: f64, min: f64, max: f64) -> u64 {
@@ -276,28 +508,260 @@ fn u64_to_avg_rating(val: u64) -> AvgRatingValue {
     .into()
 }
 
+/// Orders the collected documents by their sort key, where `first`
+/// denotes the ordering of the key that comes first. Documents with
+/// equal keys are ordered by their address to obtain a stable ordering
+/// across consecutive pages.
+fn into_ordered_doc_addrs<K: PartialOrd>(
+    mut top_docs: Vec<(K, DocAddress)>,
+    first: Ordering,
+) -> Vec<DocAddress> {
+    top_docs.sort_by(|(lhs_key, lhs_addr), (rhs_key, rhs_addr)| {
+        let key_ordering = lhs_key.partial_cmp(rhs_key).unwrap_or(Ordering::Equal);
+        let key_ordering = if first == Ordering::Greater {
+            key_ordering.reverse()
+        } else {
+            key_ordering
+        };
+        key_ordering.then_with(|| lhs_addr.cmp(rhs_addr))
+    });
+    top_docs.into_iter().map(|(_, doc_addr)| doc_addr).collect()
+}
+
+/// Checks if a file in the directory of an index has been created
+/// by Tantivy, i.e. the metadata, lock files, or segment files that
+/// are prefixed with the segment id, e.g. `<uuid>.idx` or `<uuid>.<opstamp>.del`.
+fn is_index_file_name(file_name: &str) -> bool {
+    if file_name == INDEX_META_FILE_NAME
+        || file_name == ".managed.json"
+        || (file_name.starts_with(".tantivy-") && file_name.ends_with(".lock"))
+    {
+        return true;
+    }
+    let mut parts = file_name.splitn(2, '.');
+    match (parts.next(), parts.next()) {
+        (Some(segment_id), Some(ext)) => {
+            segment_id.len() == 32
+                && segment_id.chars().all(|c| c.is_ascii_hexdigit())
+                && !ext.is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// Deletes only the files of the index in the directory. Other
+/// files and subdirectories that might have been placed into the
+/// same directory are not affected.
+fn remove_index_files(path: &Path) -> Fallible<()> {
+    // The index is unreadable without its metadata
+    let meta_file_path = path.join(INDEX_META_FILE_NAME);
+    if meta_file_path.is_file() {
+        fs::remove_file(&meta_file_path)?;
+    }
+    for dir_entry in fs::read_dir(path)? {
+        let dir_entry = dir_entry?;
+        let is_index_file = dir_entry
+            .file_name()
+            .to_str()
+            .map(is_index_file_name)
+            .unwrap_or(false);
+        if is_index_file && dir_entry.file_type()?.is_file() {
+            fs::remove_file(dir_entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens an existing index if it has been created with the same
+/// metadata, i.e. schema version and text language. Otherwise all
+/// files of the existing index are deleted and a new, empty index
+/// is created that needs to be populated from scratch.
+fn open_or_recreate_index_in_dir(
+    path: &Path,
+    schema: Schema,
+    metadata: &IndexMetadata,
+) -> Fallible<Index> {
+    if path.join(INDEX_META_FILE_NAME).is_file() {
+        let existing_index = Index::open_in_dir(path).map_err(failure::Error::from);
+        match existing_index.and_then(|index| Ok((IndexMetadata::load(&index)?, index))) {
+            Ok((Some(ref existing_metadata), index))
+                if existing_metadata.is_compatible_with(metadata) =>
+            {
+                info!(
+                    "Opening full-text search index in directory: {}",
+                    path.to_string_lossy()
+                );
+                return Ok(index);
+            }
+            Ok((existing_metadata, _)) => {
+                info!(
+                    "Rebuilding full-text search index with {:?} instead of {:?}",
+                    metadata, existing_metadata
+                );
+            }
+            Err(err) => {
+                warn!("Rebuilding unreadable full-text search index: {}", err);
+            }
+        }
+        remove_index_files(path)?;
+    }
+    info!(
+        "Creating full-text search index in directory: {}",
+        path.to_string_lossy()
+    );
+    fs::create_dir_all(path)?;
+    Ok(Index::create_in_dir(path, schema)?)
+}
+
+/// Reads the name of the subdirectory of the current entry index.
+/// Falls back to the initial directory if no index has been
+/// rebuilt yet.
+fn read_current_entry_index_dir_name(path: &Path) -> Fallible<String> {
+    let file_path = path.join(CURRENT_ENTRY_INDEX_FILE_NAME);
+    if !file_path.is_file() {
+        return Ok(ENTRY_INDEX_DIR_NAME.to_string());
+    }
+    let dir_name = fs::read_to_string(&file_path)?.trim().to_string();
+    if !dir_name.starts_with(ENTRY_INDEX_DIR_NAME) || dir_name.contains(std::path::is_separator) {
+        bail!(
+            "Invalid entry index directory '{}' in file: {}",
+            dir_name,
+            file_path.to_string_lossy()
+        );
+    }
+    Ok(dir_name)
+}
+
+/// Activates the entry index in the given subdirectory. The file
+/// is written under a temporary name and then renamed, i.e. it
+/// either refers to the previous or to the new directory.
+fn write_current_entry_index_dir_name(path: &Path, dir_name: &str) -> Fallible<()> {
+    let tmp_file_path = path.join(format!("{}.tmp", CURRENT_ENTRY_INDEX_FILE_NAME));
+    {
+        let mut tmp_file = fs::File::create(&tmp_file_path)?;
+        tmp_file.write_all(dir_name.as_bytes())?;
+        tmp_file.sync_all()?;
+    }
+    fs::rename(&tmp_file_path, path.join(CURRENT_ENTRY_INDEX_FILE_NAME))?;
+    Ok(())
+}
+
+/// Deletes the directories of replaced or unfinished entry indexes
+/// and the files of an entry index that previous versions stored in
+/// `path`. Must only be invoked while no other index is rebuilt.
+fn remove_outdated_entry_indexes(path: &Path, current_dir_name: &str) -> Fallible<()> {
+    remove_index_files(path)?;
+    for dir_entry in fs::read_dir(path)? {
+        let dir_entry = dir_entry?;
+        let is_outdated = dir_entry
+            .file_name()
+            .to_str()
+            .map(|name| name.starts_with(ENTRY_INDEX_DIR_NAME) && name != current_dir_name)
+            .unwrap_or(false);
+        if is_outdated && dir_entry.file_type()?.is_dir() {
+            info!(
+                "Deleting outdated full-text search index in directory: {}",
+                dir_entry.path().to_string_lossy()
+            );
+            fs::remove_dir_all(dir_entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Commits all pending changes and stores the metadata
+/// as the payload of the commit.
+fn commit_with_metadata(index_writer: &mut IndexWriter, metadata: &IndexMetadata) -> Fallible<()> {
+    let payload = serde_json::to_string(metadata)?;
+    let mut prepared_commit = index_writer.prepare_commit()?;
+    prepared_commit.set_payload(&payload);
+    prepared_commit.commit()?;
+    Ok(())
+}
+
+/// Creates the sub-queries for matching all documents with
+/// a position inside of the bounding box.
+fn bbox_sub_queries(lat: Field, lng: Field, bbox: &MapBbox) -> Vec<(Occur, Box<Query>)> {
+    debug_assert!(bbox.is_valid());
+    debug_assert!(!bbox.is_empty());
+    let mut sub_queries: Vec<(Occur, Box<Query>)> = Vec::with_capacity(2);
+    let lat_query = RangeQuery::new_i64_bounds(
+        lat,
+        Bound::Included(i64::from(bbox.south_west().lat().to_raw())),
+        Bound::Included(i64::from(bbox.north_east().lat().to_raw())),
+    );
+    // Latitude query: Always inclusive
+    sub_queries.push((Occur::Must, Box::new(lat_query)));
+    // Longitude query: Either inclusive or exclusive (wrap around)
+    if bbox.south_west().lng() <= bbox.north_east().lng() {
+        // regular (inclusive)
+        let lng_query = RangeQuery::new_i64_bounds(
+            lng,
+            Bound::Included(i64::from(bbox.south_west().lng().to_raw())),
+            Bound::Included(i64::from(bbox.north_east().lng().to_raw())),
+        );
+        sub_queries.push((Occur::Must, Box::new(lng_query)));
+    } else {
+        // inverse (exclusive)
+        let lng_query = RangeQuery::new_i64_bounds(
+            lng,
+            Bound::Excluded(i64::from(bbox.north_east().lng().to_raw())),
+            Bound::Excluded(i64::from(bbox.south_west().lng().to_raw())),
+        );
+        sub_queries.push((Occur::MustNot, Box::new(lng_query)));
+    }
+    sub_queries
+}
+
+/// Snippets without any highlighted terms are omitted.
+fn snippet_to_html(snippet: Snippet) -> Option<String> {
+    if snippet.highlighted().is_empty() {
+        None
+    } else {
+        Some(snippet.to_html())
+    }
+}
+
+fn lock_index<T: ?Sized>(index: &Mutex<T>) -> MutexGuard<T> {
+    match index.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
 impl TantivyEntryIndex {
+    fn commit(&mut self, high_water_mark: Option<Timestamp>) -> Fallible<()> {
+        let mut metadata = IndexMetadata::new(ENTRY_INDEX_SCHEMA_VERSION, self.text_language);
+        if let Some(high_water_mark) = high_water_mark {
+            metadata = metadata.with_high_water_mark(high_water_mark);
+        }
+        commit_with_metadata(&mut self.index_writer, &metadata)?;
+        // Manually reload the reader to ensure that all committed changes
+        // become visible immediately.
+        self.index_reader.reload()?;
+        Ok(())
+    }
+
     pub fn create_in_ram() -> Fallible<Self> {
         let no_path: Option<&Path> = None;
-        Self::create(no_path)
+        Self::create(no_path, Default::default())
     }
 
-    pub fn create<P: AsRef<Path>>(path: Option<P>) -> Fallible<Self> {
-        let (fields, schema) = IndexedEntryFields::build_schema();
+    pub fn create<P: AsRef<Path>>(path: Option<P>, text_language: TextLanguage) -> Fallible<Self> {
+        let (fields, schema) = IndexedEntryFields::build_schema(text_language);
 
-        // TODO: Open index from existing directory
         let index = if let Some(path) = path {
-            info!(
-                "Creating full-text search index in directory: {}",
-                path.as_ref().to_string_lossy()
-            );
-            Index::create_in_dir(path, schema)?
+            open_or_recreate_index_in_dir(
+                path.as_ref(),
+                schema,
+                &IndexMetadata::new(ENTRY_INDEX_SCHEMA_VERSION, text_language),
+            )?
         } else {
             warn!("Creating full-text search index in RAM");
             Index::create_in_ram(schema)
         };
 
-        register_tokenizers(&index);
+        register_tokenizers(&index, text_language);
 
         // Prefer to manually reload the index reader during `flush()`
         // to ensure that all committed changes become visible immediately.
@@ -321,6 +785,7 @@ impl TantivyEntryIndex {
         );
         Ok(Self {
             fields,
+            text_language,
             index,
             index_reader,
             index_writer,
@@ -328,6 +793,31 @@ impl TantivyEntryIndex {
         })
     }
 
+    fn tokenize_text(&self, text: &str) -> Vec<String> {
+        self.tokenize_text_with_positions(text)
+            .into_iter()
+            .map(|(_, token)| token)
+            .collect()
+    }
+
+    /// The positions of the tokens have gaps where stop
+    /// words have been removed.
+    fn tokenize_text_with_positions(&self, text: &str) -> Vec<(usize, String)> {
+        let mut tokens = Vec::new();
+        if let Some(tokenizer) = self
+            .index
+            .tokenizers()
+            .get(self.text_language.tokenizer_name())
+        {
+            let mut token_stream = tokenizer.token_stream(text);
+            while token_stream.advance() {
+                let token = token_stream.token();
+                tokens.push((token.position, token.text.clone()));
+            }
+        }
+        tokens
+    }
+
     fn build_query(&self, query: &EntryIndexQuery) -> BooleanQuery {
         let mut sub_queries: Vec<(Occur, Box<Query>)> = Vec::with_capacity(1 + 2 + 1 + 1 + 1);
 
@@ -355,33 +845,24 @@ impl TantivyEntryIndex {
         // Bbox (include)
         if let Some(ref bbox) = query.include_bbox {
             debug!("Query bbox (include): {}", bbox);
-            debug_assert!(bbox.is_valid());
-            debug_assert!(!bbox.is_empty());
-            let lat_query = RangeQuery::new_i64_bounds(
-                self.fields.lat,
-                Bound::Included(i64::from(bbox.south_west().lat().to_raw())),
-                Bound::Included(i64::from(bbox.north_east().lat().to_raw())),
-            );
-            // Latitude query: Always inclusive
-            sub_queries.push((Occur::Must, Box::new(lat_query)));
-            // Longitude query: Either inclusive or exclusive (wrap around)
-            if bbox.south_west().lng() <= bbox.north_east().lng() {
-                // regular (inclusive)
-                let lng_query = RangeQuery::new_i64_bounds(
-                    self.fields.lng,
-                    Bound::Included(i64::from(bbox.south_west().lng().to_raw())),
-                    Bound::Included(i64::from(bbox.north_east().lng().to_raw())),
-                );
-                sub_queries.push((Occur::Must, Box::new(lng_query)));
-            } else {
-                // inverse (exclusive)
-                let lng_query = RangeQuery::new_i64_bounds(
-                    self.fields.lng,
-                    Bound::Excluded(i64::from(bbox.north_east().lng().to_raw())),
-                    Bound::Excluded(i64::from(bbox.south_west().lng().to_raw())),
-                );
-                sub_queries.push((Occur::MustNot, Box::new(lng_query)));
-            }
+            sub_queries.extend(bbox_sub_queries(self.fields.lat, self.fields.lng, bbox));
+        }
+
+        // Radius: The bounding box around the circle is used for
+        // prefiltering, the exact distance is checked while collecting
+        if let Some((center, radius)) = query.radius {
+            debug!("Query radius: {} m around {}", radius.to_meters(), center);
+            let bbox = MapBbox::centered_around(center, radius);
+            sub_queries.extend(bbox_sub_queries(self.fields.lat, self.fields.lng, &bbox));
+        }
+
+        // Area: The bounding box of the polygon(s) is used for
+        // prefiltering, the exact position is checked while collecting
+        if let Some(ref area) = query.area {
+            debug!("Query area: {:?}", area);
+            debug_assert!(area.is_valid());
+            let bbox = area.bbox();
+            sub_queries.extend(bbox_sub_queries(self.fields.lat, self.fields.lng, &bbox));
         }
 
         // Inverse Bbox (exclude)
@@ -416,6 +897,42 @@ impl TantivyEntryIndex {
             }
         }
 
+        // Minimum ratings
+        if let Some(min_rating) = query.min_rating {
+            debug!("Query minimum total rating: {:?}", min_rating);
+            let rating_query = RangeQuery::new_u64_bounds(
+                self.fields.total_rating,
+                Bound::Included(avg_rating_to_u64(min_rating)),
+                Bound::Unbounded,
+            );
+            sub_queries.push((Occur::Must, Box::new(rating_query)));
+        }
+        for (context, min_rating) in &query.min_context_ratings {
+            debug!("Query minimum {:?} rating: {:?}", context, min_rating);
+            let rating_query = RangeQuery::new_u64_bounds(
+                self.fields.rating_field(*context),
+                Bound::Included(avg_rating_to_u64(*min_rating)),
+                Bound::Unbounded,
+            );
+            sub_queries.push((Occur::Must, Box::new(rating_query)));
+        }
+
+        // Opening hours
+        if let Some(open_at) = query.open_at {
+            debug!("Query open at: {}", open_at);
+            let period_queries: Vec<(Occur, Box<Query>)> = opening_hours::periods_of_week(open_at)
+                .iter()
+                .map(|period| {
+                    let period_term =
+                        Term::from_field_u64(self.fields.open_period, u64::from(*period));
+                    let period_query: Box<Query> =
+                        Box::new(TermQuery::new(period_term, IndexRecordOption::Basic));
+                    (Occur::Should, period_query)
+                })
+                .collect();
+            sub_queries.push((Occur::Must, Box::new(BooleanQuery::from(period_queries))));
+        }
+
         // Categories
         if !query.categories.is_empty() {
             let categories_query: Box<Query> = if query.categories.len() > 1 {
@@ -441,6 +958,26 @@ impl TantivyEntryIndex {
             sub_queries.push((Occur::Must, categories_query));
         }
 
+        // Categories (mandatory)
+        for category in &query.required_categories {
+            debug!("Query category (mandatory): {}", category);
+            debug_assert!(!category.trim().is_empty());
+            let category_term =
+                Term::from_field_text(self.fields.category, &category.to_lowercase());
+            let category_query = TermQuery::new(category_term, IndexRecordOption::Basic);
+            sub_queries.push((Occur::Must, Box::new(category_query)));
+        }
+
+        // Categories (excluded)
+        for category in &query.excluded_categories {
+            debug!("Query category (excluded): {}", category);
+            debug_assert!(!category.trim().is_empty());
+            let category_term =
+                Term::from_field_text(self.fields.category, &category.to_lowercase());
+            let category_query = TermQuery::new(category_term, IndexRecordOption::Basic);
+            sub_queries.push((Occur::MustNot, Box::new(category_query)));
+        }
+
         // Hash tags (mandatory)
         for tag in &query.hash_tags {
             debug!("Query hash tag (mandatory): {}", tag);
@@ -450,6 +987,58 @@ impl TantivyEntryIndex {
             sub_queries.push((Occur::Must, Box::new(tag_query)));
         }
 
+        // Hash tags (alternatives)
+        for tags in &query.hash_tag_alternatives {
+            debug!("Query hash tags (alternatives): {:?}", tags);
+            let tag_queries: Vec<(Occur, Box<Query>)> = tags
+                .iter()
+                .map(|tag| {
+                    debug_assert!(!tag.trim().is_empty());
+                    let tag_term = Term::from_field_text(self.fields.tag, &tag.to_lowercase());
+                    let tag_query: Box<Query> =
+                        Box::new(TermQuery::new(tag_term, IndexRecordOption::Basic));
+                    (Occur::Should, tag_query)
+                })
+                .collect();
+            sub_queries.push((Occur::Must, Box::new(BooleanQuery::from(tag_queries))));
+        }
+
+        // Hash tags (excluded)
+        for tag in &query.excluded_hash_tags {
+            debug!("Query hash tag (excluded): {}", tag);
+            debug_assert!(!tag.trim().is_empty());
+            let tag_term = Term::from_field_text(self.fields.tag, &tag.to_lowercase());
+            let tag_query = TermQuery::new(tag_term, IndexRecordOption::Basic);
+            sub_queries.push((Occur::MustNot, Box::new(tag_query)));
+        }
+
+        // Phrases
+        for phrase in &query.phrases {
+            debug!("Query phrase: {}", phrase);
+            let tokens = self.tokenize_text_with_positions(phrase);
+            if tokens.is_empty() {
+                continue;
+            }
+            let mut phrase_queries: Vec<(Occur, Box<Query>)> = Vec::with_capacity(2);
+            for field in &[self.fields.title, self.fields.description] {
+                let mut terms: Vec<_> = tokens
+                    .iter()
+                    .map(|(position, token)| (*position, Term::from_field_text(*field, token)))
+                    .collect();
+                // The positions must match those of the indexed tokens
+                // that have gaps where stop words have been removed
+                let phrase_query: Box<Query> = if terms.len() > 1 {
+                    Box::new(PhraseQuery::new_with_offset(terms))
+                } else if let Some((_, term)) = terms.pop() {
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+                } else {
+                    continue;
+                };
+                phrase_queries.push((Occur::Should, phrase_query));
+            }
+            sub_queries.push((Occur::Must, Box::new(BooleanQuery::from(phrase_queries))));
+        }
+
         let mut text_and_tags_queries: Vec<(Occur, Box<Query>)> =
             Vec::with_capacity(1 + query.text_tags.len());
 
@@ -459,7 +1048,7 @@ impl TantivyEntryIndex {
             debug_assert!(!text.trim().is_empty());
             match self.text_query_parser.parse_query(&text.to_lowercase()) {
                 Ok(text_query) => {
-                    if query.hash_tags.is_empty() && query.text_tags.is_empty() {
+                    if query.hash_tags.is_empty() && query.text_tags.is_empty() && !query.fuzzy {
                         sub_queries.push((Occur::Must, Box::new(text_query)));
                     } else {
                         text_and_tags_queries.push((Occur::Should, Box::new(text_query)));
@@ -469,6 +1058,20 @@ impl TantivyEntryIndex {
                     warn!("Failed to parse query text '{}': {:?}", text, err);
                 }
             }
+            // Fuzzy matches only complement the exact matches of the
+            // text query which will thereby receive a higher score
+            if query.fuzzy {
+                for token in self.tokenize_text(text) {
+                    if let Some(distance) = fuzzy_distance(&token) {
+                        debug!("Query text token (fuzzy): {}", token);
+                        for field in &[self.fields.title, self.fields.description] {
+                            let term = Term::from_field_text(*field, &token);
+                            let fuzzy_query = FuzzyTermQuery::new(term, distance, true);
+                            text_and_tags_queries.push((Occur::Should, Box::new(fuzzy_query)));
+                        }
+                    }
+                }
+            }
         }
 
         // Text tags (optional)
@@ -476,8 +1079,14 @@ impl TantivyEntryIndex {
             debug!("Query text tag (optional): {}", tag);
             debug_assert!(!tag.trim().is_empty());
             let tag_term = Term::from_field_text(self.fields.tag, &tag.to_lowercase());
-            let tag_query = TermQuery::new(tag_term, IndexRecordOption::Basic);
+            let tag_query = TermQuery::new(tag_term.clone(), IndexRecordOption::Basic);
             text_and_tags_queries.push((Occur::Should, Box::new(tag_query)));
+            if query.fuzzy {
+                if let Some(distance) = fuzzy_distance(tag) {
+                    let fuzzy_query = FuzzyTermQuery::new(tag_term, distance, true);
+                    text_and_tags_queries.push((Occur::Should, Box::new(fuzzy_query)));
+                }
+            }
         }
 
         if !text_and_tags_queries.is_empty() {
@@ -487,8 +1096,45 @@ impl TantivyEntryIndex {
             ));
         }
 
+        // Excluding queries alone would not match any documents
+        if !sub_queries.is_empty()
+            && sub_queries.iter().all(|(occur, _)| *occur == Occur::MustNot)
+        {
+            sub_queries.push((Occur::Must, Box::new(AllQuery)));
+        }
+
         BooleanQuery::from(sub_queries)
     }
+
+    /// Executes the search while applying the exact position
+    /// filters of the query that cannot be expressed as a
+    /// tantivy query.
+    fn search<C: Collector>(
+        &self,
+        searcher: &Searcher,
+        search_query: &Query,
+        query: &EntryIndexQuery,
+        collector: C,
+    ) -> tantivy::Result<C::Fruit> {
+        let mut position_filters = Vec::with_capacity(2);
+        if let Some((center, radius)) = query.radius {
+            position_filters.push(PositionFilter::WithinRadius(center, radius));
+        }
+        if let Some(ref area) = query.area {
+            position_filters.push(PositionFilter::WithinArea(Arc::new(area.clone())));
+        }
+        if position_filters.is_empty() {
+            searcher.search(search_query, &collector)
+        } else {
+            let collector = FilterByPosition::new(
+                position_filters,
+                self.fields.lat,
+                self.fields.lng,
+                collector,
+            );
+            searcher.search(search_query, &collector)
+        }
+    }
 }
 
 impl EntryIndexer for TantivyEntryIndex {
@@ -505,7 +1151,11 @@ impl EntryIndexer for TantivyEntryIndex {
             self.fields.lng,
             i64::from(entry.location.pos.lng().to_raw()),
         );
+        doc.add_i64(self.fields.created, i64::from(entry.created));
         doc.add_text(self.fields.title, &entry.title);
+        if !entry.title.is_empty() {
+            doc.add_text(self.fields.title_suggestion, &suggestion_key(&entry.title));
+        }
         doc.add_text(self.fields.description, &entry.description);
         if let Some(street) = entry
             .location
@@ -541,9 +1191,20 @@ impl EntryIndexer for TantivyEntryIndex {
         }
         for category in &entry.categories {
             doc.add_text(self.fields.category, category);
+            doc.add_facet(
+                self.fields.facet,
+                Facet::from_path(vec![CATEGORY_FACET, category]),
+            );
         }
         for tag in &entry.tags {
             doc.add_text(self.fields.tag, tag);
+            doc.add_facet(self.fields.facet, Facet::from_path(vec![TAG_FACET, tag]));
+            doc.add_text(self.fields.tag_suggestion, &suggestion_key(tag));
+        }
+        if let Some(ref opening_hours) = entry.opening_hours {
+            for period in opening_hours.open_periods_of_week() {
+                doc.add_u64(self.fields.open_period, u64::from(period));
+            }
         }
         doc.add_u64(self.fields.total_rating, avg_rating_to_u64(ratings.total()));
         doc.add_u64(
@@ -581,90 +1242,636 @@ impl EntryIndexer for TantivyEntryIndex {
     }
 
     fn flush(&mut self) -> Fallible<()> {
-        self.index_writer.commit()?;
-        // Manually reload the reader to ensure that all committed changes
-        // become visible immediately.
-        self.index_reader.reload()?;
-        Ok(())
+        // Keep the high water mark of the last commit
+        let high_water_mark = self.high_water_mark()?;
+        self.commit(high_water_mark)
+    }
+
+    fn flush_with_high_water_mark(&mut self, high_water_mark: Timestamp) -> Fallible<()> {
+        self.commit(Some(high_water_mark))
+    }
+
+    fn high_water_mark(&self) -> Fallible<Option<Timestamp>> {
+        Ok(IndexMetadata::load(&self.index)?
+            .and_then(|metadata| metadata.high_water_mark)
+            .map(Timestamp::from))
     }
 }
 
 impl EntryIndex for TantivyEntryIndex {
-    fn query_entries(&self, query: &EntryIndexQuery, limit: usize) -> Fallible<Vec<IndexedEntry>> {
+    fn query_entries_page(
+        &self,
+        query: &EntryIndexQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Fallible<IndexedEntriesPage> {
         if limit <= 0 {
             bail!("Invalid limit: {}", limit);
         }
 
+        let top_limit = match offset.checked_add(limit) {
+            Some(top_limit) => top_limit,
+            None => bail!("Invalid offset: {}", offset),
+        };
+
         let searcher = self.index_reader.searcher();
-        // TODO (2019-02-26): Ideally we would like to order the results by
-        // (score * rating) instead of only (rating). Currently Tantivy doesn't
-        // support this kind of collector.
-        // See also: https://github.com/slowtec/openfairdb/issues/186
-        let collector = TopDocs::with_limit(limit).order_by_field(self.fields.total_rating);
-        let top_docs_by_rating: Vec<(u64, DocAddress)> =
-            searcher.search(&self.build_query(query), &collector)?;
-        let mut entries = Vec::with_capacity(top_docs_by_rating.len());
-        for (_total_rating, doc_addr) in top_docs_by_rating {
+        let search_query = self.build_query(query);
+        let (doc_addrs, total_count) = match query.sort {
+            EntrySortOrder::Rating => {
+                let collector = (
+                    TopDocs::with_limit(top_limit).order_by_field(self.fields.total_rating),
+                    Count,
+                );
+                let (top_docs, total_count): (Vec<(u64, DocAddress)>, usize) =
+                    self.search(&searcher, &search_query, query, collector)?;
+                (into_ordered_doc_addrs(top_docs, Ordering::Greater), total_count)
+            }
+            EntrySortOrder::Relevance => {
+                let collector = (TopDocs::with_limit(top_limit), Count);
+                let (top_docs, total_count): (Vec<(Score, DocAddress)>, usize) =
+                    self.search(&searcher, &search_query, query, collector)?;
+                (into_ordered_doc_addrs(top_docs, Ordering::Greater), total_count)
+            }
+            EntrySortOrder::Distance(center) => {
+                let collector = (
+                    TopDocsByDistance::new(center, top_limit, self.fields.lat, self.fields.lng),
+                    Count,
+                );
+                let (top_docs, total_count): (Vec<(f64, DocAddress)>, usize) =
+                    self.search(&searcher, &search_query, query, collector)?;
+                (into_ordered_doc_addrs(top_docs, Ordering::Less), total_count)
+            }
+            EntrySortOrder::Newest => {
+                let collector = (
+                    TopDocs::with_limit(top_limit).order_by_field(self.fields.created),
+                    Count,
+                );
+                let (top_docs, total_count): (Vec<(i64, DocAddress)>, usize) =
+                    self.search(&searcher, &search_query, query, collector)?;
+                (into_ordered_doc_addrs(top_docs, Ordering::Greater), total_count)
+            }
+        };
+        let reference_point = match (query.radius, query.sort) {
+            (Some((center, _)), _) | (None, EntrySortOrder::Distance(center)) => Some(center),
+            _ => None,
+        };
+        let snippet_generators = if query.highlight {
+            let mut title_generator =
+                SnippetGenerator::create(&searcher, &search_query, self.fields.title)?;
+            title_generator.set_max_num_chars(TITLE_SNIPPET_MAX_CHARS);
+            let mut description_generator =
+                SnippetGenerator::create(&searcher, &search_query, self.fields.description)?;
+            description_generator.set_max_num_chars(DESCRIPTION_SNIPPET_MAX_CHARS);
+            Some((title_generator, description_generator))
+        } else {
+            None
+        };
+        let mut entries = Vec::with_capacity(limit.min(doc_addrs.len()));
+        for doc_addr in doc_addrs.into_iter().skip(offset) {
+            match searcher.doc(doc_addr) {
+                Ok(ref doc) => {
+                    let mut entry = self.fields.read_document(doc);
+                    entry.distance =
+                        reference_point.and_then(|center| MapPoint::distance(center, entry.pos));
+                    if let Some((ref title_generator, ref description_generator)) =
+                        snippet_generators
+                    {
+                        entry.snippets = Some(EntrySnippets {
+                            title: snippet_to_html(title_generator.snippet_from_doc(doc)),
+                            description: snippet_to_html(
+                                description_generator.snippet_from_doc(doc),
+                            ),
+                        });
+                    }
+                    entries.push(entry);
+                }
+                Err(err) => {
+                    warn!("Failed to load document {:?}: {}", doc_addr, err);
+                }
+            }
+        }
+        Ok(IndexedEntriesPage {
+            entries,
+            total_count,
+        })
+    }
+
+    fn query_facets(&self, query: &EntryIndexQuery, max_tags: usize) -> Fallible<EntryFacets> {
+        let searcher = self.index_reader.searcher();
+        let mut collector = FacetCollector::for_field(self.fields.facet);
+        collector.add_facet(facet_root(CATEGORY_FACET));
+        collector.add_facet(facet_root(TAG_FACET));
+        let search_query = self.build_query(query);
+        let facet_counts = self.search(&searcher, &search_query, query, collector)?;
+        let categories = facet_counts
+            .get(facet_root(CATEGORY_FACET))
+            .map(|(facet, count)| (facet_child(CATEGORY_FACET, facet), count as usize))
+            .collect();
+        let tags = if max_tags > 0 {
+            facet_counts
+                .top_k(facet_root(TAG_FACET), max_tags)
+                .into_iter()
+                .map(|(facet, count)| (facet_child(TAG_FACET, facet), count as usize))
+                .collect()
+        } else {
+            vec![]
+        };
+        Ok(EntryFacets { categories, tags })
+    }
+
+    fn query_clusters(
+        &self,
+        query: &EntryIndexQuery,
+        grid: &ClusterGrid,
+    ) -> Fallible<Vec<EntryCluster>> {
+        if !(grid.lat_deg > 0.0 && grid.lng_deg > 0.0) {
+            bail!("Invalid cluster grid: {:?}", grid);
+        }
+        // Convert the cell size into raw coordinates
+        let cell_size = (
+            i64::from(LatCoord::from_deg(grid.lat_deg.min(LatCoord::max().to_deg())).to_raw())
+                .max(1),
+            i64::from(LngCoord::from_deg(grid.lng_deg.min(LngCoord::max().to_deg())).to_raw())
+                .max(1),
+        );
+        let searcher = self.index_reader.searcher();
+        let search_query = self.build_query(query);
+        let collector = GroupByGridCell::new(
+            cell_size,
+            self.fields.lat,
+            self.fields.lng,
+            self.fields.total_rating,
+        );
+        let cells = self.search(&searcher, &search_query, query, collector)?;
+        let mut clusters = Vec::with_capacity(cells.len());
+        for cell in cells.values() {
+            let (_, doc_addr) = cell.top_doc;
             match searcher.doc(doc_addr) {
                 Ok(ref doc) => {
-                    entries.push(self.fields.read_document(doc));
+                    clusters.push(EntryCluster {
+                        center: cell.center(),
+                        count: cell.count,
+                        top_entry: self.fields.read_document(doc),
+                    });
                 }
                 Err(err) => {
                     warn!("Failed to load document {:?}: {}", doc_addr, err);
                 }
             }
         }
-        Ok(entries)
+        clusters.sort_by(|lhs, rhs| {
+            rhs.count
+                .cmp(&lhs.count)
+                .then_with(|| lhs.top_entry.id.cmp(&rhs.top_entry.id))
+        });
+        Ok(clusters)
+    }
+
+    fn suggest(
+        &self,
+        kind: SuggestionKind,
+        prefix: &str,
+        bbox: Option<&MapBbox>,
+        limit: usize,
+    ) -> Fallible<Vec<(String, usize)>> {
+        if limit == 0 {
+            bail!("Invalid limit: {}", limit);
+        }
+        let field = match kind {
+            SuggestionKind::Tag => self.fields.tag_suggestion,
+            SuggestionKind::Title => self.fields.title_suggestion,
+        };
+        let searcher = self.index_reader.searcher();
+        let mut counts = HashMap::new();
+        // Case-insensitive matching that ignores umlauts and accents
+        let prefix = fold_to_ascii(&prefix.to_lowercase());
+        let upper_bound = prefix_upper_bound(prefix.as_bytes());
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field);
+            let lat_reader = segment_reader.fast_field_reader::<i64>(self.fields.lat)?;
+            let lng_reader = segment_reader.fast_field_reader::<i64>(self.fields.lng)?;
+            // Only the range of terms that start with the prefix is read
+            let mut terms = inverted_index.terms().range().ge(prefix.as_bytes());
+            if let Some(ref upper_bound) = upper_bound {
+                terms = terms.lt(upper_bound);
+            }
+            let mut terms = terms.into_stream();
+            while terms.advance() {
+                let mut postings = inverted_index
+                    .read_postings_from_terminfo(terms.value(), IndexRecordOption::Basic);
+                let mut count = 0;
+                while postings.advance() {
+                    let doc = postings.doc();
+                    if segment_reader.is_deleted(doc) {
+                        continue;
+                    }
+                    if let Some(bbox) = bbox {
+                        let pos = MapPoint::new(
+                            LatCoord::from_raw(lat_reader.get(doc) as RawCoord),
+                            LngCoord::from_raw(lng_reader.get(doc) as RawCoord),
+                        );
+                        if !bbox.contains_point(pos) {
+                            continue;
+                        }
+                    }
+                    count += 1;
+                }
+                if count > 0 {
+                    let value = suggestion_value(terms.key())?;
+                    *counts.entry(value).or_insert(0) += count;
+                }
+            }
+        }
+        let mut suggestions: Vec<_> = counts.into_iter().collect();
+        suggestions.sort_by(|(lhs_value, lhs_count), (rhs_value, rhs_count)| {
+            rhs_count
+                .cmp(lhs_count)
+                .then_with(|| lhs_value.cmp(rhs_value))
+        });
+        suggestions.truncate(limit);
+        Ok(suggestions)
     }
 }
 
 #[derive(Clone)]
-pub struct SearchEngine(Arc<Mutex<Box<dyn EntryIndexer + Send>>>);
+pub struct SearchEngine {
+    entry_index: Arc<Mutex<Box<dyn EntryIndexer + Send>>>,
+    event_index: Arc<Mutex<Box<dyn EventIndexer + Send>>>,
+    /// `None` if the entry index is kept in RAM
+    entry_index_path: Option<PathBuf>,
+    text_language: TextLanguage,
+    rebuild_status: Arc<Mutex<RebuildStatus>>,
+}
 
 impl SearchEngine {
+    fn new<E, V>(
+        entry_index: E,
+        event_index: V,
+        entry_index_path: Option<PathBuf>,
+        text_language: TextLanguage,
+    ) -> Self
+    where
+        E: EntryIndexer + Send + 'static,
+        V: EventIndexer + Send + 'static,
+    {
+        Self {
+            entry_index: Arc::new(Mutex::new(Box::new(entry_index))),
+            event_index: Arc::new(Mutex::new(Box::new(event_index))),
+            entry_index_path,
+            text_language,
+            rebuild_status: Default::default(),
+        }
+    }
+
     pub fn init_in_ram() -> Fallible<SearchEngine> {
         let entry_index = TantivyEntryIndex::create_in_ram()?;
-        Ok(SearchEngine(Arc::new(Mutex::new(Box::new(entry_index)))))
+        let event_index = TantivyEventIndex::create_in_ram()?;
+        Ok(SearchEngine::new(
+            entry_index,
+            event_index,
+            None,
+            Default::default(),
+        ))
+    }
+
+    /// Opens or creates the indexes in the given directory.
+    ///
+    /// Fails if the indexes are in use by another process, e.g.
+    /// by a running server. The entry index can only be rebuilt
+    /// by the process that uses it.
+    pub fn init_with_path<P: AsRef<Path>>(
+        path: Option<P>,
+        text_language: TextLanguage,
+    ) -> Fallible<SearchEngine> {
+        let path: Option<&Path> = path.as_ref().map(AsRef::as_ref);
+        let entry_dir_name = match path {
+            Some(path) => {
+                fs::create_dir_all(path)?;
+                Some(read_current_entry_index_dir_name(path)?)
+            }
+            None => None,
+        };
+        let entry_path = path.and_then(|path| entry_dir_name.as_ref().map(|name| path.join(name)));
+        let event_path = path.map(|path| path.join(EVENT_INDEX_DIR_NAME));
+        let entry_index = TantivyEntryIndex::create(entry_path, text_language)?;
+        let event_index = TantivyEventIndex::create(event_path, text_language)?;
+        let search_engine = SearchEngine::new(
+            entry_index,
+            event_index,
+            path.map(Path::to_path_buf),
+            text_language,
+        );
+        if let (Some(path), Some(entry_dir_name)) = (path, entry_dir_name) {
+            // The writer of the current entry index holds a lock that
+            // prevents other processes from using the same directory
+            if let Some(_rebuild_guard) = search_engine.try_lock_rebuild() {
+                remove_outdated_entry_indexes(path, &entry_dir_name)?;
+            }
+        }
+        Ok(search_engine)
+    }
+
+    /// Prevents concurrent rebuilds of the entry index until the
+    /// returned guard is dropped. Returns `None` if the entry index
+    /// is currently being rebuilt.
+    pub fn try_lock_rebuild(&self) -> Option<RebuildGuard> {
+        let mut rebuild_status = lock_index(&self.rebuild_status);
+        if rebuild_status.in_progress {
+            return None;
+        }
+        *rebuild_status = RebuildStatus {
+            in_progress: true,
+            started: Some(Timestamp::now()),
+            ..Default::default()
+        };
+        Some(RebuildGuard(Arc::clone(&self.rebuild_status)))
+    }
+
+    /// The status of the current or the most recent rebuild.
+    pub fn rebuild_status(&self) -> RebuildStatus {
+        lock_index(&self.rebuild_status).clone()
+    }
+
+    /// Creates a new, empty entry index in a separate directory for
+    /// rebuilding the current index from scratch. The current index
+    /// remains accessible until it is replaced by the new index.
+    pub(crate) fn create_entry_index_for_rebuild(&self) -> Fallible<RebuiltEntryIndex> {
+        let dir_name = self
+            .entry_index_path
+            .as_ref()
+            .map(|_| format!("{}-{}", ENTRY_INDEX_DIR_NAME, Uuid::new_v4().to_simple_ref()));
+        let rebuild_path = self
+            .entry_index_path
+            .as_ref()
+            .and_then(|path| dir_name.as_ref().map(|dir_name| path.join(dir_name)));
+        let index = TantivyEntryIndex::create(rebuild_path, self.text_language)?;
+        Ok(RebuiltEntryIndex { index, dir_name })
     }
 
-    pub fn init_with_path<P: AsRef<Path>>(path: Option<P>) -> Fallible<SearchEngine> {
-        let entry_index = TantivyEntryIndex::create(path)?;
-        Ok(SearchEngine(Arc::new(Mutex::new(Box::new(entry_index)))))
+    /// Replaces the current entry index with a rebuilt index that
+    /// has been created by `create_entry_index_for_rebuild()`. All
+    /// pending changes of the rebuilt index are committed before.
+    ///
+    /// Queries are blocked until the index has been replaced, i.e.
+    /// they either see the contents of the current or of the new
+    /// index. If the new index could not be activated the current
+    /// index is kept.
+    pub(crate) fn replace_entry_index(&self, rebuilt_index: RebuiltEntryIndex) -> Fallible<()> {
+        let RebuiltEntryIndex {
+            index: mut rebuilt_index,
+            dir_name,
+        } = rebuilt_index;
+        let (path, dir_name) = match (self.entry_index_path.as_ref(), dir_name) {
+            (Some(path), Some(dir_name)) => (path, dir_name),
+            _ => {
+                rebuilt_index.flush()?;
+                *lock_index(&self.entry_index) = Box::new(rebuilt_index);
+                return Ok(());
+            }
+        };
+        let activated = rebuilt_index.flush().and_then(|()| {
+            let mut entry_index = lock_index(&self.entry_index);
+            let replaced_dir_name = read_current_entry_index_dir_name(path)?;
+            write_current_entry_index_dir_name(path, &dir_name)?;
+            let replaced_index = mem::replace(&mut *entry_index, Box::new(rebuilt_index));
+            Ok((replaced_index, replaced_dir_name))
+        });
+        // Unused directories are also deleted on the next startup
+        let obsolete_dir_name = match activated {
+            Ok((replaced_index, replaced_dir_name)) => {
+                drop(replaced_index);
+                replaced_dir_name
+            }
+            Err(err) => {
+                if let Err(err) = fs::remove_dir_all(path.join(&dir_name)) {
+                    warn!("Failed to delete the rebuilt full-text search index: {}", err);
+                }
+                return Err(err);
+            }
+        };
+        if let Err(err) = fs::remove_dir_all(path.join(&obsolete_dir_name)) {
+            warn!("Failed to delete the replaced full-text search index: {}", err);
+        }
+        Ok(())
     }
 }
 
+/// The progress and outcome of rebuilding the entry index.
+#[derive(Debug, Clone, Default)]
+pub struct RebuildStatus {
+    pub in_progress: bool,
+    pub started: Option<Timestamp>,
+    pub finished: Option<Timestamp>,
+    pub indexed_count: usize,
+    pub total_count: usize,
+    /// `None` unless the rebuild has failed
+    pub error: Option<String>,
+}
+
+/// Exclusive permission to rebuild the entry index that
+/// records the progress of the rebuild.
+pub struct RebuildGuard(Arc<Mutex<RebuildStatus>>);
+
+impl RebuildGuard {
+    pub fn report_progress(&self, indexed_count: usize, total_count: usize) {
+        let mut rebuild_status = lock_index(&self.0);
+        rebuild_status.indexed_count = indexed_count;
+        rebuild_status.total_count = total_count;
+    }
+
+    pub fn report_error(&self, error: String) {
+        lock_index(&self.0).error = Some(error);
+    }
+}
+
+impl Drop for RebuildGuard {
+    fn drop(&mut self) {
+        let mut rebuild_status = lock_index(&self.0);
+        rebuild_status.in_progress = false;
+        rebuild_status.finished = Some(Timestamp::now());
+    }
+}
+
+/// A new entry index that is built in a separate directory
+/// while the current entry index remains accessible.
+pub struct RebuiltEntryIndex {
+    pub(crate) index: TantivyEntryIndex,
+    /// `None` if the entry index is kept in RAM
+    dir_name: Option<String>,
+}
+
 impl EntryIndex for SearchEngine {
-    fn query_entries(&self, query: &EntryIndexQuery, limit: usize) -> Fallible<Vec<IndexedEntry>> {
-        let entry_index = match self.0.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        entry_index.query_entries(query, limit)
+    fn query_entries_page(
+        &self,
+        query: &EntryIndexQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Fallible<IndexedEntriesPage> {
+        lock_index(&self.entry_index).query_entries_page(query, offset, limit)
+    }
+
+    fn query_facets(&self, query: &EntryIndexQuery, max_tags: usize) -> Fallible<EntryFacets> {
+        lock_index(&self.entry_index).query_facets(query, max_tags)
+    }
+
+    fn query_clusters(
+        &self,
+        query: &EntryIndexQuery,
+        grid: &ClusterGrid,
+    ) -> Fallible<Vec<EntryCluster>> {
+        lock_index(&self.entry_index).query_clusters(query, grid)
+    }
+
+    fn suggest(
+        &self,
+        kind: SuggestionKind,
+        prefix: &str,
+        bbox: Option<&MapBbox>,
+        limit: usize,
+    ) -> Fallible<Vec<(String, usize)>> {
+        lock_index(&self.entry_index).suggest(kind, prefix, bbox, limit)
     }
 }
 
 impl EntryIndexer for SearchEngine {
     fn add_or_update_entry(&mut self, entry: &Entry, ratings: &AvgRatings) -> Fallible<()> {
-        let mut inner = match self.0.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        inner.add_or_update_entry(entry, ratings)
+        lock_index(&self.entry_index).add_or_update_entry(entry, ratings)
     }
 
     fn remove_entry_by_id(&mut self, id: &str) -> Fallible<()> {
-        let mut inner = match self.0.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        inner.remove_entry_by_id(id)
+        lock_index(&self.entry_index).remove_entry_by_id(id)
     }
 
     fn flush(&mut self) -> Fallible<()> {
-        let mut inner = match self.0.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        inner.flush()
+        lock_index(&self.entry_index).flush()
+    }
+
+    fn flush_with_high_water_mark(&mut self, high_water_mark: Timestamp) -> Fallible<()> {
+        lock_index(&self.entry_index).flush_with_high_water_mark(high_water_mark)
+    }
+
+    fn high_water_mark(&self) -> Fallible<Option<Timestamp>> {
+        lock_index(&self.entry_index).high_water_mark()
+    }
+}
+
+impl EventIndex for SearchEngine {
+    fn query_events(
+        &self,
+        query: &EventIndexQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Fallible<Vec<String>> {
+        lock_index(&self.event_index).query_events(query, offset, limit)
+    }
+}
+
+impl EventIndexer for SearchEngine {
+    fn add_or_update_event(&mut self, event: &Event) -> Fallible<()> {
+        lock_index(&self.event_index).add_or_update_event(event)
+    }
+
+    fn remove_event_by_id(&mut self, id: &str) -> Fallible<()> {
+        lock_index(&self.event_index).remove_event_by_id(id)
+    }
+
+    fn remove_all_events(&mut self) -> Fallible<()> {
+        lock_index(&self.event_index).remove_all_events()
+    }
+
+    fn flush_events(&mut self) -> Fallible<()> {
+        lock_index(&self.event_index).flush_events()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse_index_with_compatible_metadata() {
+        let metadata = IndexMetadata::new(ENTRY_INDEX_SCHEMA_VERSION, TextLanguage::German);
+        assert!(metadata.is_compatible_with(&metadata.clone().with_high_water_mark(1.into())));
+        assert!(!metadata.is_compatible_with(&IndexMetadata::new(
+            ENTRY_INDEX_SCHEMA_VERSION + 1,
+            TextLanguage::German
+        )));
+        assert!(!metadata.is_compatible_with(&IndexMetadata::new(
+            ENTRY_INDEX_SCHEMA_VERSION,
+            TextLanguage::English
+        )));
+    }
+
+    #[test]
+    fn only_remove_files_of_the_index() {
+        for file_name in &[
+            "meta.json",
+            ".managed.json",
+            ".tantivy-meta.lock",
+            ".tantivy-writer.lock",
+            "0a1b2c3d4e5f60718293a4b5c6d7e8f9.idx",
+            "0a1b2c3d4e5f60718293a4b5c6d7e8f9.12.del",
+        ] {
+            assert!(is_index_file_name(file_name), "{}", file_name);
+        }
+        for file_name in &[
+            "events",
+            "entries",
+            "entries.current",
+            "README.md",
+            "backup.json",
+            "0a1b2c3d4e5f60718293a4b5c6d7e8f9",
+            "xa1b2c3d4e5f60718293a4b5c6d7e8f9.idx",
+        ] {
+            assert!(!is_index_file_name(file_name), "{}", file_name);
+        }
+    }
+
+    #[test]
+    fn suggestion_keys_by_prefix() {
+        let key = suggestion_key("Café Müller");
+        assert!(key.starts_with("cafe muller"));
+        assert_eq!("Café Müller", suggestion_value(key.as_bytes()).unwrap());
+        assert_eq!(Some(b"cafe".to_vec()), prefix_upper_bound(b"cafd"));
+        assert_eq!(Some(b"b".to_vec()), prefix_upper_bound(b"a\xff"));
+        assert_eq!(None, prefix_upper_bound(b"\xff\xff"));
+        assert_eq!(None, prefix_upper_bound(b""));
+    }
+
+    #[test]
+    fn high_water_mark_after_flush() {
+        let mut index = TantivyEntryIndex::create_in_ram().unwrap();
+        assert_eq!(None, index.high_water_mark().unwrap());
+        index.flush().unwrap();
+        assert_eq!(None, index.high_water_mark().unwrap());
+        index.flush_with_high_water_mark(100.into()).unwrap();
+        assert_eq!(Some(100.into()), index.high_water_mark().unwrap());
+        // Flushing again keeps the high water mark
+        index.flush().unwrap();
+        assert_eq!(Some(100.into()), index.high_water_mark().unwrap());
+    }
+
+    #[test]
+    fn replace_entry_index_in_dir() {
+        let path = std::env::temp_dir().join(format!("ofdb-{}", Uuid::new_v4().to_simple_ref()));
+        let mut search_engine =
+            SearchEngine::init_with_path(Some(&path), Default::default()).unwrap();
+        search_engine.flush_with_high_water_mark(100.into()).unwrap();
+        assert!(path.join(ENTRY_INDEX_DIR_NAME).is_dir());
+
+        let mut rebuilt_index = search_engine.create_entry_index_for_rebuild().unwrap();
+        rebuilt_index.index.flush_with_high_water_mark(200.into()).unwrap();
+        let dir_name = rebuilt_index.dir_name.clone().unwrap();
+        search_engine.replace_entry_index(rebuilt_index).unwrap();
+        assert_eq!(Some(200.into()), search_engine.high_water_mark().unwrap());
+        assert_eq!(dir_name, read_current_entry_index_dir_name(&path).unwrap());
+        assert!(!path.join(ENTRY_INDEX_DIR_NAME).exists());
+        assert!(path.join(EVENT_INDEX_DIR_NAME).is_dir());
+        drop(search_engine);
+
+        let search_engine = SearchEngine::init_with_path(Some(&path), Default::default()).unwrap();
+        assert_eq!(Some(200.into()), search_engine.high_water_mark().unwrap());
+        drop(search_engine);
+        fs::remove_dir_all(&path).unwrap();
     }
 }
@@ -0,0 +1,218 @@
+use std::{fmt, str::FromStr};
+use tantivy::tokenizer::{
+    Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, StopWordFilter, Token,
+    TokenFilter, TokenStream, Tokenizer, TokenizerManager,
+};
+
+/// The language of the indexed texts that determines
+/// both stemming and stop words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextLanguage {
+    German,
+    English,
+}
+
+impl Default for TextLanguage {
+    fn default() -> Self {
+        TextLanguage::German
+    }
+}
+
+impl TextLanguage {
+    pub fn code(self) -> &'static str {
+        match self {
+            TextLanguage::German => "de",
+            TextLanguage::English => "en",
+        }
+    }
+
+    /// The name of the text tokenizer that is referenced by the index schema
+    pub(crate) fn tokenizer_name(self) -> &'static str {
+        match self {
+            TextLanguage::German => "text_de",
+            TextLanguage::English => "text_en",
+        }
+    }
+}
+
+impl fmt::Display for TextLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for TextLanguage {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &*s.trim().to_lowercase() {
+            "de" | "german" => Ok(TextLanguage::German),
+            "en" | "english" => Ok(TextLanguage::English),
+            _ => failure::bail!("Unsupported text language: {}", s),
+        }
+    }
+}
+
+const MAX_TOKEN_LEN: usize = 40;
+
+#[rustfmt::skip]
+const GERMAN_STOP_WORDS: &[&str] = &[
+    "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "da", "das",
+    "dass", "daß", "dem", "den", "der", "des", "die", "doch", "du", "ein", "eine", "einem",
+    "einen", "einer", "eines", "er", "es", "für", "hat", "ich", "ihr", "im", "in", "ist",
+    "mit", "nach", "nicht", "noch", "oder", "sie", "sind", "so", "über", "um", "und", "uns",
+    "unter", "vom", "von", "vor", "war", "wie", "wir", "wird", "zu", "zum", "zur",
+];
+
+#[rustfmt::skip]
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "if", "in",
+    "into", "is", "it", "no", "not", "of", "on", "or", "our", "such", "that", "the",
+    "their", "then", "there", "these", "they", "this", "to", "was", "we", "will", "with",
+];
+
+fn stop_words(language: TextLanguage) -> Vec<String> {
+    let stop_words = match language {
+        TextLanguage::German => GERMAN_STOP_WORDS,
+        TextLanguage::English => ENGLISH_STOP_WORDS,
+    };
+    // The stop word filter is applied after folding
+    stop_words.iter().map(|word| fold_to_ascii(word)).collect()
+}
+
+/// Registers the text analyzer chain for the given language:
+/// tokenize -> lowercase -> fold -> remove stop words -> stem
+pub(crate) fn register_text_analyzer(tokenizers: &TokenizerManager, language: TextLanguage) {
+    let analyzer = SimpleTokenizer
+        .filter(RemoveLongFilter::limit(MAX_TOKEN_LEN))
+        .filter(LowerCaser)
+        .filter(FoldingFilter)
+        .filter(StopWordFilter::remove(stop_words(language)));
+    match language {
+        TextLanguage::German => tokenizers.register(
+            language.tokenizer_name(),
+            analyzer.filter(Stemmer::new(Language::German)),
+        ),
+        TextLanguage::English => tokenizers.register(
+            language.tokenizer_name(),
+            analyzer.filter(Stemmer::new(Language::English)),
+        ),
+    }
+}
+
+/// Replaces umlauts and accented letters by their
+/// lowercase ASCII equivalent, e.g. "Café" -> "cafe"
+/// or "Läden" -> "laden".
+pub fn fold_to_ascii(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_owned();
+    }
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            folded.push(c);
+            continue;
+        }
+        for c in c.to_lowercase() {
+            match c {
+                'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => folded.push('a'),
+                'æ' => folded.push_str("ae"),
+                'ç' => folded.push('c'),
+                'è' | 'é' | 'ê' | 'ë' => folded.push('e'),
+                'ì' | 'í' | 'î' | 'ï' => folded.push('i'),
+                'ñ' => folded.push('n'),
+                'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => folded.push('o'),
+                'œ' => folded.push_str("oe"),
+                'ß' => folded.push_str("ss"),
+                'ù' | 'ú' | 'û' | 'ü' => folded.push('u'),
+                'ý' | 'ÿ' => folded.push('y'),
+                c => folded.push(c),
+            }
+        }
+    }
+    folded
+}
+
+#[derive(Clone)]
+pub struct FoldingFilter;
+
+impl<TailTokenStream> TokenFilter<TailTokenStream> for FoldingFilter
+where
+    TailTokenStream: TokenStream,
+{
+    type ResultTokenStream = FoldingTokenStream<TailTokenStream>;
+
+    fn transform(&self, token_stream: TailTokenStream) -> Self::ResultTokenStream {
+        FoldingTokenStream { tail: token_stream }
+    }
+}
+
+pub struct FoldingTokenStream<TailTokenStream> {
+    tail: TailTokenStream,
+}
+
+impl<TailTokenStream> TokenStream for FoldingTokenStream<TailTokenStream>
+where
+    TailTokenStream: TokenStream,
+{
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+        let token = self.tail.token_mut();
+        if !token.text.is_ascii() {
+            token.text = fold_to_ascii(&token.text);
+        }
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_umlauts_and_accents() {
+        assert_eq!("laden", fold_to_ascii("läden"));
+        assert_eq!("cafe", fold_to_ascii("Café"));
+        assert_eq!("strasse", fold_to_ascii("straße"));
+        assert_eq!("uber", fold_to_ascii("Über"));
+        assert_eq!("foo-bar", fold_to_ascii("foo-bar"));
+    }
+
+    #[test]
+    fn parse_text_language() {
+        assert_eq!(TextLanguage::German, "de".parse().unwrap());
+        assert_eq!(TextLanguage::German, "German".parse().unwrap());
+        assert_eq!(TextLanguage::English, "en".parse().unwrap());
+        assert!("fr".parse::<TextLanguage>().is_err());
+    }
+
+    #[test]
+    fn analyze_german_text() {
+        let tokenizers = TokenizerManager::default();
+        register_text_analyzer(&tokenizers, TextLanguage::German);
+        let tokenizer = tokenizers
+            .get(TextLanguage::German.tokenizer_name())
+            .unwrap();
+        let analyze = |text: &str| {
+            let mut tokens = Vec::new();
+            let mut token_stream = tokenizer.token_stream(text);
+            while token_stream.advance() {
+                tokens.push(token_stream.token().text.clone());
+            }
+            tokens
+        };
+        assert_eq!(analyze("Läden"), analyze("Laden"));
+        assert_eq!(analyze("Café"), analyze("cafe"));
+        assert_eq!(analyze("Laden und Café"), analyze("Laden Cafe"));
+    }
+}
@@ -2,7 +2,7 @@ use super::*;
 
 use diesel::connection::Connection;
 
-pub fn archive_events(connections: &sqlite::Connections, ids: &[&str]) -> Result<()> {
+pub fn exec_archive_events(connections: &sqlite::Connections, ids: &[&str]) -> Result<()> {
     let mut repo_err = None;
     let connection = connections.exclusive()?;
     Ok(connection
@@ -21,3 +21,33 @@ pub fn archive_events(connections: &sqlite::Connections, ids: &[&str]) -> Result
             }
         })?)
 }
+
+pub fn post_archive_events(indexer: &mut EventIndexer, ids: &[&str]) -> Result<()> {
+    // Remove archived events from search index
+    // TODO: Move to a separate task/thread that doesn't delay this request
+    for id in ids {
+        if let Err(err) = usecases::unindex_event(indexer, id) {
+            error!(
+                "Failed to remove archived event {} from search index: {}",
+                id, err
+            );
+        }
+    }
+    if let Err(err) = indexer.flush_events() {
+        error!(
+            "Failed to finish updating the search index after archiving events: {}",
+            err
+        );
+    }
+    Ok(())
+}
+
+pub fn archive_events(
+    connections: &sqlite::Connections,
+    indexer: &mut EventIndexer,
+    ids: &[&str],
+) -> Result<()> {
+    exec_archive_events(connections, ids)?;
+    post_archive_events(indexer, ids)?;
+    Ok(())
+}
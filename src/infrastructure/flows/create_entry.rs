@@ -6,23 +6,28 @@ use diesel::Connection;
 
 pub fn create_entry(
     connections: &sqlite::Connections,
-    indexer: &mut EntryIndexer,
+    jobs: &mut JobQueue,
+    geocoder: Option<&Geocoder>,
     new_entry: usecases::NewEntry,
 ) -> Result<String> {
+    // Look up a missing address before locking the database
+    let geocoded_address =
+        geocoder.and_then(|geocoder| usecases::reverse_geocode_new_entry(geocoder, &new_entry));
+
     // Create and add new entry
-    let (entry, ratings) = {
+    let entry = {
         let connection = connections.exclusive()?;
         let mut prepare_err = None;
         connection
             .transaction::<_, diesel::result::Error, _>(|| {
-                match usecases::prepare_new_entry(&*connection, new_entry) {
+                match usecases::prepare_new_entry(&*connection, geocoded_address, new_entry) {
                     Ok(storable) => {
-                        let (entry, ratings) = usecases::store_new_entry(&*connection, storable)
+                        let (entry, _) = usecases::store_new_entry(&*connection, storable)
                             .map_err(|err| {
                                 warn!("Failed to store newly created entry: {}", err);
                                 diesel::result::Error::RollbackTransaction
                             })?;
-                        Ok((entry, ratings))
+                        Ok(entry)
                     }
                     Err(err) => {
                         prepare_err = Some(err);
@@ -40,32 +45,49 @@ pub fn create_entry(
     }?;
 
     // Index newly added entry
-    // TODO: Move to a separate task/thread that doesn't delay this request
-    if let Err(err) = usecases::index_entry(indexer, &entry, &ratings).and_then(|_| indexer.flush())
-    {
-        error!("Failed to index newly added entry {}: {}", entry.id, err);
-    }
+    jobs.submit(connections, Job::IndexEntry(entry.id.clone(), entry.created));
 
     // Send subscription e-mails
-    // TODO: Move to a separate task/thread that doesn't delay this request
-    if let Err(err) = notify_entry_added(connections, &entry) {
-        error!(
-            "Failed to send notifications for newly added entry {}: {}",
-            entry.id, err
-        );
-    }
+    let id = entry.id.clone();
+    jobs.submit(connections, Job::NotifyEntryAdded(entry));
 
-    Ok(entry.id)
+    Ok(id)
 }
 
-fn notify_entry_added(connections: &sqlite::Connections, entry: &Entry) -> Result<()> {
-    let (email_addresses, all_categories) = {
-        let connection = connections.shared()?;
-        let email_addresses =
-            usecases::email_addresses_by_coordinate(&*connection, entry.location.pos)?;
-        let all_categories = connection.all_categories()?;
-        (email_addresses, all_categories)
-    };
-    notify::entry_added(&email_addresses, &entry, all_categories);
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::super::tests::prelude::*;
+
+    use crate::infrastructure::worker::Worker;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn should_index_created_entry_in_background() {
+        let fixture = EnvFixture::new();
+        let mut worker = Worker::spawn(
+            fixture.db_connections.clone(),
+            fixture.search_engine.borrow().clone(),
+        );
+        let new_entry = NewEntry::from(0);
+        let tag = new_entry.tags[0].clone();
+        let entry_id = flows::create_entry(
+            &fixture.db_connections,
+            &mut worker,
+            None,
+            new_entry.into(),
+        )
+        .unwrap();
+        assert!(fixture.entry_exists(&entry_id));
+        // Wait until the background worker has indexed the new entry
+        let mut indexed_entries = fixture.query_entries_by_tag(&tag);
+        for _ in 0..100 {
+            if !indexed_entries.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+            indexed_entries = fixture.query_entries_by_tag(&tag);
+        }
+        assert_eq!(1, indexed_entries.len());
+        assert_eq!(entry_id, indexed_entries[0].id);
+    }
 }
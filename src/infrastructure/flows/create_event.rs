@@ -0,0 +1,22 @@
+use super::*;
+
+pub fn create_event(
+    connections: &sqlite::Connections,
+    indexer: &mut EventIndexer,
+    new_event: usecases::NewEvent,
+) -> Result<String> {
+    // Create and add new event
+    let event = {
+        let mut connection = connections.exclusive()?;
+        let id = usecases::create_new_event(&mut *connection, new_event)?;
+        connection.get_event(&id)?
+    };
+
+    // Index newly added event
+    // TODO: Move to a separate task/thread that doesn't delay this request
+    if let Err(err) = usecases::index_event(indexer, &event).and_then(|_| indexer.flush_events()) {
+        error!("Failed to index newly added event {}: {}", event.id, err);
+    }
+
+    Ok(event.id)
+}
@@ -4,11 +4,11 @@ use diesel::connection::Connection;
 
 pub fn create_rating(
     connections: &sqlite::Connections,
-    indexer: &mut EntryIndexer,
+    jobs: &mut JobQueue,
     rate_entry: usecases::RateEntry,
 ) -> Result<(String, String)> {
     // Add new rating to existing entry
-    let (rating_id, comment_id, entry, ratings) = {
+    let (rating_id, comment_id, created, entry) = {
         let connection = connections.exclusive()?;
         let mut prepare_err = None;
         connection
@@ -17,12 +17,13 @@ pub fn create_rating(
                     Ok(storable) => {
                         let rating_id = storable.rating_id().to_owned();
                         let comment_id = storable.comment_id().to_owned();
-                        let (entry, ratings) = usecases::store_new_rating(&*connection, storable)
+                        let created = storable.created();
+                        let (entry, _) = usecases::store_new_rating(&*connection, storable)
                             .map_err(|err| {
-                            warn!("Failed to store new rating for entry: {}", err);
-                            diesel::result::Error::RollbackTransaction
-                        })?;
-                        Ok((rating_id, comment_id, entry, ratings))
+                                warn!("Failed to store new rating for entry: {}", err);
+                                diesel::result::Error::RollbackTransaction
+                            })?;
+                        Ok((rating_id, comment_id, created, entry))
                     }
                     Err(err) => {
                         prepare_err = Some(err);
@@ -40,14 +41,7 @@ pub fn create_rating(
     }?;
 
     // Reindex entry after adding the new rating
-    // TODO: Move to a separate task/thread that doesn't delay this request
-    if let Err(err) = usecases::index_entry(indexer, &entry, &ratings).and_then(|_| indexer.flush())
-    {
-        error!(
-            "Failed to reindex entry {} after adding a new rating: {}",
-            entry.id, err
-        );
-    }
+    jobs.submit(connections, Job::IndexEntry(entry.id, created));
 
     Ok((rating_id, comment_id))
 }
@@ -0,0 +1,18 @@
+use super::*;
+
+pub fn delete_event(
+    connections: &sqlite::Connections,
+    indexer: &mut EventIndexer,
+    id: &str,
+    token: &str,
+) -> Result<()> {
+    usecases::delete_event(&mut *connections.exclusive()?, id, token)?;
+
+    // Remove deleted event from search index
+    // TODO: Move to a separate task/thread that doesn't delay this request
+    if let Err(err) = usecases::unindex_event(indexer, id).and_then(|_| indexer.flush_events()) {
+        error!("Failed to remove deleted event {} from search index: {}", id, err);
+    }
+
+    Ok(())
+}
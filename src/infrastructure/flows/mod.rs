@@ -3,19 +3,29 @@ mod archive_entries;
 mod archive_events;
 mod archive_ratings;
 mod create_entry;
+mod create_event;
 mod create_rating;
+mod delete_event;
+mod rebuild_entry_index;
+mod revert_entry;
 mod update_entry;
+mod update_event;
 
 pub mod prelude {
     pub use super::{
         archive_comments::*, archive_entries::*, archive_events::*, archive_ratings::*,
-        create_entry::*, create_rating::*, update_entry::*,
+        create_entry::*, create_event::*, create_rating::*, delete_event::*, rebuild_entry_index::*,
+        revert_entry::*, update_entry::*, update_event::*,
     };
 }
 
 pub type Result<T> = std::result::Result<T, error::AppError>;
 
-pub(crate) use super::{db::sqlite, error, notify};
+pub(crate) use super::{
+    db::sqlite,
+    error,
+    worker::{Job, JobQueue},
+};
 
 pub(crate) use crate::core::{prelude::*, usecases};
 
@@ -42,7 +52,10 @@ mod tests {
             response::Response,
         };
 
-        use crate::ports::web::rocket_instance;
+        use crate::{
+            infrastructure::{geocoding::GeocodingService, worker::Worker},
+            ports::web::rocket_instance,
+        };
 
         use rocket::{
             config::{Config, Environment},
@@ -70,6 +83,8 @@ mod tests {
                 let rocket = rocket_instance(
                     db_connections.clone(),
                     search_engine.clone(),
+                    Worker::immediate(search_engine.clone()),
+                    GeocodingService::new(vec![]),
                     vec![("/", api::routes())],
                     Some(cfg),
                 );
@@ -85,6 +100,7 @@ mod tests {
                 flows::create_entry(
                     &self.db_connections,
                     &mut *self.search_engine.borrow_mut(),
+                    None,
                     new_entry.into(),
                 )
                 .unwrap()
@@ -207,6 +223,7 @@ mod tests {
                     homepage: None,
                     image_url: None,
                     image_link_url: None,
+                    opening_hours: None,
                 }
             }
         }
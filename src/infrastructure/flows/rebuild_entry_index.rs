@@ -0,0 +1,86 @@
+use super::*;
+
+use crate::infrastructure::db::tantivy;
+
+/// Rebuilds the entry index from scratch. All entries are loaded
+/// and indexed in chunks into a new index that replaces the current
+/// index when finished. The progress is reported after each chunk
+/// with the number of indexed entries and the expected total.
+///
+/// Returns the number of indexed entries.
+pub fn rebuild_entry_index(
+    connections: &sqlite::Connections,
+    search_engine: &mut tantivy::SearchEngine,
+    progress: &mut FnMut(usize, usize),
+) -> Result<usize> {
+    let started = Timestamp::now();
+    let total_count = connections.shared()?.count_entries()?;
+    let mut rebuilt_index = search_engine
+        .create_entry_index_for_rebuild()
+        .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+    let mut indexed_count = 0;
+    let mut last_id = None;
+    loop {
+        // Concurrent requests must not be blocked
+        // while rebuilding the whole index
+        let ids = usecases::index_entries_chunk(
+            &*connections.shared()?,
+            &mut rebuilt_index.index,
+            last_id.as_ref().map(String::as_str),
+        )?;
+        if ids.is_empty() {
+            break;
+        }
+        indexed_count += ids.len();
+        progress(indexed_count, total_count.max(indexed_count));
+        last_id = ids.into_iter().last();
+    }
+    search_engine
+        .replace_entry_index(rebuilt_index)
+        .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+
+    // Entries that have been changed during the rebuild might
+    // only have been indexed in the replaced index
+    let since = Timestamp::from(i64::from(started) - usecases::INDEX_CATCH_UP_OVERLAP_IN_SECONDS);
+    let caught_up = Timestamp::now();
+    usecases::index_changed_entries(&*connections.shared()?, search_engine, since)?;
+    search_engine
+        .flush_with_high_water_mark(caught_up)
+        .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+    Ok(indexed_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::prelude::*;
+
+    fn rebuild_entry_index(fixture: &EnvFixture) -> super::Result<usize> {
+        super::rebuild_entry_index(
+            &fixture.db_connections,
+            &mut *fixture.search_engine.borrow_mut(),
+            &mut |_, _| {},
+        )
+    }
+
+    #[test]
+    fn should_rebuild_entry_index_with_all_current_entries() {
+        let fixture = EnvFixture::new();
+        let entry_ids = vec![
+            fixture.create_entry(0.into()),
+            fixture.create_entry(1.into()),
+            fixture.create_entry(2.into()),
+        ];
+        flows::archive_entries(
+            &fixture.db_connections,
+            &mut *fixture.search_engine.borrow_mut(),
+            &[&entry_ids[1]],
+        )
+        .unwrap();
+
+        assert_eq!(2, rebuild_entry_index(&fixture).unwrap());
+
+        assert_eq!(1, fixture.query_entries_by_tag("tag_0").len());
+        assert!(fixture.query_entries_by_tag("tag_1").is_empty());
+        assert_eq!(1, fixture.query_entries_by_tag("tag_2").len());
+    }
+}
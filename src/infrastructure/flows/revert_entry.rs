@@ -0,0 +1,116 @@
+use super::{
+    update_entry::{reindex_and_notify_updated_entry, store_updated_entry},
+    *,
+};
+
+pub fn revert_entry(
+    connections: &sqlite::Connections,
+    jobs: &mut JobQueue,
+    username: &str,
+    id: String,
+    version: u64,
+) -> Result<Entry> {
+    // Store the reverted entry together with the scout who reverted it
+    let entry = {
+        let connection = connections.exclusive()?;
+        let update_entry = usecases::revert_entry(&*connection, username, &id, version)?;
+        store_updated_entry(&*connection, id, update_entry, |entry| {
+            let revert = EntryRevert {
+                entry_id: entry.id.clone(),
+                version: entry.version,
+                reverted_version: version,
+                reverted_by: username.into(),
+                created: entry.created,
+            };
+            Ok(connection.create_entry_revert(&revert)?)
+        })?
+    };
+
+    // Reindexing and notifications are handled like for any other update
+    reindex_and_notify_updated_entry(connections, jobs, &entry);
+
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::prelude::*;
+
+    #[test]
+    fn should_revert_entry_and_record_the_scout() {
+        let fixture = EnvFixture::new();
+        let users = vec![
+            User {
+                id: "user".into(),
+                username: "user".into(),
+                password: "secret1".parse::<Password>().unwrap(),
+                email: "user@example.com".into(),
+                email_confirmed: true,
+                role: Role::User,
+            },
+            User {
+                id: "scout".into(),
+                username: "scout".into(),
+                password: "secret2".parse::<Password>().unwrap(),
+                email: "scout@example.com".into(),
+                email_confirmed: true,
+                role: Role::Scout,
+            },
+        ];
+        for u in users {
+            fixture
+                .db_connections
+                .exclusive()
+                .unwrap()
+                .create_user(u)
+                .unwrap();
+        }
+        let entry_id = fixture.create_entry(0.into());
+        let mut vandalized = fixture.try_get_entry(&entry_id).unwrap();
+        vandalized.version = 1;
+        vandalized.title = "vandalized".into();
+        fixture
+            .db_connections
+            .exclusive()
+            .unwrap()
+            .update_entry(&vandalized)
+            .unwrap();
+
+        assert!(flows::revert_entry(
+            &fixture.db_connections,
+            &mut *fixture.search_engine.borrow_mut(),
+            "user",
+            entry_id.clone(),
+            0,
+        )
+        .is_err());
+
+        let entry = flows::revert_entry(
+            &fixture.db_connections,
+            &mut *fixture.search_engine.borrow_mut(),
+            "scout",
+            entry_id.clone(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(2, entry.version);
+        assert_eq!("title_0", entry.title);
+        let stored_entry = fixture.try_get_entry(&entry_id).unwrap();
+        assert_eq!(2, stored_entry.version);
+        assert_eq!("title_0", stored_entry.title);
+        let indexed_entries = fixture.query_entries_by_tag("tag_0");
+        assert_eq!(1, indexed_entries.len());
+        assert_eq!("title_0", indexed_entries[0].title);
+
+        let reverts = fixture
+            .db_connections
+            .shared()
+            .unwrap()
+            .get_entry_reverts(&entry_id)
+            .unwrap();
+        assert_eq!(1, reverts.len());
+        assert_eq!(2, reverts[0].version);
+        assert_eq!(0, reverts[0].reverted_version);
+        assert_eq!("scout", reverts[0].reverted_by);
+    }
+}
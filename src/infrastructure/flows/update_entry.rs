@@ -4,69 +4,60 @@ use diesel::connection::Connection;
 
 pub fn update_entry(
     connections: &sqlite::Connections,
-    indexer: &mut EntryIndexer,
+    jobs: &mut JobQueue,
     id: String,
     update_entry: usecases::UpdateEntry,
 ) -> Result<Entry> {
     // Update existing entry
-    let (entry, ratings) = {
+    let entry = {
         let connection = connections.exclusive()?;
-        let mut prepare_err = None;
-        connection
-            .transaction::<_, diesel::result::Error, _>(|| {
-                match usecases::prepare_updated_entry(&*connection, id, update_entry) {
-                    Ok(storable) => {
-                        let (entry, ratings) =
-                            usecases::store_updated_entry(&*connection, storable).map_err(
-                                |err| {
-                                    warn!("Failed to store updated entry: {}", err);
-                                    diesel::result::Error::RollbackTransaction
-                                },
-                            )?;
-                        Ok((entry, ratings))
-                    }
-                    Err(err) => {
-                        prepare_err = Some(err);
-                        Err(diesel::result::Error::RollbackTransaction)
-                    }
-                }
-            })
-            .map_err(|err| {
-                if let Some(err) = prepare_err {
-                    err
-                } else {
-                    RepoError::from(err).into()
-                }
-            })
-    }?;
-
-    // Reindex updated entry
-    // TODO: Move to a separate task/thread that doesn't delay this request
-    if let Err(err) = usecases::index_entry(indexer, &entry, &ratings).and_then(|_| indexer.flush())
-    {
-        error!("Failed to reindex updated entry {}: {}", entry.id, err);
-    }
-
-    // Send subscription e-mails
-    // TODO: Move to a separate task/thread that doesn't delay this request
-    if let Err(err) = notify_entry_updated(connections, &entry) {
-        error!(
-            "Failed to send notifications for updated entry {}: {}",
-            entry.id, err
-        );
-    }
+        store_updated_entry(&*connection, id, update_entry, |_| Ok(()))?
+    };
+    reindex_and_notify_updated_entry(connections, jobs, &entry);
+    Ok(entry)
+}
 
+/// Prepares and stores the update of an entry in a transaction
+/// of the open connection. Any additional changes are stored
+/// within the same transaction.
+pub(super) fn store_updated_entry<F>(
+    connection: &sqlite::Connection,
+    id: String,
+    update_entry: usecases::UpdateEntry,
+    store_along: F,
+) -> Result<Entry>
+where
+    F: FnOnce(&Entry) -> std::result::Result<(), Error>,
+{
+    let mut store_err = None;
+    let entry = connection
+        .transaction::<_, diesel::result::Error, _>(|| {
+            usecases::prepare_updated_entry(connection, id, update_entry)
+                .and_then(|storable| usecases::store_updated_entry(connection, storable))
+                .and_then(|(entry, _)| store_along(&entry).map(|()| entry))
+                .map_err(|err| {
+                    store_err = Some(err);
+                    diesel::result::Error::RollbackTransaction
+                })
+        })
+        .map_err(|err| {
+            if let Some(err) = store_err {
+                err
+            } else {
+                RepoError::from(err).into()
+            }
+        })?;
     Ok(entry)
 }
 
-fn notify_entry_updated(connections: &sqlite::Connections, entry: &Entry) -> Result<()> {
-    let (email_addresses, all_categories) = {
-        let connection = connections.shared()?;
-        let email_addresses =
-            usecases::email_addresses_by_coordinate(&*connection, entry.location.pos)?;
-        let all_categories = connection.all_categories()?;
-        (email_addresses, all_categories)
-    };
-    notify::entry_updated(&email_addresses, &entry, all_categories);
-    Ok(())
+pub(super) fn reindex_and_notify_updated_entry(
+    connections: &sqlite::Connections,
+    jobs: &mut JobQueue,
+    entry: &Entry,
+) {
+    // Reindex updated entry
+    jobs.submit(connections, Job::IndexEntry(entry.id.clone(), entry.created));
+
+    // Send subscription e-mails
+    jobs.submit(connections, Job::NotifyEntryUpdated(entry.clone()));
 }
@@ -0,0 +1,23 @@
+use super::*;
+
+pub fn update_event(
+    connections: &sqlite::Connections,
+    indexer: &mut EventIndexer,
+    id: &str,
+    update_event: usecases::UpdateEvent,
+) -> Result<Event> {
+    // Update existing event
+    let event = {
+        let mut connection = connections.exclusive()?;
+        usecases::update_event(&mut *connection, id, update_event)?;
+        connection.get_event(id)?
+    };
+
+    // Reindex updated event
+    // TODO: Move to a separate task/thread that doesn't delay this request
+    if let Err(err) = usecases::index_event(indexer, &event).and_then(|_| indexer.flush_events()) {
+        error!("Failed to reindex updated event {}: {}", event.id, err);
+    }
+
+    Ok(event)
+}
@@ -0,0 +1,225 @@
+use crate::core::prelude::*;
+
+use failure::Fallible;
+use std::{cmp::Ordering, collections::HashMap, fs::File, io, path::Path};
+
+#[derive(Debug, Deserialize)]
+struct GazetteerRecord {
+    country: Option<String>,
+    zip: Option<String>,
+    city: Option<String>,
+    lat: f64,
+    lng: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Place {
+    /// The normalized country
+    country: Option<String>,
+    pos: MapPoint,
+}
+
+/// Places that are farther away from a position
+/// are not considered for reverse geocoding.
+const MAX_REVERSE_DISTANCE: Distance = Distance::from_meters(10_000.0);
+
+fn normalize(name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_lowercase())
+    }
+}
+
+/// The centroid of all matching places, optionally restricted to a
+/// country. Places in different countries are ambiguous.
+fn find_centroid(places: &[Place], country: Option<&str>) -> Option<MapPoint> {
+    let places: Vec<_> = places
+        .iter()
+        .filter(|place| {
+            country.is_none() || place.country.as_ref().map(String::as_str) == country
+        })
+        .collect();
+    let first = places.first()?;
+    if places.iter().any(|place| place.country != first.country) {
+        debug!("Ambiguous places in different countries");
+        return None;
+    }
+    let (lat_sum, lng_sum) = places.iter().fold((0.0, 0.0), |(lat_sum, lng_sum), place| {
+        let (lat, lng) = place.pos.to_lat_lng_deg();
+        (lat_sum + lat, lng_sum + lng)
+    });
+    let count = places.len() as f64;
+    MapPoint::try_from_lat_lng_deg(lat_sum / count, lng_sum / count)
+}
+
+fn non_empty(name: Option<String>) -> Option<String> {
+    name.map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// An offline geocoder that resolves addresses by the centroids
+/// of their postcode or city. Countries and city names need to be
+/// spelled exactly as in the gazetteer, but case is ignored.
+///
+/// Positions are reverse geocoded into the postcode, city, and
+/// country of the nearest place.
+#[derive(Debug, Default)]
+pub struct Gazetteer {
+    places_by_zip: HashMap<String, Vec<Place>>,
+    places_by_city: HashMap<String, Vec<Place>>,
+    addresses: Vec<(MapPoint, Address)>,
+}
+
+impl Gazetteer {
+    /// Loads the gazetteer from a CSV file with the
+    /// columns `country`, `zip`, `city`, `lat`, and `lng`.
+    pub fn load_csv<P: AsRef<Path>>(path: P) -> Fallible<Self> {
+        Self::read_csv(File::open(path)?)
+    }
+
+    pub fn read_csv<R: io::Read>(reader: R) -> Fallible<Self> {
+        let mut gazetteer = Self::default();
+        for record in csv::Reader::from_reader(reader).deserialize() {
+            let record: GazetteerRecord = record?;
+            let pos = match MapPoint::try_from_lat_lng_deg(record.lat, record.lng) {
+                Some(pos) => pos,
+                None => {
+                    warn!("Invalid position in gazetteer: {:?}", record);
+                    continue;
+                }
+            };
+            let place = Place {
+                country: record.country.as_ref().and_then(|c| normalize(c)),
+                pos,
+            };
+            let address = Address {
+                street: None,
+                zip: non_empty(record.zip.clone()),
+                city: non_empty(record.city.clone()),
+                country: non_empty(record.country.clone()),
+            };
+            if !address.is_empty() {
+                gazetteer.addresses.push((pos, address));
+            }
+            if let Some(zip) = record.zip.as_ref().and_then(|zip| normalize(zip)) {
+                gazetteer
+                    .places_by_zip
+                    .entry(zip)
+                    .or_default()
+                    .push(place.clone());
+            }
+            if let Some(city) = record.city.as_ref().and_then(|city| normalize(city)) {
+                gazetteer
+                    .places_by_city
+                    .entry(city)
+                    .or_default()
+                    .push(place);
+            }
+        }
+        Ok(gazetteer)
+    }
+}
+
+impl Geocoder for Gazetteer {
+    fn resolve_address(&self, address: &Address) -> Fallible<Option<MapPoint>> {
+        let country = address.country.as_ref().and_then(|c| normalize(c));
+        let country = country.as_ref().map(String::as_str);
+        // Postcodes are more precise than cities
+        let by_zip = address
+            .zip
+            .as_ref()
+            .and_then(|zip| normalize(zip))
+            .and_then(|zip| self.places_by_zip.get(&zip))
+            .and_then(|places| find_centroid(places, country));
+        if by_zip.is_some() {
+            return Ok(by_zip);
+        }
+        Ok(address
+            .city
+            .as_ref()
+            .and_then(|city| normalize(city))
+            .and_then(|city| self.places_by_city.get(&city))
+            .and_then(|places| find_centroid(places, country)))
+    }
+
+    fn reverse_geocode(&self, pos: MapPoint) -> Fallible<Option<Address>> {
+        Ok(self
+            .addresses
+            .iter()
+            .filter_map(|(place_pos, address)| {
+                MapPoint::distance(pos, *place_pos).map(|distance| (distance, address))
+            })
+            .filter(|(distance, _)| *distance <= MAX_REVERSE_DISTANCE)
+            .min_by(|(lhs, _), (rhs, _)| lhs.partial_cmp(rhs).unwrap_or(Ordering::Equal))
+            .map(|(_, address)| address.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "country,zip,city,lat,lng
+Germany,70173,Stuttgart,48.78,9.18
+Germany,70176,Stuttgart,48.78,9.16
+Germany,12345,Berlin,52.52,13.4
+Austria,1010,Wien,48.21,16.37
+Switzerland,1010,Lausanne,46.52,6.63
+";
+
+    fn resolve(
+        gazetteer: &Gazetteer,
+        zip: &str,
+        city: &str,
+        country: &str,
+    ) -> Option<(f64, f64)> {
+        let addr = Address {
+            zip: Some(zip.into()),
+            city: Some(city.into()),
+            country: Some(country.into()),
+            ..Default::default()
+        };
+        gazetteer
+            .resolve_address(&addr)
+            .unwrap()
+            .map(|pos| pos.to_lat_lng_deg())
+            .map(|(lat, lng)| ((lat * 100.0).round() / 100.0, (lng * 100.0).round() / 100.0))
+    }
+
+    #[test]
+    fn resolve_by_zip_or_city() {
+        let gazetteer = Gazetteer::read_csv(CSV.as_bytes()).unwrap();
+        assert_eq!(Some((48.78, 9.18)), resolve(&gazetteer, "70173", "", ""));
+        assert_eq!(Some((48.78, 9.17)), resolve(&gazetteer, "", "stuttgart", ""));
+        assert_eq!(Some((52.52, 13.4)), resolve(&gazetteer, "99999", "Berlin", "germany"));
+        assert_eq!(None, resolve(&gazetteer, "", "Berlin", "Austria"));
+        assert_eq!(None, resolve(&gazetteer, "1010", "", ""));
+        assert_eq!(Some((48.21, 16.37)), resolve(&gazetteer, "1010", "", "Austria"));
+    }
+
+    #[test]
+    fn reverse_geocode_nearest_place() {
+        let gazetteer = Gazetteer::read_csv(CSV.as_bytes()).unwrap();
+        let address = gazetteer
+            .reverse_geocode(MapPoint::from_lat_lng_deg(48.781, 9.161))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Address {
+                street: None,
+                zip: Some("70176".into()),
+                city: Some("Stuttgart".into()),
+                country: Some("Germany".into()),
+            },
+            address
+        );
+        assert_eq!(
+            None,
+            gazetteer
+                .reverse_geocode(MapPoint::from_lat_lng_deg(50.0, 10.0))
+                .unwrap()
+        );
+    }
+}
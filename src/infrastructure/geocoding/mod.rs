@@ -0,0 +1,284 @@
+//! Resolving the location of addresses by different providers.
+
+mod gazetteer;
+mod nominatim;
+mod opencage;
+
+pub use self::{gazetteer::Gazetteer, nominatim::Nominatim, opencage::OpenCage};
+
+use super::db::sqlite;
+use crate::core::prelude::*;
+
+use failure::Fallible;
+use itertools::Itertools;
+use std::{env, sync::Arc};
+
+/// Addresses that could not be resolved are retried after
+/// this time, e.g. when a provider was unavailable or has
+/// been added in the meantime.
+const UNRESOLVED_ADDRESS_TTL_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+fn is_cached_result_expired(pos: Option<MapPoint>, created: Timestamp, now: Timestamp) -> bool {
+    pos.is_none() && i64::from(now) - i64::from(created) > UNRESOLVED_ADDRESS_TTL_SECONDS
+}
+
+fn address_to_forward_query_string(addr: &Address) -> String {
+    let addr_parts = [&addr.street, &addr.zip, &addr.city, &addr.country];
+    addr_parts.iter().filter_map(|x| x.as_ref()).join(",")
+}
+
+pub type GeocodingProvider = Box<dyn Geocoder + Send + Sync>;
+
+/// Resolves addresses by trying all providers in order until
+/// the first one succeeds. The results are cached (if enabled)
+/// to resolve each address only once.
+///
+/// Positions are reverse geocoded by the first provider
+/// that finds an address.
+#[derive(Clone)]
+pub struct GeocodingService {
+    providers: Arc<Vec<GeocodingProvider>>,
+    cache: Option<sqlite::Connections>,
+}
+
+impl GeocodingService {
+    pub fn new(providers: Vec<GeocodingProvider>) -> Self {
+        Self {
+            providers: Arc::new(providers),
+            cache: None,
+        }
+    }
+
+    pub fn with_cache(self, connections: sqlite::Connections) -> Self {
+        Self {
+            cache: Some(connections),
+            ..self
+        }
+    }
+
+    /// Configures the providers by the following environment
+    /// variables in this order:
+    ///
+    /// - `OPENCAGE_API_KEY`: OpenCage
+    /// - `NOMINATIM_URL`: A Nominatim-compatible server
+    /// - `GAZETTEER_FILE`: A CSV file for the offline [`Gazetteer`]
+    pub fn from_env() -> Fallible<Self> {
+        let mut providers: Vec<GeocodingProvider> = Vec::with_capacity(3);
+        if let Ok(api_key) = env::var("OPENCAGE_API_KEY") {
+            info!("Resolving addresses with OpenCage");
+            providers.push(Box::new(OpenCage::new(api_key)));
+        }
+        if let Ok(url) = env::var("NOMINATIM_URL") {
+            info!("Resolving addresses with Nominatim at {}", url);
+            providers.push(Box::new(Nominatim::new(url)));
+        }
+        if let Ok(file) = env::var("GAZETTEER_FILE") {
+            info!("Resolving addresses with the gazetteer from {}", file);
+            providers.push(Box::new(Gazetteer::load_csv(&file)?));
+        }
+        if providers.is_empty() {
+            warn!("No geocoding provider found");
+        }
+        Ok(Self::new(providers))
+    }
+
+    fn load_cached(&self, query: &str) -> Option<Option<MapPoint>> {
+        let connections = self.cache.as_ref()?;
+        let cached = connections
+            .shared()
+            .map_err(|err| RepoError::Other(Box::new(err.compat())))
+            .and_then(|db| db.get_geocoded_address(query));
+        match cached {
+            Ok((pos, created)) => {
+                if is_cached_result_expired(pos, created, Timestamp::now()) {
+                    debug!("Retrying to resolve the location of '{}'", query);
+                    None
+                } else {
+                    Some(pos)
+                }
+            }
+            Err(RepoError::NotFound) => None,
+            Err(err) => {
+                warn!("Failed to load cached location of '{}': {}", query, err);
+                None
+            }
+        }
+    }
+
+    fn store_cached(&self, query: &str, pos: Option<MapPoint>) {
+        if let Some(ref connections) = self.cache {
+            let stored = connections
+                .exclusive()
+                .map_err(|err| RepoError::Other(Box::new(err.compat())))
+                .and_then(|db| db.store_geocoded_address(query, pos));
+            if let Err(err) = stored {
+                warn!("Failed to cache location of '{}': {}", query, err);
+            }
+        }
+    }
+}
+
+impl Geocoder for GeocodingService {
+    fn resolve_address(&self, address: &Address) -> Fallible<Option<MapPoint>> {
+        if address.is_empty() {
+            return Ok(None);
+        }
+        let query = address_to_forward_query_string(address);
+        if let Some(pos) = self.load_cached(&query) {
+            debug!("Found cached location of '{}': {:?}", query, pos);
+            return Ok(pos);
+        }
+        let mut failed = false;
+        let mut resolved = None;
+        for provider in self.providers.iter() {
+            match provider.resolve_address(address) {
+                Ok(Some(pos)) => {
+                    debug!("Resolved location of '{}': {}", query, pos);
+                    resolved = Some(pos);
+                    break;
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    warn!("Failed to resolve location of '{}': {}", query, err);
+                    failed = true;
+                }
+            }
+        }
+        // Unresolvable addresses are only cached if all
+        // providers have been asked successfully
+        if resolved.is_some() || !(failed || self.providers.is_empty()) {
+            self.store_cached(&query, resolved);
+        }
+        Ok(resolved)
+    }
+
+    /// Reverse geocoded addresses are not cached, because
+    /// positions are rarely requested more than once.
+    fn reverse_geocode(&self, pos: MapPoint) -> Fallible<Option<Address>> {
+        for provider in self.providers.iter() {
+            match provider.reverse_geocode(pos) {
+                Ok(Some(address)) => {
+                    debug!("Found address nearby {}: {:?}", pos, address);
+                    return Ok(Some(address));
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    warn!("Failed to find address nearby {}: {}", pos, err);
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    embed_migrations!();
+
+    struct FakeGeocoder {
+        calls: Arc<AtomicUsize>,
+        pos: Option<MapPoint>,
+    }
+
+    impl Geocoder for FakeGeocoder {
+        fn resolve_address(&self, _address: &Address) -> Fallible<Option<MapPoint>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.pos)
+        }
+
+        fn reverse_geocode(&self, pos: MapPoint) -> Fallible<Option<Address>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.pos.filter(|p| *p == pos).map(|_| Address {
+                city: Some("Stuttgart".into()),
+                ..Default::default()
+            }))
+        }
+    }
+
+    #[test]
+    fn address_to_forward_query_string_partial() {
+        let mut addr = Address {
+            street: Some("A street".into()),
+            city: Some("A city".into()),
+            ..Default::default()
+        };
+        assert_eq!("A street,A city", address_to_forward_query_string(&addr));
+        addr.country = Some("A country".into());
+        assert_eq!(
+            "A street,A city,A country",
+            address_to_forward_query_string(&addr)
+        );
+        addr.street = None;
+        addr.zip = Some("1234".into());
+        assert_eq!(
+            "1234,A city,A country",
+            address_to_forward_query_string(&addr)
+        );
+    }
+
+    #[test]
+    fn expire_only_unresolved_addresses() {
+        let pos = MapPoint::from_lat_lng_deg(48.7, 9.1);
+        let created = Timestamp::from(1_000_000);
+        let not_yet = Timestamp::from(1_000_000 + UNRESOLVED_ADDRESS_TTL_SECONDS);
+        let expired = Timestamp::from(1_000_001 + UNRESOLVED_ADDRESS_TTL_SECONDS);
+        assert!(!is_cached_result_expired(None, created, not_yet));
+        assert!(is_cached_result_expired(None, created, expired));
+        assert!(!is_cached_result_expired(Some(pos), created, expired));
+    }
+
+    #[test]
+    fn resolve_each_address_only_once() {
+        let connections = sqlite::Connections::init(":memory:", 1).unwrap();
+        embedded_migrations::run(&*connections.exclusive().unwrap()).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let pos = MapPoint::from_lat_lng_deg(48.7, 9.1);
+        let geocoder = GeocodingService::new(vec![
+            Box::new(FakeGeocoder {
+                calls: Arc::clone(&calls),
+                pos: None,
+            }),
+            Box::new(FakeGeocoder {
+                calls: Arc::clone(&calls),
+                pos: Some(pos),
+            }),
+        ])
+        .with_cache(connections);
+        let addr = Address {
+            city: Some("Stuttgart".into()),
+            ..Default::default()
+        };
+        assert_eq!(Some(pos), geocoder.resolve_address(&addr).unwrap());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        assert_eq!(Some(pos), geocoder.resolve_address(&addr).unwrap());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        assert_eq!(None, geocoder.resolve_address(&Address::default()).unwrap());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn reverse_geocode_by_first_matching_provider() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let pos = MapPoint::from_lat_lng_deg(48.7, 9.1);
+        let geocoder = GeocodingService::new(vec![
+            Box::new(FakeGeocoder {
+                calls: Arc::clone(&calls),
+                pos: None,
+            }),
+            Box::new(FakeGeocoder {
+                calls: Arc::clone(&calls),
+                pos: Some(pos),
+            }),
+        ]);
+        let address = geocoder.reverse_geocode(pos).unwrap().unwrap();
+        assert_eq!(Some("Stuttgart".to_string()), address.city);
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        let other_pos = MapPoint::from_lat_lng_deg(52.5, 13.4);
+        assert_eq!(None, geocoder.reverse_geocode(other_pos).unwrap());
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+    }
+}
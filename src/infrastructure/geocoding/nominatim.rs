@@ -0,0 +1,29 @@
+use super::address_to_forward_query_string;
+
+use crate::core::prelude::*;
+
+use ::geocoding::{Forward, Openstreetmap};
+use failure::Fallible;
+
+/// Any server that is compatible with the search API of
+/// Nominatim, e.g. a self-hosted instance.
+pub struct Nominatim {
+    endpoint: String,
+}
+
+impl Nominatim {
+    pub fn new(endpoint: String) -> Self {
+        Self { endpoint }
+    }
+}
+
+impl Geocoder for Nominatim {
+    fn resolve_address(&self, address: &Address) -> Fallible<Option<MapPoint>> {
+        let query = address_to_forward_query_string(address);
+        let points = Openstreetmap::new_with_endpoint(self.endpoint.clone()).forward(&query)?;
+        Ok(points.first().and_then(|point| {
+            let (lat, lng): (f64, f64) = (point.lat(), point.lng());
+            MapPoint::try_from_lat_lng_deg(lat, lng)
+        }))
+    }
+}
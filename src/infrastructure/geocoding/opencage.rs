@@ -0,0 +1,54 @@
+use super::address_to_forward_query_string;
+
+use crate::core::prelude::*;
+
+use ::geocoding::{Forward, Opencage, Point};
+use failure::Fallible;
+
+/// The commercial OpenCage geocoding API that requires an API key.
+pub struct OpenCage {
+    api_key: String,
+}
+
+impl OpenCage {
+    pub fn new(api_key: String) -> Self {
+        Self { api_key }
+    }
+}
+
+impl Geocoder for OpenCage {
+    fn resolve_address(&self, address: &Address) -> Fallible<Option<MapPoint>> {
+        let query = address_to_forward_query_string(address);
+        let points = Opencage::new(self.api_key.clone()).forward(&query)?;
+        Ok(points.first().and_then(|point| {
+            let (lat, lng): (f64, f64) = (point.lat(), point.lng());
+            MapPoint::try_from_lat_lng_deg(lat, lng)
+        }))
+    }
+
+    fn reverse_geocode(&self, pos: MapPoint) -> Fallible<Option<Address>> {
+        let (lat, lng) = pos.to_lat_lng_deg();
+        let res = Opencage::new(self.api_key.clone()).reverse_full(&Point::new(lng, lat))?;
+        Ok(res.results.first().map(|result| {
+            let component = |keys: &[&str]| {
+                keys.iter()
+                    .filter_map(|key| result.components.get(*key))
+                    .filter_map(|val| val.as_str())
+                    .map(ToString::to_string)
+                    .next()
+            };
+            let street = component(&["road", "pedestrian", "footway"]).map(|road| {
+                match component(&["house_number"]) {
+                    Some(house_number) => format!("{} {}", road, house_number),
+                    None => road,
+                }
+            });
+            Address {
+                street,
+                zip: component(&["postcode"]),
+                city: component(&["city", "town", "village"]),
+                country: component(&["country"]),
+            }
+        }))
+    }
+}
@@ -1,8 +1,10 @@
 pub mod db;
 pub mod error;
 pub mod flows;
+pub mod geocoding;
 pub mod notify;
 pub mod osm;
+pub mod worker;
 
 #[cfg(feature = "email")]
 pub mod mail;
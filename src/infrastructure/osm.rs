@@ -1,5 +1,5 @@
 use crate::{
-    core::prelude::*,
+    core::{prelude::*, util::opening_hours::OpeningHours},
     infrastructure::{db::sqlite, error::AppError},
 };
 
@@ -125,6 +125,13 @@ fn map_osm_to_ofdb_entry(osm: &OsmEntry) -> Result<Entry> {
     let license = Some("ODbL-1.0".into());
     let image_url = None;
     let image_link_url = None;
+    // Invalid opening hours are dropped instead of
+    // rejecting the whole entry
+    let opening_hours = osm.tags.get("opening_hours").and_then(|oh| {
+        oh.parse::<OpeningHours>()
+            .map_err(|_| warn!("Invalid opening hours of OSM node {}: {}", osm.id, oh))
+            .ok()
+    });
 
     let street = street.map(|s| {
         if let Some(nr) = house_nr {
@@ -163,6 +170,7 @@ fn map_osm_to_ofdb_entry(osm: &OsmEntry) -> Result<Entry> {
         license,
         image_url,
         image_link_url,
+        opening_hours,
     })
 }
 
@@ -224,6 +232,10 @@ fn test_from_osm_of_entry() {
     tags.insert("name".into(), "denn's Biomarkt".into());
     tags.insert("phone".into(), "+43 316-422677".into());
     tags.insert("website".into(), "http://www.denns-biomarkt.at/".into());
+    tags.insert(
+        "opening_hours".into(),
+        "Mo-Fr 08:00-19:00; Sa 08:00-18:00".into(),
+    );
 
     tags.insert("diet:dairy_free".into(), "yes".into());
     tags.insert("diet:egg_free".into(), "yes".into());
@@ -267,6 +279,10 @@ fn test_from_osm_of_entry() {
         }
     );
     assert_eq!(e.license, Some("ODbL-1.0".into()));
+    assert_eq!(
+        e.opening_hours.as_ref().map(OpeningHours::as_str),
+        Some("Mo-Fr 08:00-19:00; Sa 08:00-18:00")
+    );
 
     assert!(e.tags.iter().any(|id| id == "vegan"));
     assert!(e.tags.iter().any(|id| id == "vegetarisch"));
@@ -277,3 +293,21 @@ fn test_from_osm_of_entry() {
     assert!(e.tags.iter().any(|id| id == "milchfrei"));
     assert!(e.tags.iter().any(|id| id == "glutenfrei"));
 }
+
+#[test]
+fn test_from_osm_with_unsupported_opening_hours() {
+    let mut tags = HashMap::new();
+    tags.insert("name".into(), "Biomarkt".into());
+    tags.insert("opening_hours".into(), "sunrise-sunset".into());
+    let mut osm = OsmEntry {
+        id: 12124,
+        lat: 48.0,
+        lon: 10.0,
+        tags,
+    };
+    let e = map_osm_to_ofdb_entry(&osm).unwrap();
+    assert_eq!(Some("sunrise-sunset"), e.opening_hours.as_ref().map(OpeningHours::as_str));
+    osm.tags.insert("opening_hours".into(), "ask the owner".into());
+    let e = map_osm_to_ofdb_entry(&osm).unwrap();
+    assert_eq!(e.opening_hours, None);
+}
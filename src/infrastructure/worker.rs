@@ -0,0 +1,356 @@
+//! Jobs that are executed after changes have been stored
+//! and that should not delay the corresponding request.
+
+use super::{
+    db::{sqlite, tantivy},
+    error::AppError,
+    notify,
+};
+
+use crate::core::{prelude::*, usecases};
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+};
+
+type Result<T> = std::result::Result<T, AppError>;
+
+/// The maximum number of pending jobs before submitting
+/// new jobs blocks the caller.
+const QUEUE_CAPACITY: usize = 1_000;
+
+/// The maximum number of jobs that are executed at once
+/// with a single commit of the search index.
+const MAX_BATCH_SIZE: usize = 100;
+
+/// Changes that could not be indexed are retried with the
+/// next batch until the maximum number of attempts.
+const MAX_INDEX_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub enum Job {
+    /// Reindex the current version of an entry, e.g. after
+    /// it has been created, updated, or rated at the given time
+    IndexEntry(String, Timestamp),
+    /// Send subscription e-mails for a newly created entry
+    NotifyEntryAdded(Entry),
+    /// Send subscription e-mails for an updated entry
+    NotifyEntryUpdated(Entry),
+}
+
+pub trait JobQueue {
+    fn submit(&mut self, connections: &sqlite::Connections, job: Job);
+}
+
+/// An indexer without a background worker executes
+/// all jobs immediately.
+impl<T: EntryIndexer> JobQueue for T {
+    fn submit(&mut self, connections: &sqlite::Connections, job: Job) {
+        execute_jobs(connections, self, None, vec![job]);
+    }
+}
+
+/// The times when all changes that have been submitted
+/// for indexing but have not been indexed yet were stored.
+#[derive(Debug, Default)]
+struct PendingChanges {
+    created: BTreeMap<Timestamp, usize>,
+    /// Changes that could not be indexed yet together
+    /// with the number of failed attempts
+    failed: Vec<(String, Timestamp, usize)>,
+}
+
+impl PendingChanges {
+    fn add(&mut self, created: Timestamp) {
+        *self.created.entry(created).or_insert(0) += 1;
+    }
+
+    fn remove(&mut self, created: Timestamp) {
+        let count = match self.created.get_mut(&created) {
+            Some(count) => count,
+            None => return,
+        };
+        *count -= 1;
+        if *count == 0 {
+            self.created.remove(&created);
+        }
+    }
+
+    /// The change remains pending until it is retried.
+    fn add_failed(&mut self, id: String, created: Timestamp, attempts: usize) {
+        self.failed.push((id, created, attempts));
+    }
+
+    fn take_failed(&mut self) -> Vec<(String, Timestamp, usize)> {
+        std::mem::replace(&mut self.failed, Vec::new())
+    }
+
+    /// All changes that have been stored before the
+    /// oldest pending change have been indexed.
+    fn oldest(&self) -> Option<Timestamp> {
+        self.created.keys().next().cloned()
+    }
+}
+
+fn lock_pending(pending: &Mutex<PendingChanges>) -> MutexGuard<PendingChanges> {
+    match pending.lock() {
+        Ok(pending) => pending,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Executes a batch of jobs with a single commit of the index.
+/// Notifications are sent after all changes have been indexed.
+///
+/// The high water mark of the index is only updated if the
+/// pending changes are known.
+fn execute_jobs(
+    connections: &sqlite::Connections,
+    indexer: &mut EntryIndexer,
+    pending: Option<&Mutex<PendingChanges>>,
+    jobs: Vec<Job>,
+) {
+    let mut changes = Vec::with_capacity(jobs.len());
+    let mut added_entries = Vec::new();
+    let mut updated_entries = Vec::new();
+    for job in jobs {
+        match job {
+            Job::IndexEntry(id, created) => changes.push((id, created)),
+            Job::NotifyEntryAdded(entry) => added_entries.push(entry),
+            Job::NotifyEntryUpdated(entry) => updated_entries.push(entry),
+        }
+    }
+    if !changes.is_empty() {
+        if let Err(err) = index_entries(connections, indexer, pending, &changes) {
+            error!("Failed to reindex entries {:?}: {}", changes, err);
+        }
+    }
+    for entry in added_entries {
+        if let Err(err) = notify_entry_added(connections, &entry) {
+            error!(
+                "Failed to send notifications for newly added entry {}: {}",
+                entry.id, err
+            );
+        }
+    }
+    for entry in updated_entries {
+        if let Err(err) = notify_entry_updated(connections, &entry) {
+            error!(
+                "Failed to send notifications for updated entry {}: {}",
+                entry.id, err
+            );
+        }
+    }
+}
+
+/// Indexes the current versions of the entries and returns
+/// the ids of all entries that could not be indexed.
+fn index_current_entries(
+    connections: &sqlite::Connections,
+    indexer: &mut EntryIndexer,
+    ids: &[&str],
+) -> Result<Vec<String>> {
+    let mut failed_ids = Vec::new();
+    // The current versions are loaded when executing the job
+    // to avoid overwriting recent changes with outdated data
+    let connection = connections.shared()?;
+    for entry in connection.get_entries(ids)? {
+        let ratings = match connection.load_ratings_of_entry(&entry.id) {
+            Ok(ratings) => ratings,
+            Err(err) => {
+                error!("Failed to load ratings of entry {}: {}", entry.id, err);
+                failed_ids.push(entry.id);
+                continue;
+            }
+        };
+        if let Err(err) = usecases::index_entry(indexer, &entry, &ratings) {
+            error!("Failed to reindex entry {}: {}", entry.id, err);
+            failed_ids.push(entry.id);
+        }
+    }
+    Ok(failed_ids)
+}
+
+/// Changes that could not be indexed are retried with the next
+/// batch and hold back the high water mark until they have been
+/// indexed or until they are dropped after too many attempts.
+fn index_entries(
+    connections: &sqlite::Connections,
+    indexer: &mut EntryIndexer,
+    pending: Option<&Mutex<PendingChanges>>,
+    changes: &[(String, Timestamp)],
+) -> Result<()> {
+    let pending = match pending {
+        Some(pending) => pending,
+        None => {
+            let ids: Vec<_> = changes.iter().map(|(id, _)| id.as_str()).collect();
+            index_current_entries(connections, indexer, &ids)?;
+            indexer
+                .flush()
+                .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+            return Ok(());
+        }
+    };
+    let mut attempts: Vec<_> = changes
+        .iter()
+        .map(|(id, created)| (id.clone(), *created, 0))
+        .collect();
+    attempts.extend(lock_pending(pending).take_failed());
+    let failed_ids = {
+        let ids: Vec<_> = attempts.iter().map(|(id, _, _)| id.as_str()).collect();
+        match index_current_entries(connections, indexer, &ids) {
+            Ok(failed_ids) => failed_ids,
+            Err(err) => {
+                error!("Failed to reindex entries {:?}: {}", ids, err);
+                ids.into_iter().map(ToString::to_string).collect()
+            }
+        }
+    };
+    let high_water_mark = {
+        let mut pending = lock_pending(pending);
+        for (id, created, failed_attempts) in attempts {
+            if !failed_ids.contains(&id) {
+                pending.remove(created);
+            } else if failed_attempts + 1 < MAX_INDEX_ATTEMPTS {
+                pending.add_failed(id, created, failed_attempts + 1);
+            } else {
+                error!(
+                    "Failed to reindex entry {} after {} attempts: Giving up",
+                    id, MAX_INDEX_ATTEMPTS
+                );
+                pending.remove(created);
+            }
+        }
+        pending.oldest().unwrap_or_else(Timestamp::now)
+    };
+    // Uncommitted changes are committed with the next flush
+    indexer
+        .flush_with_high_water_mark(high_water_mark)
+        .map_err(|err| RepoError::Other(Box::new(err.compat())))?;
+    Ok(())
+}
+
+fn notify_entry_added(connections: &sqlite::Connections, entry: &Entry) -> Result<()> {
+    let (email_addresses, all_categories) = {
+        let connection = connections.shared()?;
+        let email_addresses =
+            usecases::email_addresses_by_coordinate(&*connection, entry.location.pos)?;
+        let all_categories = connection.all_categories()?;
+        (email_addresses, all_categories)
+    };
+    notify::entry_added(&email_addresses, &entry, all_categories);
+    Ok(())
+}
+
+fn notify_entry_updated(connections: &sqlite::Connections, entry: &Entry) -> Result<()> {
+    let (email_addresses, all_categories) = {
+        let connection = connections.shared()?;
+        let email_addresses =
+            usecases::email_addresses_by_coordinate(&*connection, entry.location.pos)?;
+        let all_categories = connection.all_categories()?;
+        (email_addresses, all_categories)
+    };
+    notify::entry_updated(&email_addresses, &entry, all_categories);
+    Ok(())
+}
+
+/// Executes jobs in a background thread. Pending jobs are lost
+/// when the process terminates. Index changes are recovered by
+/// the catch-up on the next startup.
+#[derive(Clone)]
+pub struct Worker {
+    search_engine: tantivy::SearchEngine,
+    /// `None` if jobs are executed immediately
+    jobs: Option<Arc<Mutex<SyncSender<Job>>>>,
+    pending: Arc<Mutex<PendingChanges>>,
+}
+
+impl Worker {
+    pub fn spawn(connections: sqlite::Connections, search_engine: tantivy::SearchEngine) -> Self {
+        let (sender, receiver) = sync_channel(QUEUE_CAPACITY);
+        let worker_search_engine = search_engine.clone();
+        let pending = Arc::new(Mutex::new(PendingChanges::default()));
+        let worker_pending = Arc::clone(&pending);
+        thread::spawn(move || run(&connections, worker_search_engine, &worker_pending, receiver));
+        Self {
+            search_engine,
+            jobs: Some(Arc::new(Mutex::new(sender))),
+            pending,
+        }
+    }
+
+    /// Executes all jobs immediately without a background
+    /// thread, i.e. the results are visible when returning
+    /// from a request.
+    #[cfg(test)]
+    pub fn immediate(search_engine: tantivy::SearchEngine) -> Self {
+        Self {
+            search_engine,
+            jobs: None,
+            pending: Default::default(),
+        }
+    }
+}
+
+impl JobQueue for Worker {
+    fn submit(&mut self, connections: &sqlite::Connections, job: Job) {
+        let jobs = match self.jobs {
+            Some(ref jobs) => jobs,
+            None => {
+                self.search_engine.submit(connections, job);
+                return;
+            }
+        };
+        // Changes are pending until they have been indexed
+        if let Job::IndexEntry(_, created) = job {
+            lock_pending(&self.pending).add(created);
+        }
+        // The lock must not be held while waiting for a full queue
+        let jobs = match jobs.lock() {
+            Ok(jobs) => jobs.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let job = match jobs.try_send(job) {
+            Ok(()) => return,
+            Err(TrySendError::Full(job)) => {
+                warn!("Job queue is full: Waiting until pending jobs have been executed");
+                job
+            }
+            Err(TrySendError::Disconnected(job)) => job,
+        };
+        if let Err(err) = jobs.send(job) {
+            let job = err.0;
+            error!("Background worker has stopped: Discarding {:?}", job);
+            if let Job::IndexEntry(_, created) = job {
+                lock_pending(&self.pending).remove(created);
+            }
+        }
+    }
+}
+
+fn run(
+    connections: &sqlite::Connections,
+    mut search_engine: tantivy::SearchEngine,
+    pending: &Mutex<PendingChanges>,
+    jobs: Receiver<Job>,
+) {
+    info!("Started background worker");
+    while let Ok(job) = jobs.recv() {
+        let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+        batch.push(job);
+        while batch.len() < MAX_BATCH_SIZE {
+            match jobs.try_recv() {
+                Ok(job) => batch.push(job),
+                Err(_) => break,
+            }
+        }
+        debug!("Executing {} job(s)", batch.len());
+        execute_jobs(connections, &mut search_engine, Some(pending), batch);
+    }
+    info!("Stopped background worker");
+}
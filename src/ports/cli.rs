@@ -3,35 +3,47 @@ use super::web;
 use crate::core::prelude::*;
 use crate::infrastructure::{
     db::{sqlite, tantivy},
+    error::AppError,
+    flows::prelude as flows,
+    geocoding::GeocodingService,
     osm,
 };
 
 use clap::{App, Arg, SubCommand};
 use dotenv::dotenv;
-use std::{env, path::Path, process};
+use std::{env, path::Path, process, result};
 
 const DEFAULT_DB_URL: &str = "openfair.db";
 const DB_CONNECTION_POOL_SIZE: u32 = 10;
 
 embed_migrations!();
 
-fn update_event_locations<D: Db>(db: &mut D) -> Result<()> {
-    let events = db.all_events()?;
+fn update_event_locations(
+    connections: &sqlite::Connections,
+    geocoder: &Geocoder,
+) -> result::Result<(), AppError> {
+    let events = connections.shared()?.all_events()?;
     for mut e in events {
-        if let Some(ref mut loc) = e.location {
-            if let Some(ref addr) = loc.address {
-                if let Some((lat, lng)) = web::api::geocoding::resolve_address_lat_lng(addr) {
-                    if let Some(pos) = MapPoint::try_from_lat_lng_deg(lat, lng) {
-                        if pos.is_valid() {
-                            if let Err(err) = db.update_event(&e) {
-                                warn!("Failed to update location of event {}: {}", e.id, err);
-                            } else {
-                                info!("Updated location of event {}", e.id);
-                            }
-                        }
-                    }
+        // The database must not be locked while resolving
+        // addresses that might be cached in the database
+        let pos = match e.location.as_ref().and_then(|loc| loc.address.as_ref()) {
+            Some(addr) => match geocoder.resolve_address(addr) {
+                Ok(Some(pos)) => pos,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!("Failed to resolve address of event {}: {}", e.id, err);
+                    continue;
                 }
-            }
+            },
+            None => continue,
+        };
+        if let Some(ref mut loc) = e.location {
+            loc.pos = pos;
+        }
+        if let Err(err) = connections.exclusive()?.update_event(&e) {
+            warn!("Failed to update location of event {}: {}", e.id, err);
+        } else {
+            info!("Updated location of event {}", e.id);
         }
     }
     Ok(())
@@ -54,6 +66,12 @@ pub fn run() {
                 .value_name("INDEX_DIR")
                 .help("File system directory for the full-text search index"),
         )
+        .arg(
+            Arg::with_name("idx-lang")
+                .long("idx-lang")
+                .value_name("INDEX_LANGUAGE")
+                .help("Language of the full-text search index: de (default) or en"),
+        )
         .arg(
             Arg::with_name("enable-cors")
                 .long("enable-cors")
@@ -77,6 +95,18 @@ pub fn run() {
                         ),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("index")
+                .about("Full-text search index functionalities")
+                .subcommand(
+                    SubCommand::with_name("rebuild")
+                        .about("rebuild the index of all entries from scratch")
+                        .after_help(
+                            "Only works while the server is stopped. A running server \
+                             rebuilds the index with POST /server/reindex.",
+                        ),
+                ),
+        )
         .get_matches();
 
     let db_url = matches
@@ -97,8 +127,36 @@ pub fn run() {
         .map(ToString::to_string)
         .or_else(|| env::var("INDEX_DIR").map(Option::Some).unwrap_or(None));
     let idx_path = idx_dir.as_ref().map(|dir| Path::new(dir));
-    info!("Initializing Tantivy full-text search engine");
-    let search_engine = tantivy::SearchEngine::init_with_path(idx_path).unwrap();
+    let idx_lang = matches
+        .value_of("idx-lang")
+        .map(ToString::to_string)
+        .or_else(|| env::var("INDEX_LANGUAGE").ok())
+        .map(|lang| match lang.parse::<tantivy::TextLanguage>() {
+            Ok(idx_lang) => idx_lang,
+            Err(err) => {
+                println!("Invalid index language: {}", err);
+                process::exit(1)
+            }
+        })
+        .unwrap_or_default();
+    info!(
+        "Initializing Tantivy full-text search engine (language = {})",
+        idx_lang
+    );
+    let mut search_engine = match tantivy::SearchEngine::init_with_path(idx_path, idx_lang) {
+        Ok(search_engine) => search_engine,
+        Err(err) => {
+            println!(
+                "Could not open the full-text search index (in use by a running server?): {}",
+                err
+            );
+            process::exit(1)
+        }
+    };
+
+    let geocoding = GeocodingService::from_env()
+        .unwrap()
+        .with_cache(connections.clone());
 
     match matches.subcommand() {
         ("osm", Some(osm_matches)) => match osm_matches.subcommand() {
@@ -117,14 +175,37 @@ pub fn run() {
             }
             _ => println!("{}", osm_matches.usage()),
         },
+        ("index", Some(index_matches)) => match index_matches.subcommand() {
+            ("rebuild", Some(_)) => {
+                info!("Rebuilding the entry index...");
+                let result = flows::rebuild_entry_index(
+                    &connections,
+                    &mut search_engine,
+                    &mut |indexed_count, total_count| {
+                        println!("Indexed {} of {} entries", indexed_count, total_count)
+                    },
+                );
+                match result {
+                    Ok(indexed_count) => {
+                        println!("Rebuilt the entry index with {} entries", indexed_count)
+                    }
+                    Err(err) => {
+                        println!("Could not rebuild the entry index: {}", err);
+                        process::exit(1)
+                    }
+                }
+            }
+            _ => println!("{}", index_matches.usage()),
+        },
         _ => {
             if matches.is_present("fix-event-address-location") {
                 info!("Updating all event locations...");
-                update_event_locations(&mut *connections.exclusive().unwrap()).unwrap();
+                update_event_locations(&connections, &geocoding).unwrap();
             }
             web::run(
                 connections,
                 search_engine,
+                geocoding,
                 matches.is_present("enable-cors"),
             );
         }
@@ -1,4 +1,7 @@
-use super::{super::guards::Bearer, geocoding::*, *};
+use super::{
+    super::{geocoding::GeocodingService, guards::Bearer},
+    *,
+};
 
 use crate::core::util::{geo::MapBbox, validate};
 
@@ -8,7 +11,7 @@ use rocket::{
     request::{FromQuery, Query},
 };
 
-fn check_and_set_address_location(e: &mut usecases::NewEvent) {
+fn check_and_set_address_location(geocoder: &Geocoder, e: &mut usecases::NewEvent) {
     // TODO: Parse logical parts of NewEvent earlier
     let addr = Address {
         street: e.street.clone(),
@@ -16,22 +19,31 @@ fn check_and_set_address_location(e: &mut usecases::NewEvent) {
         city: e.city.clone(),
         country: e.country.clone(),
     };
-    if let Some((lat, lng)) = resolve_address_lat_lng(&addr) {
-        e.lat = Some(lat);
-        e.lng = Some(lng);
+    match geocoder.resolve_address(&addr) {
+        Ok(Some(pos)) => {
+            let (lat, lng) = pos.to_lat_lng_deg();
+            e.lat = Some(lat);
+            e.lng = Some(lng);
+        }
+        Ok(None) => (),
+        Err(err) => {
+            warn!("Failed to resolve the address of an event: {}", err);
+        }
     }
 }
 
 #[post("/events", format = "application/json", data = "<e>")]
 pub fn post_event_with_token(
-    db: sqlite::Connections,
+    connections: sqlite::Connections,
+    mut search_engine: tantivy::SearchEngine,
+    geocoding: GeocodingService,
     token: Bearer,
     e: Json<usecases::NewEvent>,
 ) -> Result<String> {
     let mut e = e.into_inner();
     e.token = Some(token.0);
-    check_and_set_address_location(&mut e);
-    let id = usecases::create_new_event(&mut *db.exclusive()?, e.clone())?;
+    check_and_set_address_location(&geocoding, &mut e);
+    let id = flows::create_event(&connections, &mut search_engine, e)?;
     Ok(Json(id))
 }
 
@@ -72,15 +84,17 @@ pub fn put_event(
 
 #[put("/events/<id>", format = "application/json", data = "<e>")]
 pub fn put_event_with_token(
-    db: sqlite::Connections,
+    connections: sqlite::Connections,
+    mut search_engine: tantivy::SearchEngine,
+    geocoding: GeocodingService,
     token: Bearer,
     id: &RawStr,
     e: Json<usecases::UpdateEvent>,
 ) -> Result<()> {
     let mut e = e.into_inner();
     e.token = Some(token.0);
-    check_and_set_address_location(&mut e);
-    usecases::update_event(&mut *db.exclusive()?, &id.to_string(), e.clone())?;
+    check_and_set_address_location(&geocoding, &mut e);
+    flows::update_event(&connections, &mut search_engine, &id.to_string(), e)?;
     Ok(Json(()))
 }
 
@@ -91,6 +105,31 @@ pub struct EventQuery {
     pub bbox: Option<MapBbox>,
     pub start_min: Option<i64>,
     pub start_max: Option<i64>,
+    pub text: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    pub fn into_index_query(self) -> (EventIndexQuery, Option<String>) {
+        let EventQuery {
+            tags,
+            created_by,
+            bbox,
+            start_min,
+            start_max,
+            text,
+            ..
+        } = self;
+        let index_query = EventIndexQuery {
+            bbox,
+            start_min: start_min.map(|x| NaiveDateTime::from_timestamp(x, 0)),
+            start_max: start_max.map(|x| NaiveDateTime::from_timestamp(x, 0)),
+            tags: tags.unwrap_or_default(),
+            text,
+        };
+        (index_query, created_by)
+    }
 }
 
 impl<'q> FromQuery<'q> for EventQuery {
@@ -117,6 +156,14 @@ impl<'q> FromQuery<'q> for EventQuery {
             .filter(|v| !v.is_empty())
             .nth(0);
 
+        q.text = query
+            .clone()
+            .filter(|i| i.key == "text")
+            .map(|i| i.value.url_decode_lossy())
+            .map(|v| v.trim().to_owned())
+            .filter(|v| !v.is_empty())
+            .nth(0);
+
         let start_min = query
             .clone()
             .filter(|i| i.key == "start_min")
@@ -139,6 +186,28 @@ impl<'q> FromQuery<'q> for EventQuery {
             q.start_max = Some(x);
         }
 
+        let offset = query
+            .clone()
+            .filter(|i| i.key == "offset")
+            .map(|i| i.value.url_decode_lossy())
+            .filter(|v| !v.is_empty())
+            .nth(0);
+        if let Some(o) = offset {
+            let x = o.parse::<usize>().map_err(|_| ParameterError::InvalidOffset)?;
+            q.offset = Some(x);
+        }
+
+        let limit = query
+            .clone()
+            .filter(|i| i.key == "limit")
+            .map(|i| i.value.url_decode_lossy())
+            .filter(|v| !v.is_empty())
+            .nth(0);
+        if let Some(l) = limit {
+            let x = l.parse::<usize>().map_err(|_| ParameterError::InvalidLimit)?;
+            q.limit = Some(x);
+        }
+
         let bbox = query
             .filter(|i| i.key == "bbox")
             .map(|i| i.value.url_decode_lossy())
@@ -159,17 +228,20 @@ impl<'q> FromQuery<'q> for EventQuery {
 #[get("/events?<query..>")]
 pub fn get_events_with_token(
     db: sqlite::Connections,
+    search_engine: tantivy::SearchEngine,
     token: Bearer,
     query: EventQuery,
 ) -> Result<Vec<json::Event>> {
     //TODO: check token
+    let (offset, limit) = (query.offset.unwrap_or(0), query.limit);
+    let (index_query, created_by) = query.into_index_query();
     let events = usecases::query_events(
         &*db.shared()?,
-        query.tags,
-        query.bbox,
-        query.start_min.map(|x| NaiveDateTime::from_timestamp(x, 0)),
-        query.start_max.map(|x| NaiveDateTime::from_timestamp(x, 0)),
-        query.created_by,
+        &search_engine,
+        index_query,
+        offset,
+        limit,
+        created_by,
         Some(token.0),
     )?;
     let events = events.into_iter().map(json::Event::from).collect();
@@ -177,17 +249,23 @@ pub fn get_events_with_token(
 }
 
 #[get("/events?<query..>", rank = 2)]
-pub fn get_events(db: sqlite::Connections, query: EventQuery) -> Result<Vec<json::Event>> {
+pub fn get_events(
+    db: sqlite::Connections,
+    search_engine: tantivy::SearchEngine,
+    query: EventQuery,
+) -> Result<Vec<json::Event>> {
     if query.created_by.is_some() {
         return Err(Error::Parameter(ParameterError::Unauthorized).into());
     }
+    let (offset, limit) = (query.offset.unwrap_or(0), query.limit);
+    let (index_query, created_by) = query.into_index_query();
     let events = usecases::query_events(
         &*db.shared()?,
-        query.tags,
-        query.bbox,
-        query.start_min.map(|x| NaiveDateTime::from_timestamp(x, 0)),
-        query.start_max.map(|x| NaiveDateTime::from_timestamp(x, 0)),
-        query.created_by,
+        &search_engine,
+        index_query,
+        offset,
+        limit,
+        created_by,
         None,
     )?;
     let events = events.into_iter().map(json::Event::from).collect();
@@ -200,8 +278,13 @@ pub fn delete_event(mut _db: sqlite::Connections, _id: &RawStr) -> Status {
 }
 
 #[delete("/events/<id>")]
-pub fn delete_event_with_token(db: sqlite::Connections, token: Bearer, id: &RawStr) -> Result<()> {
-    usecases::delete_event(&mut *db.exclusive()?, &id.to_string(), &token.0)?;
+pub fn delete_event_with_token(
+    connections: sqlite::Connections,
+    mut search_engine: tantivy::SearchEngine,
+    token: Bearer,
+    id: &RawStr,
+) -> Result<()> {
+    flows::delete_event(&connections, &mut search_engine, &id.to_string(), &token.0)?;
     Ok(Json(()))
 }
 
@@ -566,6 +649,8 @@ mod tests {
                 registration: Some(RegistrationType::Email),
                 organizer: None,
                 archived: None,
+                rrule: None,
+                exdates: vec![],
             };
             db.exclusive().unwrap().create_event(e).unwrap();
             let req = client.get("/events/1234").header(ContentType::JSON);
@@ -600,6 +685,8 @@ mod tests {
                         registration: None,
                         organizer: None,
                         archived: None,
+                        rrule: None,
+                        exdates: vec![],
                     })
                     .unwrap();
             }
@@ -633,6 +720,8 @@ mod tests {
                         registration: None,
                         organizer: None,
                         archived: None,
+                        rrule: None,
+                        exdates: vec![],
                     })
                     .unwrap();
             }
@@ -669,6 +758,8 @@ mod tests {
                         registration: None,
                         organizer: None,
                         archived: None,
+                        rrule: None,
+                        exdates: vec![],
                     })
                     .unwrap();
             }
@@ -688,6 +779,13 @@ mod tests {
             assert!(!body_str.contains("\"id\":\"a\""));
             assert!(body_str.contains("\"id\":\"b\""));
             assert!(!body_str.contains("\"id\":\"c\""));
+
+            let req = client.get("/events?tag=B").header(ContentType::JSON);
+            let mut response = req.dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+            assert!(!body_str.contains("\"id\":\"a\""));
+            assert!(body_str.contains("\"id\":\"b\""));
         }
 
         #[test]
@@ -731,6 +829,8 @@ mod tests {
                         registration: None,
                         organizer: None,
                         archived: None,
+                        rrule: None,
+                        exdates: vec![],
                     })
                     .unwrap();
                 db.exclusive()
@@ -800,6 +900,8 @@ mod tests {
                         registration: None,
                         organizer: None,
                         archived: None,
+                        rrule: None,
+                        exdates: vec![],
                     })
                     .unwrap();
             }
@@ -838,6 +940,8 @@ mod tests {
                         registration: None,
                         organizer: None,
                         archived: None,
+                        rrule: None,
+                        exdates: vec![],
                     })
                     .unwrap();
             }
@@ -880,6 +984,8 @@ mod tests {
                         registration: None,
                         organizer: None,
                         archived: None,
+                        rrule: None,
+                        exdates: vec![],
                     })
                     .unwrap();
             }
@@ -896,6 +1002,134 @@ mod tests {
             assert!(objects[1].contains("\"id\":\"0.3-5\""));
             assert!(objects[2].contains("\"id\":\"7-7.9\""));
         }
+
+        #[test]
+        fn filtered_by_text() {
+            let (client, db) = setup();
+            db.exclusive()
+                .unwrap()
+                .create_org(Organization {
+                    id: "foo".into(),
+                    name: "bar".into(),
+                    owned_tags: vec![],
+                    api_token: "foo".into(),
+                })
+                .unwrap();
+            let new_events = &[
+                r#"{"title":"Repair Café","start":100,"created_by":"foo@bar.com"}"#,
+                r#"{"title":"Kleidertausch","start":200,"created_by":"foo@bar.com","organizer":"Repair-Initiative"}"#,
+                r#"{"title":"Gartentreffen","start":300,"created_by":"foo@bar.com","description":"Wir reparieren nichts"}"#,
+            ];
+            for new_event in new_events {
+                let res = client
+                    .post("/events")
+                    .header(ContentType::JSON)
+                    .header(Header::new("Authorization", "Bearer foo"))
+                    .body(*new_event)
+                    .dispatch();
+                assert_eq!(res.status(), Status::Ok);
+            }
+
+            let mut res = client
+                .get("/events?text=repair")
+                .header(ContentType::JSON)
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            test_json(&res);
+            let body_str = res.body().and_then(|b| b.into_string()).unwrap();
+            let objects: Vec<_> = body_str.split("},{").collect();
+            assert_eq!(objects.len(), 2);
+            assert!(objects[0].contains("\"title\":\"Repair Café\""));
+            assert!(objects[1].contains("\"title\":\"Kleidertausch\""));
+
+            let mut res = client
+                .get("/events?text=repair&start_min=150")
+                .header(ContentType::JSON)
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+            let body_str = res.body().and_then(|b| b.into_string()).unwrap();
+            assert!(!body_str.contains("Repair Café"));
+            assert!(body_str.contains("Kleidertausch"));
+            assert!(!body_str.contains("Gartentreffen"));
+
+            let mut pages = vec![];
+            for offset in 0..2 {
+                let mut res = client
+                    .get(format!("/events?text=repair&offset={}&limit=1", offset))
+                    .header(ContentType::JSON)
+                    .dispatch();
+                assert_eq!(res.status(), Status::Ok);
+                pages.push(res.body().and_then(|b| b.into_string()).unwrap());
+            }
+            assert!(pages.iter().all(|p| p.split("},{").count() == 1));
+            assert!(pages.iter().any(|p| p.contains("Repair Café")));
+            assert!(pages.iter().any(|p| p.contains("Kleidertausch")));
+
+            let res = client
+                .get("/events?text=repair&limit=0")
+                .header(ContentType::JSON)
+                .dispatch();
+            assert_eq!(res.status(), Status::BadRequest);
+        }
+
+        #[test]
+        fn recurring_events_within_range() {
+            let (client, db) = setup();
+            db.exclusive()
+                .unwrap()
+                .create_org(Organization {
+                    id: "foo".into(),
+                    name: "bar".into(),
+                    owned_tags: vec![],
+                    api_token: "foo".into(),
+                })
+                .unwrap();
+            // Every Saturday from 2019-06-01 10:00 to 12:00 UTC,
+            // except for 2019-06-15
+            let res = client
+                .post("/events")
+                .header(ContentType::JSON)
+                .header(Header::new("Authorization", "Bearer foo"))
+                .body(
+                    r#"{"title":"Repair Café","start":1559383200,"end":1559390400,"created_by":"foo@bar.com","rrule":"FREQ=WEEKLY;BYDAY=SA","exdates":[1560592800]}"#,
+                )
+                .dispatch();
+            assert_eq!(res.status(), Status::Ok);
+
+            for query in &["", "text=repair&"] {
+                let mut res = client
+                    .get(format!(
+                        "/events?{}start_min=1559728800&start_max=1561888800",
+                        query
+                    ))
+                    .header(ContentType::JSON)
+                    .dispatch();
+                assert_eq!(res.status(), Status::Ok);
+                test_json(&res);
+                let body_str = res.body().and_then(|b| b.into_string()).unwrap();
+                let events: Vec<json::Event> = serde_json::from_str(&body_str).unwrap();
+                let occurrences: Vec<_> = events.iter().map(|e| (e.start, e.end)).collect();
+                assert_eq!(
+                    vec![
+                        (1559988000, Some(1559995200)),
+                        (1561197600, Some(1561204800)),
+                        (1561802400, Some(1561809600)),
+                    ],
+                    occurrences
+                );
+                let rrule = events[0].rrule.as_ref().map(String::as_str);
+                assert_eq!(Some("FREQ=WEEKLY;BYDAY=SA"), rrule);
+                assert_eq!(vec![1560592800], events[0].exdates);
+            }
+
+            let res = client
+                .post("/events")
+                .header(ContentType::JSON)
+                .header(Header::new("Authorization", "Bearer foo"))
+                .body(r#"{"title":"x","start":0,"created_by":"foo@bar.com","rrule":"FREQ=SOMETIMES"}"#)
+                .dispatch();
+            assert_eq!(res.status(), Status::BadRequest);
+        }
     }
 
     mod update {
@@ -959,6 +1193,8 @@ mod tests {
                 registration: None,
                 organizer: None,
                 archived: None,
+                rrule: None,
+                exdates: vec![],
             };
             db.exclusive().unwrap().create_event(e.clone()).unwrap();
             let res = client
@@ -1000,6 +1236,8 @@ mod tests {
                 registration: None,
                 organizer: None,
                 archived: None,
+                rrule: None,
+                exdates: vec![],
             };
             db.exclusive().unwrap().create_event(e.clone()).unwrap();
             let res = client
@@ -1037,6 +1275,8 @@ mod tests {
                 registration: None,
                 organizer: None,
                 archived: None,
+                rrule: None,
+                exdates: vec![],
             };
             db.exclusive().unwrap().create_event(e.clone()).unwrap();
             let res = client
@@ -1076,6 +1316,8 @@ mod tests {
                 registration: None,
                 organizer: None,
                 archived: None,
+                rrule: None,
+                exdates: vec![],
             };
             db.exclusive().unwrap().create_event(e.clone()).unwrap();
             let res = client
@@ -1150,6 +1392,8 @@ mod tests {
                 registration: None,
                 organizer: None,
                 archived: None,
+                rrule: None,
+                exdates: vec![],
             };
             let e1 = Event {
                 id: "9999".into(),
@@ -1165,6 +1409,8 @@ mod tests {
                 registration: None,
                 organizer: None,
                 archived: None,
+                rrule: None,
+                exdates: vec![],
             };
             db.exclusive().unwrap().create_event(e0.clone()).unwrap();
             db.exclusive().unwrap().create_event(e1.clone()).unwrap();
@@ -1,4 +1,4 @@
-use super::guards::*;
+use super::{geocoding::GeocodingService, guards::*};
 
 use crate::{
     adapters::{self, json},
@@ -11,7 +11,7 @@ use crate::{
         db::{sqlite, tantivy},
         error::AppError,
         flows::prelude as flows,
-        notify,
+        notify, worker,
     },
 };
 
@@ -24,15 +24,17 @@ use rocket::{
     Route,
 };
 use rocket_contrib::json::Json;
-use std::result;
+use std::{result, thread};
 
 mod count;
 pub mod events;
-pub mod geocoding;
+mod geocoding;
 mod ratings;
 mod search;
+mod suggest;
 #[cfg(test)]
 pub mod tests;
+mod tiles;
 mod users;
 
 type Result<T> = result::Result<Json<T>, AppError>;
@@ -46,8 +48,11 @@ pub fn routes() -> Vec<Route> {
         get_bbox_subscriptions,
         unsubscribe_all_bboxes,
         get_entry,
+        get_entry_history,
+        get_entry_version,
         post_entry,
         put_entry,
+        post_entry_revert,
         events::post_event,
         events::post_event_with_token,
         events::get_event,
@@ -66,10 +71,16 @@ pub fn routes() -> Vec<Route> {
         get_category,
         get_tags,
         search::get_search,
+        search::get_clusters,
+        suggest::get_suggest,
+        tiles::get_tile,
+        geocoding::get_reverse_geocoding,
         get_duplicates,
         count::get_count_entries,
         count::get_count_tags,
         get_version,
+        post_reindex,
+        get_reindex,
         csv_export,
         get_api
     ]
@@ -100,6 +111,21 @@ fn get_entry(db: sqlite::Connections, ids: String) -> Result<Vec<json::Entry>> {
     Ok(Json(results))
 }
 
+#[get("/entries/<id>/history")]
+fn get_entry_history(db: sqlite::Connections, id: String) -> Result<Vec<json::EntryHistoryItem>> {
+    let history = usecases::get_entry_history(&*db.shared()?, &id)?;
+    Ok(Json(history.into_iter().map(Into::into).collect()))
+}
+
+#[get("/entries/<id>/versions/<version>")]
+fn get_entry_version(db: sqlite::Connections, id: String, version: u64) -> Result<json::Entry> {
+    let db = db.shared()?;
+    let e = usecases::get_entry_version(&*db, &id, version)?;
+    // Ratings are not versioned
+    let r = db.load_ratings_of_entry(&e.id)?;
+    Ok(Json(json::Entry::from_entry_with_ratings(e, r)))
+}
+
 #[get("/duplicates/<ids>")]
 fn get_duplicates(
     db: sqlite::Connections,
@@ -122,6 +148,66 @@ fn get_version() -> &'static str {
     env!("CARGO_PKG_VERSION")
 }
 
+#[post("/server/reindex")]
+fn post_reindex(
+    db: sqlite::Connections,
+    mut search_engine: tantivy::SearchEngine,
+    user: Login,
+) -> result::Result<Status, AppError> {
+    let Login(username) = user;
+    usecases::authorize_admin(&*db.shared()?, &username)?;
+    let rebuild_guard = match search_engine.try_lock_rebuild() {
+        Some(rebuild_guard) => rebuild_guard,
+        None => return Ok(Status::Conflict),
+    };
+    info!("Rebuilding the entry index on behalf of {}...", username);
+    // Rebuilding the whole index takes too long for a single request
+    thread::spawn(move || {
+        let result = flows::rebuild_entry_index(
+            &db,
+            &mut search_engine,
+            &mut |indexed_count, total_count| {
+                info!("Indexed {} of {} entries", indexed_count, total_count);
+                rebuild_guard.report_progress(indexed_count, total_count);
+            },
+        );
+        match result {
+            Ok(indexed_count) => info!("Rebuilt the entry index with {} entries", indexed_count),
+            Err(err) => {
+                error!("Failed to rebuild the entry index: {}", err);
+                rebuild_guard.report_error(err.to_string());
+            }
+        }
+    });
+    Ok(Status::Accepted)
+}
+
+#[get("/server/reindex")]
+fn get_reindex(
+    db: sqlite::Connections,
+    search_engine: tantivy::SearchEngine,
+    user: Login,
+) -> Result<json::RebuildIndexStatus> {
+    let Login(username) = user;
+    usecases::authorize_admin(&*db.shared()?, &username)?;
+    let tantivy::RebuildStatus {
+        in_progress,
+        started,
+        finished,
+        indexed_count,
+        total_count,
+        error,
+    } = search_engine.rebuild_status();
+    Ok(Json(json::RebuildIndexStatus {
+        in_progress,
+        started: started.map(Into::into),
+        finished: finished.map(Into::into),
+        indexed_count,
+        total_count,
+        error,
+    }))
+}
+
 #[get("/server/api.yaml")]
 fn get_api() -> Content<&'static str> {
     let data = include_str!("../../../../openapi.yaml");
@@ -211,12 +297,14 @@ fn get_bbox_subscriptions(
 #[post("/entries", format = "application/json", data = "<body>")]
 fn post_entry(
     connections: sqlite::Connections,
-    mut search_engine: tantivy::SearchEngine,
+    mut worker: worker::Worker,
+    geocoding: GeocodingService,
     body: Json<usecases::NewEntry>,
 ) -> Result<String> {
     Ok(Json(flows::create_entry(
         &connections,
-        &mut search_engine,
+        &mut worker,
+        Some(&geocoding),
         body.into_inner(),
     )?))
 }
@@ -224,13 +312,24 @@ fn post_entry(
 #[put("/entries/<id>", format = "application/json", data = "<data>")]
 fn put_entry(
     connections: sqlite::Connections,
-    mut search_engine: tantivy::SearchEngine,
+    mut worker: worker::Worker,
     id: String,
     data: Json<usecases::UpdateEntry>,
 ) -> Result<String> {
-    Ok(Json(
-        flows::update_entry(&connections, &mut search_engine, id, data.into_inner())?.id,
-    ))
+    Ok(Json(flows::update_entry(&connections, &mut worker, id, data.into_inner())?.id))
+}
+
+#[post("/entries/<id>/versions/<version>/revert")]
+fn post_entry_revert(
+    connections: sqlite::Connections,
+    mut worker: worker::Worker,
+    user: Login,
+    id: String,
+    version: u64,
+) -> Result<String> {
+    let Login(username) = user;
+    let entry = flows::revert_entry(&connections, &mut worker, &username, id, version)?;
+    Ok(Json(entry.id))
 }
 
 #[get("/tags")]
@@ -264,7 +363,9 @@ fn get_category(connections: sqlite::Connections, ids: String) -> Result<Vec<Cat
 
 #[derive(FromForm, Clone, Serialize)]
 struct CsvExport {
-    bbox: String,
+    bbox: Option<String>,
+    area: Option<String>,
+    as_of: Option<i64>,
 }
 
 // TODO: CSV export should only be permitted with a valid API key!
@@ -275,46 +376,82 @@ fn csv_export(
     search_engine: tantivy::SearchEngine,
     export: Form<CsvExport>,
 ) -> result::Result<Content<String>, AppError> {
-    let bbox = export
-        .bbox
-        .parse::<geo::MapBbox>()
-        .map_err(|_| ParameterError::Bbox)
+    let area = export
+        .area
+        .as_ref()
+        .map(String::as_str)
+        .map(search::parse_area)
+        .transpose()
         .map_err(Error::Parameter)
         .map_err(AppError::Business)?;
 
-    let req = usecases::SearchRequest {
-        bbox,
-        ids: vec![],
-        categories: vec![],
-        hash_tags: vec![],
-        text: None,
+    // The bounding box is optional when exporting an area
+    let bbox = match (&export.bbox, &area) {
+        (Some(bbox), _) => bbox
+            .parse::<geo::MapBbox>()
+            .map_err(|_| ParameterError::Bbox)
+            .map_err(Error::Parameter)
+            .map_err(AppError::Business)?,
+        (None, Some(area)) => area.bbox(),
+        (None, None) => {
+            return Err(AppError::Business(Error::Parameter(ParameterError::Bbox)));
+        }
     };
 
     let entries_categories_and_ratings = {
         let db = connections.shared()?;
         let all_categories: Vec<_> = db.all_categories()?;
-        let limit = db.count_entries()? + 100;
-        usecases::search(&search_engine, req, limit)?
-            .0
-            .into_iter()
-            .filter_map(|indexed_entry| {
-                let IndexedEntry {
-                    ref id,
-                    ref ratings,
-                    ..
-                } = indexed_entry;
-                if let Ok(entry) = db.get_entry(id) {
-                    let categories = all_categories
-                        .iter()
-                        .filter(|c1| entry.categories.iter().any(|c2| *c2 == c1.id))
-                        .cloned()
-                        .collect::<Vec<Category>>();
-                    Some((entry, categories, ratings.total()))
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>()
+        let categories_of_entry = |entry: &Entry| {
+            all_categories
+                .iter()
+                .filter(|c1| entry.categories.iter().any(|c2| *c2 == c1.id))
+                .cloned()
+                .collect::<Vec<Category>>()
+        };
+        if let Some(as_of) = export.as_of {
+            // The search index only contains the current entries
+            usecases::query_entries_as_of(&*db, as_of.into(), &bbox, area.as_ref())?
+                .into_iter()
+                .map(|(entry, ratings)| {
+                    let categories = categories_of_entry(&entry);
+                    (entry, categories, ratings.total())
+                })
+                .collect::<Vec<_>>()
+        } else {
+            let req = usecases::SearchRequest {
+                bbox,
+                radius: None,
+                area,
+                ids: vec![],
+                categories: vec![],
+                hash_tags: vec![],
+                text: None,
+                min_rating: None,
+                min_context_ratings: vec![],
+                open_at: None,
+                fuzzy: false,
+                sort: Default::default(),
+                highlight: false,
+                facets: None,
+            };
+            let limit = db.count_entries()? + 100;
+            usecases::search_all(&search_engine, req, limit)?
+                .into_iter()
+                .filter_map(|indexed_entry| {
+                    let IndexedEntry {
+                        ref id,
+                        ref ratings,
+                        ..
+                    } = indexed_entry;
+                    if let Ok(entry) = db.get_entry(id) {
+                        let categories = categories_of_entry(&entry);
+                        Some((entry, categories, ratings.total()))
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        }
     };
 
     let records: Vec<adapters::csv::CsvRecord> = entries_categories_and_ratings
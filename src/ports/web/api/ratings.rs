@@ -5,10 +5,10 @@ use crate::{core::util, infrastructure::flows::prelude as flows};
 #[post("/ratings", format = "application/json", data = "<data>")]
 pub fn post_rating(
     connections: sqlite::Connections,
-    mut search_engine: tantivy::SearchEngine,
+    mut worker: worker::Worker,
     data: Json<usecases::RateEntry>,
 ) -> Result<()> {
-    let _ = flows::create_rating(&connections, &mut search_engine, data.into_inner())?;
+    let _ = flows::create_rating(&connections, &mut worker, data.into_inner())?;
     Ok(Json(()))
 }
 
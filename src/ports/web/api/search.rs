@@ -8,18 +8,38 @@ use crate::{
     infrastructure::{db::tantivy, error::AppError},
 };
 
+use chrono::{Duration, NaiveDateTime, Utc};
 use rocket::{self, request::Form};
 use rocket_contrib::json::Json;
 use std::result;
 
 #[derive(FromForm, Clone)]
 pub struct SearchQuery {
-    bbox: String,
+    bbox: Option<String>,
     categories: Option<String>,
     ids: Option<String>,
     tags: Option<String>,
     text: Option<String>,
+    fuzzy: Option<bool>,
+    center: Option<String>,
+    radius: Option<f64>,
+    area: Option<String>,
+    min_rating: Option<f64>,
+    min_diversity: Option<f64>,
+    min_fairness: Option<f64>,
+    min_humanity: Option<f64>,
+    min_renewable: Option<f64>,
+    min_solidarity: Option<f64>,
+    min_transparency: Option<f64>,
+    open_now: Option<bool>,
+    utc_offset: Option<i32>,
+    open_at: Option<String>,
+    sort: Option<String>,
+    highlight: Option<bool>,
+    facets: Option<bool>,
+    facet_tags: Option<usize>,
 
+    offset: Option<usize>,
     limit: Option<usize>,
 }
 
@@ -27,18 +47,84 @@ type Result<T> = result::Result<Json<T>, AppError>;
 
 const MAX_RESULTS: usize = 100;
 
-#[get("/search?<search..>")]
-pub fn get_search(
-    search_engine: tantivy::SearchEngine,
-    search: Form<SearchQuery>,
-) -> Result<json::SearchResponse> {
-    let bbox = search
-        .bbox
-        .parse::<geo::MapBbox>()
-        .map_err(|_| ParameterError::Bbox)
-        .map_err(Error::Parameter)
-        .map_err(AppError::Business)?;
+const DEFAULT_FACET_TAGS: usize = 10;
+
+const MAX_FACET_TAGS: usize = 100;
+
+/// Deep pages are expensive, because all preceding
+/// results need to be collected and ranked.
+pub(super) const MAX_SEARCH_WINDOW: usize = 10_000;
+
+/// The range of UTC offsets of all time zones in minutes
+const MIN_UTC_OFFSET: i32 = -12 * 60;
+const MAX_UTC_OFFSET: i32 = 14 * 60;
+
+/// Parses a GeoJSON `Polygon` or `MultiPolygon` geometry.
+pub(super) fn parse_area(area: &str) -> result::Result<geo::MapMultiPolygon, ParameterError> {
+    serde_json::from_str::<json::GeoJsonArea>(area)
+        .map_err(|err| {
+            warn!("Failed to parse area: {}", err);
+            ParameterError::Area
+        })?
+        .try_into_map_area()
+        .ok_or_else(|| {
+            warn!("Invalid area: {}", area);
+            ParameterError::Area
+        })
+}
+
+/// Parses a local date and time with or without seconds,
+/// e.g. `2019-06-03T10:30`.
+fn parse_open_at(open_at: &str) -> result::Result<NaiveDateTime, ParameterError> {
+    let open_at = open_at.trim();
+    NaiveDateTime::parse_from_str(open_at, "%Y-%m-%dT%H:%M")
+        .or_else(|_| open_at.parse())
+        .map_err(|_| {
+            warn!("Invalid time for opening hours: {}", open_at);
+            ParameterError::OpenAt
+        })
+}
+
+/// The current local time of the client with the given
+/// offset from UTC in minutes. Defaults to UTC.
+fn local_time_now(utc_offset: Option<i32>) -> result::Result<NaiveDateTime, ParameterError> {
+    let offset = utc_offset.unwrap_or(0);
+    if offset < MIN_UTC_OFFSET || offset > MAX_UTC_OFFSET {
+        warn!("Invalid UTC offset: {}", offset);
+        return Err(ParameterError::UtcOffset);
+    }
+    Ok(Utc::now().naive_utc() + Duration::minutes(offset.into()))
+}
+
+fn parse_sort_order(
+    sort: Option<&str>,
+    center: Option<geo::MapPoint>,
+    within_radius: bool,
+    fuzzy: bool,
+) -> result::Result<EntrySortOrder, ParameterError> {
+    match sort.map(str::trim).map(str::to_lowercase).as_ref().map(String::as_str) {
+        // Nearest entries first when searching around a center
+        None | Some("") if within_radius => center
+            .map(EntrySortOrder::Distance)
+            .ok_or(ParameterError::InvalidPosition),
+        // Exact matches should precede fuzzy matches
+        None | Some("") if fuzzy => Ok(EntrySortOrder::Relevance),
+        None | Some("") | Some("rating") => Ok(EntrySortOrder::Rating),
+        Some("relevance") => Ok(EntrySortOrder::Relevance),
+        Some("newest") => Ok(EntrySortOrder::Newest),
+        Some("distance") => center
+            .map(EntrySortOrder::Distance)
+            .ok_or(ParameterError::InvalidPosition),
+        Some(sort) => {
+            warn!("Invalid sort order: {}", sort);
+            Err(ParameterError::SortOrder)
+        }
+    }
+}
 
+/// Parses all parameters of the search request except
+/// the offset and limit of the results.
+fn parse_search_request(search: &SearchQuery) -> result::Result<usecases::SearchRequest, AppError> {
     let ids = search
         .ids
         .as_ref()
@@ -62,13 +148,126 @@ pub fn get_search(
 
     let text = search.text.as_ref().map(String::as_str);
 
-    let req = usecases::SearchRequest {
+    let center = search
+        .center
+        .as_ref()
+        .map(|center| center.parse::<geo::MapPoint>())
+        .transpose()
+        .map_err(|_| ParameterError::InvalidPosition)
+        .map_err(Error::Parameter)
+        .map_err(AppError::Business)?;
+
+    let radius = if let Some(radius) = search.radius {
+        if !(radius > 0.0 && radius.is_finite()) {
+            warn!("Invalid search radius: {}", radius);
+            return Err(AppError::Business(Error::Parameter(ParameterError::Radius)));
+        }
+        let center = center.ok_or_else(|| {
+            warn!("Missing center for search radius");
+            AppError::Business(Error::Parameter(ParameterError::InvalidPosition))
+        })?;
+        Some((center, Distance::from_meters(radius)))
+    } else {
+        None
+    };
+
+    let area = search
+        .area
+        .as_ref()
+        .map(String::as_str)
+        .map(parse_area)
+        .transpose()
+        .map_err(Error::Parameter)
+        .map_err(AppError::Business)?;
+
+    // The bounding box is optional when searching within a radius or an area
+    let bbox = match (&search.bbox, radius, &area) {
+        (Some(bbox), _, _) => bbox
+            .parse::<geo::MapBbox>()
+            .map_err(|_| ParameterError::Bbox)
+            .map_err(Error::Parameter)
+            .map_err(AppError::Business)?,
+        (None, Some((center, radius)), _) => geo::MapBbox::centered_around(center, radius),
+        (None, None, Some(area)) => area.bbox(),
+        (None, None, None) => {
+            return Err(AppError::Business(Error::Parameter(ParameterError::Bbox)));
+        }
+    };
+
+    let min_rating = search.min_rating.map(AvgRatingValue::from);
+
+    let min_context_ratings = [
+        (RatingContext::Diversity, search.min_diversity),
+        (RatingContext::Fairness, search.min_fairness),
+        (RatingContext::Humanity, search.min_humanity),
+        (RatingContext::Renewable, search.min_renewable),
+        (RatingContext::Solidarity, search.min_solidarity),
+        (RatingContext::Transparency, search.min_transparency),
+    ]
+    .iter()
+    .filter_map(|(context, min_rating)| min_rating.map(|value| (*context, value.into())))
+    .collect();
+
+    // An explicitly requested time takes precedence. Otherwise
+    // opening hours are compared with the local time of the client.
+    let open_at = match (&search.open_at, search.open_now) {
+        (Some(open_at), _) => Some(
+            parse_open_at(open_at)
+                .map_err(Error::Parameter)
+                .map_err(AppError::Business)?,
+        ),
+        (None, Some(true)) => Some(
+            local_time_now(search.utc_offset)
+                .map_err(Error::Parameter)
+                .map_err(AppError::Business)?,
+        ),
+        (None, _) => None,
+    };
+
+    let fuzzy = search.fuzzy.unwrap_or(false);
+
+    let sort = parse_sort_order(
+        search.sort.as_ref().map(String::as_str),
+        center,
+        radius.is_some(),
+        fuzzy,
+    )
+    .map_err(Error::Parameter)
+    .map_err(AppError::Business)?;
+
+    Ok(usecases::SearchRequest {
         bbox,
+        radius,
+        area,
         ids,
         categories,
         hash_tags,
         text,
-    };
+        min_rating,
+        min_context_ratings,
+        open_at,
+        fuzzy,
+        sort,
+        highlight: search.highlight.unwrap_or(false),
+        facets: if search.facets.unwrap_or(false) {
+            Some(
+                search
+                    .facet_tags
+                    .unwrap_or(DEFAULT_FACET_TAGS)
+                    .min(MAX_FACET_TAGS),
+            )
+        } else {
+            None
+        },
+    })
+}
+
+#[get("/search?<search..>")]
+pub fn get_search(
+    search_engine: tantivy::SearchEngine,
+    search: Form<SearchQuery>,
+) -> Result<json::SearchResponse> {
+    let req = parse_search_request(&search)?;
 
     let search_limit = if let Some(limit) = search.limit {
         if limit > MAX_RESULTS {
@@ -93,11 +292,114 @@ pub fn get_search(
         MAX_RESULTS
     };
 
-    let (visible, invisible) = usecases::search(&search_engine, req, search_limit)?;
+    let offset = search.offset.unwrap_or(0);
+    match offset.checked_add(search_limit) {
+        Some(window) if window <= MAX_SEARCH_WINDOW => (),
+        _ => {
+            warn!("Invalid search offset: {}", offset);
+            return Err(AppError::Business(Error::Parameter(
+                ParameterError::InvalidOffset,
+            )));
+        }
+    }
+
+    let usecases::SearchResults {
+        visible,
+        invisible,
+        visible_count,
+        next_offset,
+        facets,
+    } = usecases::search(&search_engine, req, offset, search_limit)?;
 
     let visible: Vec<json::EntrySearchResult> = visible.into_iter().map(Into::into).collect();
 
     let invisible: Vec<json::EntrySearchResult> = invisible.into_iter().map(Into::into).collect();
 
-    Ok(Json(json::SearchResponse { visible, invisible }))
+    Ok(Json(json::SearchResponse {
+        visible,
+        invisible,
+        total: visible_count,
+        next: next_offset,
+        facets: facets.map(Into::into),
+    }))
+}
+
+#[get("/clusters?<zoom>&<search..>")]
+pub fn get_clusters(
+    search_engine: tantivy::SearchEngine,
+    zoom: u8,
+    search: Form<SearchQuery>,
+) -> Result<Vec<json::SearchCluster>> {
+    let req = parse_search_request(&search)?;
+    let clusters = usecases::search_clusters(&search_engine, req, zoom)?;
+    Ok(Json(clusters.into_iter().map(Into::into).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sort_order_parameter() {
+        let center = geo::MapPoint::from_lat_lng_deg(48.7, 9.1);
+        assert_eq!(
+            EntrySortOrder::Rating,
+            parse_sort_order(None, None, false, false).unwrap()
+        );
+        assert_eq!(
+            EntrySortOrder::Relevance,
+            parse_sort_order(Some("Relevance"), None, false, false).unwrap()
+        );
+        assert_eq!(
+            EntrySortOrder::Newest,
+            parse_sort_order(Some("newest"), Some(center), true, true).unwrap()
+        );
+        assert_eq!(
+            EntrySortOrder::Distance(center),
+            parse_sort_order(Some("distance"), Some(center), false, false).unwrap()
+        );
+        assert_eq!(
+            EntrySortOrder::Relevance,
+            parse_sort_order(None, None, false, true).unwrap()
+        );
+        assert_eq!(
+            EntrySortOrder::Rating,
+            parse_sort_order(Some("rating"), None, false, true).unwrap()
+        );
+        assert!(parse_sort_order(Some("distance"), None, false, false).is_err());
+        assert!(parse_sort_order(Some("foo"), None, false, false).is_err());
+        assert_eq!(
+            EntrySortOrder::Distance(center),
+            parse_sort_order(None, Some(center), true, true).unwrap()
+        );
+        assert_eq!(
+            EntrySortOrder::Rating,
+            parse_sort_order(Some("rating"), Some(center), true, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_open_at_parameter() {
+        use chrono::NaiveDate;
+        let expected = NaiveDate::from_ymd(2019, 6, 3).and_hms(10, 30, 0);
+        assert_eq!(expected, parse_open_at("2019-06-03T10:30").unwrap());
+        assert_eq!(expected, parse_open_at("2019-06-03T10:30:00").unwrap());
+        assert!(parse_open_at("2019-06-03").is_err());
+        assert!(parse_open_at("10:30").is_err());
+    }
+
+    #[test]
+    fn local_time_with_utc_offset() {
+        let utc = Utc::now().naive_utc();
+        let local = local_time_now(Some(120)).unwrap();
+        assert!(local - utc >= Duration::minutes(120));
+        assert!(local - utc < Duration::minutes(121));
+        assert!(local_time_now(Some(-12 * 60)).is_ok());
+        assert!(local_time_now(Some(15 * 60)).is_err());
+        // Defaults to UTC
+        let utc = Utc::now().naive_utc();
+        let local = local_time_now(None).unwrap();
+        assert!(local - utc >= Duration::zero());
+        assert!(local - utc < Duration::minutes(1));
+    }
 }
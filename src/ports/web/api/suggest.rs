@@ -0,0 +1,86 @@
+use crate::{
+    adapters::json,
+    core::{prelude::*, usecases, util::geo},
+    infrastructure::{db::tantivy, error::AppError},
+};
+
+use rocket::{self, request::Form};
+use rocket_contrib::json::Json;
+use std::result;
+
+#[derive(FromForm, Clone)]
+pub struct SuggestQuery {
+    q: Option<String>,
+    kind: Option<String>,
+    bbox: Option<String>,
+    limit: Option<usize>,
+}
+
+type Result<T> = result::Result<Json<T>, AppError>;
+
+const DEFAULT_SUGGESTIONS: usize = 10;
+
+const MAX_SUGGESTIONS: usize = 100;
+
+fn parse_suggestion_kind(kind: Option<&str>) -> result::Result<SuggestionKind, ParameterError> {
+    match kind.map(str::trim).map(str::to_lowercase).as_ref().map(String::as_str) {
+        None | Some("") | Some("tag") => Ok(SuggestionKind::Tag),
+        Some("title") => Ok(SuggestionKind::Title),
+        Some(kind) => {
+            warn!("Invalid suggestion kind: {}", kind);
+            Err(ParameterError::SuggestionKind)
+        }
+    }
+}
+
+#[get("/suggest?<suggest..>")]
+pub fn get_suggest(
+    search_engine: tantivy::SearchEngine,
+    suggest: Form<SuggestQuery>,
+) -> Result<Vec<json::FacetCount>> {
+    let kind = parse_suggestion_kind(suggest.kind.as_ref().map(String::as_str))
+        .map_err(Error::Parameter)
+        .map_err(AppError::Business)?;
+
+    let bbox = suggest
+        .bbox
+        .as_ref()
+        .map(|bbox| bbox.parse::<geo::MapBbox>())
+        .transpose()
+        .map_err(|_| ParameterError::Bbox)
+        .map_err(Error::Parameter)
+        .map_err(AppError::Business)?;
+
+    let limit = match suggest.limit {
+        Some(limit) if limit == 0 => {
+            warn!("Invalid suggestion limit: {}", limit);
+            return Err(AppError::Business(Error::Parameter(
+                ParameterError::InvalidLimit,
+            )));
+        }
+        Some(limit) => limit.min(MAX_SUGGESTIONS),
+        None => DEFAULT_SUGGESTIONS,
+    };
+
+    // Without a prefix the most frequent values are suggested
+    let prefix = suggest.q.as_ref().map(String::as_str).unwrap_or_default();
+
+    let suggestions = usecases::suggest(&search_engine, kind, prefix, bbox, limit)?;
+    Ok(Json(suggestions.into_iter().map(Into::into).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_suggestion_kind_parameter() {
+        assert_eq!(SuggestionKind::Tag, parse_suggestion_kind(None).unwrap());
+        assert_eq!(SuggestionKind::Tag, parse_suggestion_kind(Some("tag")).unwrap());
+        assert_eq!(
+            SuggestionKind::Title,
+            parse_suggestion_kind(Some(" Title ")).unwrap()
+        );
+        assert!(parse_suggestion_kind(Some("city")).is_err());
+    }
+}
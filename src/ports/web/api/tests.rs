@@ -5,6 +5,7 @@ use crate::{
     core::{usecases as usecase, util::sort::Rated},
     test::Bencher,
 };
+use rocket::http::{uri::Uri, Header};
 
 pub mod prelude {
     pub use crate::core::db::*;
@@ -213,6 +214,49 @@ fn get_one_entry() {
     );
 }
 
+#[test]
+fn get_entry_history_and_versions() {
+    let e = Entry::build()
+        .id("history_test")
+        .title("foo")
+        .description("desc")
+        .finish();
+    let (client, db) = setup();
+    db.exclusive().unwrap().create_entry(e.clone()).unwrap();
+    let mut e1 = e.clone();
+    e1.version = 1;
+    e1.title = "bar".into();
+    db.exclusive().unwrap().update_entry(&e1).unwrap();
+
+    let mut response = client.get("/entries/history_test/history").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    test_json(&response);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let history: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(
+        history,
+        serde_json::json!([
+            { "version": 0, "created": i64::from(e.created), "changes": [] },
+            {
+                "version": 1,
+                "created": i64::from(e1.created),
+                "changes": [{ "field": "title", "old": "foo", "new": "bar" }]
+            }
+        ])
+    );
+
+    let mut response = client.get("/entries/history_test/versions/0").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let entry: json::Entry = serde_json::from_str(&body_str).unwrap();
+    assert_eq!(entry, json::Entry::from_entry_with_ratings(e, vec![]));
+
+    let response = client.get("/entries/history_test/versions/2").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/entries/unknown/history").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
 #[test]
 fn get_multiple_entries() {
     let one = Entry::build()
@@ -262,6 +306,7 @@ fn default_new_entry() -> usecases::NewEntry {
         license: "CC0-1.0".into(),
         image_url: None,
         image_link_url: None,
+        opening_hours: None,
     }
 }
 
@@ -274,6 +319,55 @@ fn new_entry_with_category(category: &str, lat: f64, lng: f64) -> usecases::NewE
     }
 }
 
+#[test]
+fn search_with_categories_in_text() {
+    let entries = vec![
+        new_entry_with_category("foo", 1.0, 1.0),
+        usecases::NewEntry {
+            categories: vec!["foo".into(), "bar".into()],
+            ..new_entry_with_category("foo", 2.0, 2.0)
+        },
+        new_entry_with_category("bar", 3.0, 3.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    for id in &["foo", "bar"] {
+        connections
+            .exclusive()
+            .unwrap()
+            .create_category_if_it_does_not_exist(&Category {
+                id: (*id).into(),
+                created: 0,
+                version: 0,
+                name: (*id).into(),
+            })
+            .unwrap();
+    }
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    // Categories in the text narrow down the requested categories
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&categories=foo&text=category%3Abar")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[0])));
+    assert!(body_str.contains(&format!("\"{}\"", entry_ids[1])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[2])));
+
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&categories=foo&text=-category%3Abar")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(&format!("\"{}\"", entry_ids[0])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[1])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[2])));
+}
+
 #[test]
 fn search_with_categories_and_bbox() {
     let entries = vec![
@@ -304,7 +398,7 @@ fn search_with_categories_and_bbox() {
         .unwrap();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
     search_engine.flush().unwrap();
 
@@ -351,6 +445,73 @@ fn search_with_categories_and_bbox() {
     assert!(!body_str.contains(&format!("\"{}\"", entry_ids[2])));
 }
 
+#[test]
+fn search_open_at() {
+    let entries = vec![
+        usecases::NewEntry {
+            opening_hours: Some("Mo-Fr 08:00-18:00; Sa 10:00-22:00".into()),
+            ..default_new_entry()
+        },
+        usecases::NewEntry {
+            opening_hours: Some("Fr-Sa 20:00-02:00".into()),
+            ..default_new_entry()
+        },
+        default_new_entry(),
+        // Accepted, but never considered as open
+        usecases::NewEntry {
+            opening_hours: Some("Mo-Su sunrise-sunset".into()),
+            ..default_new_entry()
+        },
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    let mut response = client.get(format!("/entries/{}", entry_ids[0])).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(r#""opening_hours":"Mo-Fr 08:00-18:00; Sa 10:00-22:00""#));
+
+    // 2019-06-07 is a Friday
+    let search_open_at = |open_at: &str| {
+        let url = format!("/search?bbox=-10,-10,10,10&open_at={}", open_at);
+        let mut response = client.get(url).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        let res: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+        let mut ids: Vec<_> = res["visible"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["id"].as_str().unwrap().to_string())
+            .collect();
+        ids.sort_unstable();
+        ids
+    };
+    assert_eq!(vec![entry_ids[0].clone()], search_open_at("2019-06-07T09:00"));
+    assert_eq!(vec![entry_ids[1].clone()], search_open_at("2019-06-08T01:30"));
+    assert!(search_open_at("2019-06-09T12:00").is_empty());
+    let mut both = vec![entry_ids[0].clone(), entry_ids[1].clone()];
+    both.sort_unstable();
+    assert_eq!(both, search_open_at("2019-06-08T21:00"));
+
+    let response = client.get("/search?bbox=-10,-10,10,10&open_at=tomorrow").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client
+        .get("/search?bbox=-10,-10,10,10&open_now=true&utc_offset=120")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get("/search?bbox=-10,-10,10,10&open_now=true").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+        .get("/search?bbox=-10,-10,10,10&open_now=true&utc_offset=900")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
 fn new_entry_with_text(title: &str, description: &str, lat: f64, lng: f64) -> usecases::NewEntry {
     usecases::NewEntry {
         title: title.into(),
@@ -371,7 +532,7 @@ fn search_with_text() {
     let (client, connections, mut search_engine) = setup2();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
     search_engine.flush().unwrap();
 
@@ -433,6 +594,36 @@ fn search_with_text() {
     */
 }
 
+#[test]
+fn search_with_highlighted_snippets() {
+    let entries = vec![
+        new_entry_with_text("Foo & bar", "Some <foo> text", 1.0, 1.0),
+        new_entry_with_text("baz", "blub", 2.0, 2.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    for e in entries {
+        flows::create_entry(&connections, &mut search_engine, None, e).unwrap();
+    }
+
+    // Without highlighting
+    let mut response = client.get("/search?bbox=-10,-10,10,10&text=foo").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(!body_str.contains("title_snippet"));
+    assert!(!body_str.contains("description_snippet"));
+
+    // With highlighting: Matched terms are marked and
+    // all other characters are escaped
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&text=foo&highlight=true")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    test_json(&response);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(r#""title_snippet":"<b>Foo</b> &amp; bar""#));
+    assert!(body_str.contains(r#""description_snippet":"Some &lt;<b>foo</b>&gt; text""#));
+}
+
 fn new_entry_with_city(city: &str, latlng: f64) -> usecases::NewEntry {
     usecases::NewEntry {
         city: Some(city.into()),
@@ -452,7 +643,7 @@ fn search_with_city() {
     let (client, connections, mut search_engine) = setup2();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
     search_engine.flush().unwrap();
 
@@ -469,6 +660,32 @@ fn search_with_city() {
     assert!(body_str.contains(&format!("\"{}\"", entry_ids[2])));
 }
 
+#[test]
+fn search_with_city_field() {
+    let entries = vec![
+        new_entry_with_city("Stuttgart", 1.0),
+        usecases::NewEntry {
+            title: "Stuttgart".into(),
+            ..new_entry_with_city("Mannheim", 2.0)
+        },
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+
+    let req = client.get("/search?bbox=-10,-10,10,10&text=city:Stuttgart");
+    let mut response = req.dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    test_json(&response);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(&format!("\"{}\"", entry_ids[0])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[1])));
+    // The address is returned with the search results
+    assert!(body_str.contains(r#""city":"Stuttgart""#));
+}
+
 #[ignore]
 #[bench]
 fn bench_search_in_10_000_rated_entries(b: &mut Bencher) {
@@ -484,6 +701,26 @@ fn bench_search_in_10_000_rated_entries(b: &mut Bencher) {
     b.iter(|| client.get("/search?bbox=-10,-10,10,10").dispatch());
 }
 
+#[test]
+fn search_with_phrase_that_contains_stop_words() {
+    let entries = vec![
+        new_entry_with_text("Haus der Kulturen der Welt", "bla", 1.0, 1.0),
+        new_entry_with_text("Kulturen im Haus", "bla", 1.0, 1.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+
+    let req = client.get("/search?bbox=-10,-10,10,10&text=%22Haus%20der%20Kulturen%22");
+    let mut response = req.dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(&format!("\"{}\"", entry_ids[0])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[1])));
+}
+
 #[test]
 fn search_with_tags() {
     let entries = vec![
@@ -529,7 +766,7 @@ fn search_with_tags() {
         .unwrap();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
 
     let req = client.get("/search?bbox=-10,-10,10,10&tags=bla-blubb");
@@ -585,7 +822,7 @@ fn search_with_uppercase_tags() {
         .unwrap();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
 
     let req = client.get("/search?bbox=-10,-10,10,10&tags=Foo");
@@ -629,7 +866,7 @@ fn search_with_hashtag() {
         .unwrap();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
 
     let req = client.get("/search?bbox=-10,-10,10,10&text=%23foo-bar");
@@ -673,7 +910,7 @@ fn search_with_two_hashtags() {
         .unwrap();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
 
     let req = client.get("/search?bbox=-10,-10,10,10&text=%23bla-blubb%20%23foo-bar");
@@ -685,6 +922,247 @@ fn search_with_two_hashtags() {
     assert!(!body_str.contains(&format!("\"{}\"", entry_ids[2])));
 }
 
+#[test]
+fn search_with_query_operators() {
+    let entries = vec![
+        usecases::NewEntry {
+            tags: vec!["vegan".to_string(), "restaurant".to_string()],
+            ..default_new_entry()
+        },
+        usecases::NewEntry {
+            tags: vec!["vegan".to_string()],
+            ..default_new_entry()
+        },
+        usecases::NewEntry {
+            tags: vec!["cafe".to_string()],
+            ..default_new_entry()
+        },
+        usecases::NewEntry {
+            title: "Fresh organic food".to_string(),
+            tags: vec!["vegan".to_string()],
+            ..default_new_entry()
+        },
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+
+    let search = |text: &str| {
+        let mut response = client
+            .get(format!(
+                "/search?bbox=-10,-10,10,10&text={}",
+                Uri::percent_encode(text)
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        entry_ids
+            .iter()
+            .map(|id| body_str.contains(&format!("\"{}\"", id)))
+            .collect::<Vec<_>>()
+    };
+
+    // Excluded tag
+    assert_eq!(vec![false, true, false, true], search("#vegan -#restaurant"));
+    // Alternative tags
+    assert_eq!(vec![true, false, true, false], search("#restaurant|#cafe"));
+    // Phrases
+    assert_eq!(vec![false, false, false, true], search(r#""organic food""#));
+    assert_eq!(vec![false, false, false, false], search(r#""food organic""#));
+}
+
+#[test]
+fn search_with_min_ratings() {
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = (0..3)
+        .map(|_| {
+            flows::create_entry(&connections, &mut search_engine, None, default_new_entry())
+                .unwrap()
+        })
+        .collect();
+    let ratings = vec![
+        (&entry_ids[0], RatingContext::Renewable, 2),
+        (&entry_ids[1], RatingContext::Renewable, -1),
+        (&entry_ids[2], RatingContext::Humanity, 2),
+    ];
+    for (entry_id, context, value) in ratings {
+        flows::create_rating(
+            &connections,
+            &mut search_engine,
+            usecase::RateEntry {
+                context,
+                value: RatingValue::from(value),
+                user: None,
+                title: "title".into(),
+                entry: entry_id.clone(),
+                comment: "comment".into(),
+                source: Some("source".into()),
+            },
+        )
+        .unwrap();
+    }
+
+    let search = |params: &str| {
+        let mut response = client
+            .get(format!("/search?bbox=-10,-10,10,10&{}", params))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        entry_ids
+            .iter()
+            .map(|id| body_str.contains(&format!("\"{}\"", id)))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(vec![true, false, false], search("min_renewable=1"));
+    // Entries without ratings have an average rating of 0
+    assert_eq!(vec![true, false, true], search("min_renewable=0"));
+    assert_eq!(vec![true, true, true], search("min_renewable=-1"));
+    assert_eq!(vec![false, false, true], search("min_humanity=0.5"));
+    assert_eq!(vec![false, false, false], search("min_renewable=1&min_humanity=1"));
+
+    let response = client.get("/search?bbox=-10,-10,10,10&min_rating=3").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn search_clusters() {
+    let (client, connections, mut search_engine) = setup2();
+    let positions = vec![(1.0, 1.0), (1.5, 1.5), (2.0, 2.0), (-3.0, -3.0), (1.2, 1.2)];
+    let entry_ids: Vec<_> = positions
+        .into_iter()
+        .enumerate()
+        .map(|(i, (lat, lng))| {
+            let e = usecases::NewEntry {
+                lat,
+                lng,
+                tags: vec![if i < 4 { "foo" } else { "bar" }.to_string()],
+                ..default_new_entry()
+            };
+            flows::create_entry(&connections, &mut search_engine, None, e).unwrap()
+        })
+        .collect();
+    flows::create_rating(
+        &connections,
+        &mut search_engine,
+        usecase::RateEntry {
+            context: RatingContext::Fairness,
+            value: RatingValue::from(2),
+            user: None,
+            title: "title".into(),
+            entry: entry_ids[1].clone(),
+            comment: "comment".into(),
+            source: Some("source".into()),
+        },
+    )
+    .unwrap();
+
+    let mut response = client.get("/clusters?bbox=-10,-10,10,10&zoom=4&tags=foo").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    test_json(&response);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let clusters: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    let clusters = clusters.as_array().unwrap();
+    assert_eq!(2, clusters.len());
+    assert_eq!(3, clusters[0]["count"]);
+    assert_eq!(entry_ids[1], clusters[0]["top_entry"]["id"]);
+    assert!((clusters[0]["lat"].as_f64().unwrap() - 1.5).abs() < 0.01);
+    assert!((clusters[0]["lng"].as_f64().unwrap() - 1.5).abs() < 0.01);
+    assert_eq!(1, clusters[1]["count"]);
+    assert_eq!(entry_ids[3], clusters[1]["top_entry"]["id"]);
+
+    // The cells are aligned to the equator and the prime meridian
+    let mut response = client.get("/clusters?bbox=-10,-10,10,10&zoom=0").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let clusters: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    let clusters = clusters.as_array().unwrap();
+    assert_eq!(2, clusters.len());
+    assert_eq!(4, clusters[0]["count"]);
+    assert_eq!(1, clusters[1]["count"]);
+
+    let response = client.get("/clusters?bbox=-10,-10,10,10&zoom=23").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    // Too many cells for a large bounding box at a high zoom level
+    let response = client.get("/clusters?bbox=-10,-10,10,10&zoom=12").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client.get("/clusters?bbox=1,1,1.1,1.1&zoom=12").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+fn get_vector_tiles() {
+    let (client, connections, mut search_engine) = setup2();
+    let entries = vec![
+        usecases::NewEntry {
+            title: "foo".into(),
+            lat: 48.7,
+            lng: 9.1,
+            tags: vec!["foo".into()],
+            ..default_new_entry()
+        },
+        usecases::NewEntry {
+            title: "bar".into(),
+            lat: -33.9,
+            lng: 151.2,
+            tags: vec!["bar".into()],
+            ..default_new_entry()
+        },
+    ];
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+
+    let mut response = client.get("/tiles/1/1/0.mvt").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.headers().get_one("Content-Type"),
+        Some("application/vnd.mapbox-vector-tile")
+    );
+    let etag = response.headers().get_one("ETag").unwrap().to_string();
+    let data = response.body().and_then(|b| b.into_bytes()).unwrap();
+    let contains = |data: &[u8], id: &str| data.windows(id.len()).any(|w| w == id.as_bytes());
+    assert!(contains(&data, &entry_ids[0]));
+    assert!(!contains(&data, &entry_ids[1]));
+
+    // Unchanged tiles
+    let response = client
+        .get("/tiles/1/1/0.mvt")
+        .header(Header::new("If-None-Match", etag.clone()))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotModified);
+    assert_eq!(response.headers().get_one("ETag"), Some(etag.as_str()));
+
+    // Filtered tiles
+    let mut response = client.get("/tiles/0/0/0.mvt?tags=bar").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_ne!(response.headers().get_one("ETag"), Some(etag.as_str()));
+    let data = response.body().and_then(|b| b.into_bytes()).unwrap();
+    assert!(!contains(&data, &entry_ids[0]));
+    assert!(contains(&data, &entry_ids[1]));
+
+    let response = client.get("/tiles/1/2/0.mvt").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client.get("/tiles/1/1/0.png").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn reverse_geocoding() {
+    let (client, _) = setup();
+    // No geocoding providers are configured for testing
+    let response = client.get("/geocoding/reverse?lat=48.7&lng=9.1").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/geocoding/reverse?lat=91.0&lng=9.1").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client.get("/geocoding/reverse?lat=48.7").dispatch();
+    assert_eq!(response.status(), Status::NotFound);
+}
+
 #[test]
 fn search_with_commata() {
     let entries = vec![
@@ -723,7 +1201,7 @@ fn search_with_commata() {
         .unwrap();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
 
     // With hashtag symbol '#' -> all hashtags are mandatory
@@ -798,7 +1276,7 @@ fn search_without_specifying_hashtag_symbol() {
         .unwrap();
     let entry_ids: Vec<_> = entries
         .into_iter()
-        .map(|e| flows::create_entry(&connections, &mut search_engine, e).unwrap())
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
         .collect();
 
     let mut response = client.get("/search?bbox=-10,-10,10,10&text=foo").dispatch();
@@ -856,37 +1334,555 @@ fn search_without_specifying_hashtag_symbol() {
 }
 
 #[test]
-fn create_new_user() {
-    let (client, db) = setup();
-    let req = client
-        .post("/users")
-        .header(ContentType::JSON)
-        .body(r#"{"username":"foo","email":"foo@bar.com","password":"foo bar"}"#);
-    let response = req.dispatch();
-    assert_eq!(response.status(), Status::Ok);
-    let u = db.exclusive().unwrap().get_user("foo").unwrap();
-    assert_eq!(u.username, "foo");
-    assert!(u.password.verify("foo bar"));
-    test_json(&response);
+fn search_with_offset() {
+    let entries = vec![
+        new_entry_with_text("foo", "bla", 1.0, 1.0),
+        new_entry_with_text("foo", "bla", 2.0, 2.0),
+        new_entry_with_text("foo", "bla", 3.0, 3.0),
+        new_entry_with_text("foo", "bla", 4.0, 4.0),
+        new_entry_with_text("foo", "bla", 5.0, 5.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    let mut found_ids = vec![];
+    let mut offset = Some(0);
+    while let Some(next_offset) = offset {
+        let mut response = client
+            .get(format!(
+                "/search?bbox=-10,-10,10,10&limit=2&offset={}",
+                next_offset
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        let body: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+        assert_eq!(body["total"], 5);
+        let visible = body["visible"].as_array().unwrap();
+        assert!(visible.len() <= 2);
+        for e in visible {
+            found_ids.push(e["id"].as_str().unwrap().to_string());
+        }
+        offset = body["next"].as_u64().map(|next| next as usize);
+    }
+
+    // Every entry is found exactly once
+    assert_eq!(entry_ids.len(), found_ids.len());
+    for id in entry_ids {
+        assert_eq!(1, found_ids.iter().filter(|found| **found == id).count());
+    }
 }
 
 #[test]
-fn create_rating() {
-    let (client, connections, _) = setup2();
-    let entries = vec![Entry::build().id("foo").finish()];
-    for e in entries {
-        connections.exclusive().unwrap().create_entry(e).unwrap();
+fn search_with_too_large_offset() {
+    let (client, _, _) = setup2();
+    for offset in &[10_000, std::usize::MAX] {
+        let response = client
+            .get(format!(
+                "/search?bbox=-10,-10,10,10&limit=2&offset={}",
+                offset
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
     }
-    let req = client.post("/ratings")
-        .header(ContentType::JSON)
-        .body(r#"{"value": 1,"context":"fairness","entry":"foo","comment":"test", "title":"idontcare", "source":"source..."}"#);
-    let response = req.dispatch();
-    assert_eq!(response.status(), Status::Ok);
-    assert_eq!(
-        connections
-            .shared()
-            .unwrap()
-            .load_ratings_of_entry("foo")
+}
+
+#[test]
+fn search_sorted_by_distance() {
+    let entries = vec![
+        new_entry_with_text("foo", "bla", 1.0, 1.0),
+        new_entry_with_text("foo", "bla", 3.0, 3.0),
+        new_entry_with_text("foo", "bla", 2.0, 2.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&sort=distance&center=3.1,3.1")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    let visible_ids: Vec<_> = body["visible"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        vec![&*entry_ids[1], &*entry_ids[2], &*entry_ids[0]],
+        visible_ids
+    );
+
+    // Sorting by distance requires a center
+    let response = client
+        .get("/search?bbox=-10,-10,10,10&sort=distance")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = client
+        .get("/search?bbox=-10,-10,10,10&sort=relevance&text=foo")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+fn search_visible_ids(client: &Client, query: &str) -> Vec<String> {
+    let mut response = client.get(format!("/search?{}", query)).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    body["visible"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn search_sorted_by_newest() {
+    let (client, _, mut search_engine) = setup2();
+    for &(id, created) in &[("a", 100), ("b", 300), ("c", 200)] {
+        let mut e = Entry::build()
+            .id(id)
+            .title("foo")
+            .pos(MapPoint::from_lat_lng_deg(1.0, 1.0))
+            .finish();
+        e.created = created.into();
+        search_engine
+            .add_or_update_entry(&e, &AvgRatings::default())
+            .unwrap();
+    }
+    search_engine.flush().unwrap();
+
+    assert_eq!(
+        vec!["b", "c", "a"],
+        search_visible_ids(&client, "bbox=-10,-10,10,10&sort=newest")
+    );
+}
+
+#[test]
+fn search_sorted_by_relevance() {
+    let entries = vec![
+        new_entry_with_text("bar", "foo and some other words", 1.0, 1.0),
+        new_entry_with_text("baz", "nothing to see here", 2.0, 2.0),
+        new_entry_with_text("foo", "foo", 3.0, 3.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    assert_eq!(
+        vec![entry_ids[2].clone(), entry_ids[0].clone()],
+        search_visible_ids(&client, "bbox=-10,-10,10,10&sort=relevance&text=foo")
+    );
+}
+
+#[test]
+fn search_with_stable_order_across_pages() {
+    let (client, _, mut search_engine) = setup2();
+    // All entries have the same creation time
+    for i in 0..7 {
+        let e = Entry::build()
+            .id(&format!("entry{}", i))
+            .title("foo")
+            .pos(MapPoint::from_lat_lng_deg(1.0, 1.0))
+            .finish();
+        search_engine
+            .add_or_update_entry(&e, &AvgRatings::default())
+            .unwrap();
+    }
+    search_engine.flush().unwrap();
+
+    for sort in &["newest", "rating", "relevance"] {
+        let all_ids = search_visible_ids(
+            &client,
+            &format!("bbox=-10,-10,10,10&sort={}&text=foo&limit=7", sort),
+        );
+        assert_eq!(7, all_ids.len());
+        let mut paged_ids = vec![];
+        for offset in (0..7).step_by(3) {
+            paged_ids.extend(search_visible_ids(
+                &client,
+                &format!(
+                    "bbox=-10,-10,10,10&sort={}&text=foo&limit=3&offset={}",
+                    sort, offset
+                ),
+            ));
+        }
+        assert_eq!(all_ids, paged_ids);
+    }
+}
+
+#[test]
+fn search_within_radius() {
+    let entries = vec![
+        new_entry_with_text("foo", "bla", 48.79, 9.19),
+        new_entry_with_text("foo", "bla", 48.78, 9.18),
+        new_entry_with_text("foo", "bla", 48.80, 9.20),
+        new_entry_with_text("foo", "bla", 49.48, 8.46),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    // No bounding box required and sorted by distance
+    let mut response = client
+        .get("/search?center=48.7755,9.1827&radius=2000")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    let visible = body["visible"].as_array().unwrap();
+    let visible_ids: Vec<_> = visible.iter().map(|e| e["id"].as_str().unwrap()).collect();
+    assert_eq!(vec![&*entry_ids[1], &*entry_ids[0]], visible_ids);
+    let distances: Vec<_> = visible
+        .iter()
+        .map(|e| e["distance"].as_f64().unwrap())
+        .collect();
+    assert!(distances[0] < distances[1]);
+    assert!(distances[1] < 2000.0);
+    assert!(body["invisible"].as_array().unwrap().is_empty());
+
+    // Without a radius no distance is calculated
+    let mut response = client.get("/search?bbox=48,9,49,10").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(!body_str.contains("distance"));
+
+    // A radius requires a center
+    let response = client.get("/search?radius=2000").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = client
+        .get("/search?center=48.7755,9.1827&radius=-1")
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn search_within_area() {
+    let entries = vec![
+        new_entry_with_text("foo", "bla", 1.0, 1.0),
+        new_entry_with_text("foo", "bla", 1.5, 1.5),
+        // Inside of the bounding box, but outside of the polygon
+        new_entry_with_text("foo", "bla", 3.0, 3.0),
+        new_entry_with_text("foo", "bla", 5.0, 5.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    // No bounding box required
+    let area = r#"{"type":"Polygon","coordinates":[[[0,0],[4,0],[0,4],[0,0]]]}"#;
+    let mut response = client
+        .get(format!("/search?area={}", Uri::percent_encode(area)))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    let mut visible_ids: Vec<_> = body["visible"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["id"].as_str().unwrap())
+        .collect();
+    visible_ids.sort();
+    let mut expected_ids = vec![&*entry_ids[0], &*entry_ids[1]];
+    expected_ids.sort();
+    assert_eq!(expected_ids, visible_ids);
+
+    // Holes are excluded
+    let area = r#"{"type":"MultiPolygon","coordinates":[
+        [[[0,0],[4,0],[0,4],[0,0]],[[1.2,1.2],[1.8,1.2],[1.8,1.8],[1.2,1.8],[1.2,1.2]]],
+        [[[4.5,4.5],[5.5,4.5],[5.5,5.5],[4.5,5.5],[4.5,4.5]]]
+    ]}"#;
+    let mut response = client
+        .get(format!("/search?area={}", Uri::percent_encode(area)))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    let mut visible_ids: Vec<_> = body["visible"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["id"].as_str().unwrap())
+        .collect();
+    visible_ids.sort();
+    let mut expected_ids = vec![&*entry_ids[0], &*entry_ids[3]];
+    expected_ids.sort();
+    assert_eq!(expected_ids, visible_ids);
+
+    // Degenerated polygon
+    let area = r#"{"type":"Polygon","coordinates":[[[0,0],[4,0]]]}"#;
+    let response = client
+        .get(format!("/search?area={}", Uri::percent_encode(area)))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+
+    // Unsupported geometry
+    let area = r#"{"type":"Point","coordinates":[0,0]}"#;
+    let response = client
+        .get(format!("/search?area={}", Uri::percent_encode(area)))
+        .dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn search_with_facets() {
+    let entries = vec![
+        usecases::NewEntry {
+            tags: vec!["vegan".into()],
+            ..new_entry_with_category("foo", 1.0, 1.0)
+        },
+        usecases::NewEntry {
+            tags: vec!["vegan".into()],
+            ..new_entry_with_category("foo", 2.0, 2.0)
+        },
+        usecases::NewEntry {
+            tags: vec!["organic".into()],
+            ..new_entry_with_category("bar", 3.0, 3.0)
+        },
+        usecases::NewEntry {
+            tags: vec!["vegan".into()],
+            ..new_entry_with_category("bar", 20.0, 20.0)
+        },
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    for id in &["foo", "bar"] {
+        connections
+            .exclusive()
+            .unwrap()
+            .create_category_if_it_does_not_exist(&Category {
+                id: id.to_string(),
+                created: 0,
+                version: 0,
+                name: id.to_string(),
+            })
+            .unwrap();
+    }
+    for e in entries {
+        flows::create_entry(&connections, &mut search_engine, None, e).unwrap();
+    }
+    search_engine.flush().unwrap();
+
+    // Facets are not included by default
+    let mut response = client.get("/search?bbox=-10,-10,10,10").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(!body_str.contains("facets"));
+
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&facets=true&facet_tags=1")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    let categories = body["facets"]["categories"].as_array().unwrap();
+    assert_eq!(2, categories.len());
+    assert!(categories.contains(&serde_json::json!({"value": "foo", "count": 2})));
+    assert!(categories.contains(&serde_json::json!({"value": "bar", "count": 1})));
+    // Only the top tag within the visible bbox
+    assert_eq!(
+        &serde_json::json!([{"value": "vegan", "count": 2}]),
+        &body["facets"]["tags"]
+    );
+}
+
+#[test]
+fn suggest_tags_and_titles() {
+    let entries = vec![
+        usecases::NewEntry {
+            tags: vec!["vegan".into(), "vegetarian".into()],
+            ..new_entry_with_text("Weltladen", "bla", 1.0, 1.0)
+        },
+        usecases::NewEntry {
+            tags: vec!["vegan".into()],
+            ..new_entry_with_text("Weltladen", "bla", 2.0, 2.0)
+        },
+        usecases::NewEntry {
+            tags: vec!["organic".into()],
+            ..new_entry_with_text("Bioladen", "bla", 3.0, 3.0)
+        },
+        usecases::NewEntry {
+            tags: vec!["veggie-day".into()],
+            ..new_entry_with_text("Café Vegan", "bla", 20.0, 20.0)
+        },
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    for e in entries {
+        flows::create_entry(&connections, &mut search_engine, None, e).unwrap();
+    }
+    search_engine.flush().unwrap();
+
+    let suggest = |uri: &str| {
+        let mut response = client.get(uri).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        serde_json::from_str::<serde_json::Value>(&body_str).unwrap()
+    };
+
+    // Ordered by the number of entries
+    assert_eq!(
+        serde_json::json!([
+            {"value": "vegan", "count": 2},
+            {"value": "vegetarian", "count": 1},
+            {"value": "veggie-day", "count": 1},
+        ]),
+        suggest("/suggest?q=veg")
+    );
+    assert_eq!(
+        serde_json::json!([
+            {"value": "vegan", "count": 2},
+            {"value": "vegetarian", "count": 1},
+        ]),
+        suggest("/suggest?q=%23VEG&bbox=-10,-10,10,10")
+    );
+    assert_eq!(
+        serde_json::json!([{"value": "vegan", "count": 2}]),
+        suggest("/suggest?q=veg&limit=1")
+    );
+    assert_eq!(
+        serde_json::json!([{"value": "Weltladen", "count": 2}]),
+        suggest("/suggest?q=welt&kind=title")
+    );
+    assert_eq!(
+        serde_json::json!([{"value": "Café Vegan", "count": 1}]),
+        suggest("/suggest?q=cafe&kind=title")
+    );
+    assert_eq!(serde_json::json!([]), suggest("/suggest?q=foo"));
+
+    let response = client.get("/suggest?q=veg&kind=city").dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn search_with_fuzzy_text() {
+    let entries = vec![
+        new_entry_with_text("Bäckerei", "bla", 1.0, 1.0),
+        new_entry_with_text("organik", "bla", 2.0, 2.0),
+        new_entry_with_text("organic", "bla", 3.0, 3.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&text=B%C3%A4ckerai")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[0])));
+
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&text=B%C3%A4ckerai&fuzzy=true")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(&format!("\"{}\"", entry_ids[0])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[1])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[2])));
+
+    // The exact match precedes the fuzzy match
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&text=organic&fuzzy=true")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+    let visible_ids: Vec<_> = body["visible"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec![&*entry_ids[2], &*entry_ids[1]], visible_ids);
+}
+
+#[test]
+fn search_with_stemmed_and_folded_text() {
+    let entries = vec![
+        new_entry_with_text("Laden", "bla", 1.0, 1.0),
+        new_entry_with_text("Café", "bla", 2.0, 2.0),
+        new_entry_with_text("foo", "Die Läden am Markt", 3.0, 3.0),
+    ];
+    let (client, connections, mut search_engine) = setup2();
+    let entry_ids: Vec<_> = entries
+        .into_iter()
+        .map(|e| flows::create_entry(&connections, &mut search_engine, None, e).unwrap())
+        .collect();
+    search_engine.flush().unwrap();
+
+    let mut response = client
+        .get("/search?bbox=-10,-10,10,10&text=L%C3%A4den")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(&format!("\"{}\"", entry_ids[0])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[1])));
+    assert!(body_str.contains(&format!("\"{}\"", entry_ids[2])));
+
+    let mut response = client.get("/search?bbox=-10,-10,10,10&text=cafe").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[0])));
+    assert!(body_str.contains(&format!("\"{}\"", entry_ids[1])));
+    assert!(!body_str.contains(&format!("\"{}\"", entry_ids[2])));
+}
+
+#[test]
+fn create_new_user() {
+    let (client, db) = setup();
+    let req = client
+        .post("/users")
+        .header(ContentType::JSON)
+        .body(r#"{"username":"foo","email":"foo@bar.com","password":"foo bar"}"#);
+    let response = req.dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let u = db.exclusive().unwrap().get_user("foo").unwrap();
+    assert_eq!(u.username, "foo");
+    assert!(u.password.verify("foo bar"));
+    test_json(&response);
+}
+
+#[test]
+fn create_rating() {
+    let (client, connections, _) = setup2();
+    let entries = vec![Entry::build().id("foo").finish()];
+    for e in entries {
+        connections.exclusive().unwrap().create_entry(e).unwrap();
+    }
+    let req = client.post("/ratings")
+        .header(ContentType::JSON)
+        .body(r#"{"value": 1,"context":"fairness","entry":"foo","comment":"test", "title":"idontcare", "source":"source..."}"#);
+    let response = req.dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        connections
+            .shared()
+            .unwrap()
+            .load_ratings_of_entry("foo")
             .unwrap()[0]
             .value,
         RatingValue::from(1)
@@ -1249,6 +2245,86 @@ fn subscribe_to_bbox() {
     assert_eq!(response.status(), Status::Ok);
 }
 
+#[test]
+fn reindex_as_admin() {
+    let (client, connections, mut search_engine) = setup2();
+    let users = vec![
+        User {
+            id: "123".into(),
+            username: "guest".into(),
+            password: "secret1".parse::<Password>().unwrap(),
+            email: "guest@bar".into(),
+            email_confirmed: true,
+            role: Role::Guest,
+        },
+        User {
+            id: "456".into(),
+            username: "admin".into(),
+            password: "secret2".parse::<Password>().unwrap(),
+            email: "admin@bar".into(),
+            email_confirmed: true,
+            role: Role::Admin,
+        },
+    ];
+    for u in users {
+        connections.exclusive().unwrap().create_user(u).unwrap();
+    }
+    let entry_id = flows::create_entry(
+        &connections,
+        &mut search_engine,
+        None,
+        new_entry_with_text("Foo", "bla", 1.0, 1.0),
+    )
+    .unwrap();
+
+    let response = client.post("/server/reindex").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    let response = client.get("/server/reindex").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "guest", "password": "secret1"}"#)
+        .dispatch();
+    let cookie = user_id_cookie(&response).unwrap();
+    let response = client.post("/server/reindex").cookie(cookie).dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .post("/login")
+        .header(ContentType::JSON)
+        .body(r#"{"username": "admin", "password": "secret2"}"#)
+        .dispatch();
+    let cookie = user_id_cookie(&response).unwrap();
+    let rebuild_guard = search_engine.try_lock_rebuild().unwrap();
+    let response = client.post("/server/reindex").cookie(cookie.clone()).dispatch();
+    assert_eq!(response.status(), Status::Conflict);
+    drop(rebuild_guard);
+
+    let response = client.post("/server/reindex").cookie(cookie.clone()).dispatch();
+    assert_eq!(response.status(), Status::Accepted);
+    // Wait until the rebuild in the background has finished
+    let status = loop {
+        let mut response = client.get("/server/reindex").cookie(cookie.clone()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        let status: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+        if status["in_progress"] == false {
+            break status;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    assert_eq!(status["indexed_count"], 1);
+    assert!(status["finished"].is_i64());
+    assert!(status["error"].is_null());
+
+    let mut response = client.get("/search?bbox=-10,-10,10,10&text=foo").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains(&format!("\"{}\"", entry_id)));
+}
+
 #[test]
 fn openapi() {
     let (client, _) = setup();
@@ -1301,6 +2377,7 @@ fn export_csv() {
             .finish(),
     );
     entries[0].homepage = Some("homepage1".to_string());
+    entries[0].opening_hours = Some("Mo-Fr 08:00-18:00".parse().unwrap());
 
     let (client, db, mut search_engine) = setup2();
 
@@ -1380,7 +2457,147 @@ fn export_csv() {
         }
     }
     let body_str = response.body().and_then(|b| b.into_string()).unwrap();
-    assert_eq!(body_str, format!("id,osm_node,created,version,title,description,lat,lng,street,zip,city,country,homepage,categories,tags,license,avg_rating\n\
-        entry1,1,2,3,title1,desc1,{lat1},{lng1},street1,zip1,city1,country1,homepage1,\"cat1,cat2\",\"bla,bli\",license1,0.25\n\
-        entry2,,0,0,,,0.0,0.0,,,,,,cat1,,,0.0\n", lat1 = LatCoord::from_deg(0.1).to_deg(), lng1 = LngCoord::from_deg(0.2).to_deg()));
+    assert_eq!(body_str, format!("id,osm_node,created,version,title,description,lat,lng,street,zip,city,country,homepage,categories,tags,license,opening_hours,avg_rating\n\
+        entry1,1,2,3,title1,desc1,{lat1},{lng1},street1,zip1,city1,country1,homepage1,\"cat1,cat2\",\"bla,bli\",license1,Mo-Fr 08:00-18:00,0.25\n\
+        entry2,,0,0,,,0.0,0.0,,,,,,cat1,,,,0.0\n", lat1 = LatCoord::from_deg(0.1).to_deg(), lng1 = LngCoord::from_deg(0.2).to_deg()));
+
+    // Only entries within the area are exported
+    let area = r#"{"type":"Polygon","coordinates":[[[0.1,0.05],[0.3,0.05],[0.3,0.15],[0.1,0.15],[0.1,0.05]]]}"#;
+    let req = client.get(format!("/export/entries.csv?area={}", Uri::percent_encode(area)));
+    let mut response = req.dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    assert!(body_str.contains("\nentry1,"));
+    assert!(!body_str.contains("\nentry2,"));
+    assert!(!body_str.contains("\nentry3,"));
+
+    let req = client.get("/export/entries.csv");
+    let response = req.dispatch();
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn export_csv_with_more_entries_than_search_window() {
+    let (client, db, mut search_engine) = setup2();
+    let entry_count = search::MAX_SEARCH_WINDOW + 1;
+    {
+        let conn = db.exclusive().unwrap();
+        for i in 0..entry_count {
+            let entry = Entry::build().id(&format!("entry{}", i)).finish();
+            search_engine
+                .add_or_update_entry(&entry, &Default::default())
+                .unwrap();
+            conn.create_entry(entry).unwrap();
+        }
+    }
+    search_engine.flush().unwrap();
+
+    let req = client.get("/export/entries.csv?bbox=-1,-1,1,1");
+    let mut response = req.dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+    // Header and all entries
+    assert_eq!(entry_count + 1, body_str.lines().count());
+}
+
+#[test]
+fn export_csv_as_of() {
+    let (client, db) = setup();
+    let mut entries = vec![
+        Entry::build().id("entry1").title("old").finish(),
+        Entry::build().id("entry2").title("later").finish(),
+        Entry::build().id("entry3").title("archived").finish(),
+        Entry::build().id("entry4").title("archived later").finish(),
+    ];
+    entries[0].created = 100.into();
+    entries[1].created = 300.into();
+    entries[2].created = 50.into();
+    entries[3].created = 50.into();
+    let mut updated = entries[0].clone();
+    updated.version = 1;
+    updated.created = 200.into();
+    updated.title = "new".into();
+    for e in entries {
+        db.exclusive().unwrap().create_entry(e).unwrap();
+    }
+    db.exclusive().unwrap().update_entry(&updated).unwrap();
+    db.exclusive()
+        .unwrap()
+        .archive_entries(&["entry3"], 150.into())
+        .unwrap();
+    db.exclusive()
+        .unwrap()
+        .archive_entries(&["entry4"], 400.into())
+        .unwrap();
+    let ratings: Vec<(&str, i64, i8, Option<i64>)> = vec![
+        ("rating1", 120, 3, Some(180)),
+        ("rating2", 220, 3, None),
+        ("rating3", 240, 0, Some(500)),
+    ];
+    for (id, created, value, _) in &ratings {
+        db.exclusive()
+            .unwrap()
+            .create_rating(Rating {
+                id: (*id).into(),
+                entry_id: "entry1".into(),
+                created: (*created).into(),
+                archived: None,
+                title: (*id).into(),
+                value: RatingValue::from(*value),
+                context: RatingContext::Diversity,
+                source: None,
+            })
+            .unwrap();
+    }
+    for (id, _, _, archived) in &ratings {
+        if let Some(archived) = archived {
+            db.exclusive()
+                .unwrap()
+                .archive_ratings(&[*id], (*archived).into())
+                .unwrap();
+        }
+    }
+
+    let export_as_of = |as_of: i64| {
+        let url = format!("/export/entries.csv?bbox=-1,-1,1,1&as_of={}", as_of);
+        let mut response = client.get(url).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        body_str
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').take(5).collect::<Vec<_>>().join(","))
+            .collect::<Vec<_>>()
+    };
+    assert!(export_as_of(10).is_empty());
+    assert_eq!(
+        vec!["entry1,,100,0,old", "entry4,,50,0,archived later"],
+        export_as_of(150)
+    );
+    assert_eq!(
+        vec!["entry1,,200,1,new", "entry4,,50,0,archived later"],
+        export_as_of(250)
+    );
+    assert_eq!(
+        vec!["entry1,,200,1,new", "entry2,,300,0,later"],
+        export_as_of(400)
+    );
+
+    // Only ratings that existed at the given time are included
+    let avg_rating_as_of = |as_of: i64| {
+        let url = format!("/export/entries.csv?bbox=-1,-1,1,1&as_of={}", as_of);
+        let mut response = client.get(url).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body_str = response.body().and_then(|b| b.into_string()).unwrap();
+        body_str
+            .lines()
+            .find(|line| line.starts_with("entry1,"))
+            .and_then(|line| line.rsplit(',').next())
+            .map(ToString::to_string)
+            .unwrap()
+    };
+    assert_eq!("0.0", avg_rating_as_of(110));
+    assert_eq!("0.5", avg_rating_as_of(150));
+    assert_eq!("0.25", avg_rating_as_of(250));
+    assert_eq!("0.5", avg_rating_as_of(600));
 }
@@ -0,0 +1,132 @@
+use crate::{
+    adapters::mvt,
+    core::{prelude::*, usecases, util},
+    infrastructure::{db::tantivy, error::AppError},
+    ports::web::guards::IfNoneMatch,
+};
+
+use rocket::{
+    self,
+    http::{ContentType, Status},
+    request::{Form, Request},
+    response::{self, Responder, Response},
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    io::Cursor,
+    result,
+};
+
+#[derive(FromForm, Clone)]
+pub struct TileQuery {
+    categories: Option<String>,
+    tags: Option<String>,
+}
+
+/// The top rated entries are preferred if a tile
+/// contains more entries.
+const MAX_TILE_ENTRIES: usize = 10_000;
+
+const MVT_FILE_EXTENSION: &str = ".mvt";
+
+pub enum TileResponse {
+    Modified { etag: String, data: Vec<u8> },
+    NotModified { etag: String },
+}
+
+impl<'r> Responder<'r> for TileResponse {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        let mut response = Response::build();
+        // Clients should always revalidate their cached tiles
+        response.raw_header("Cache-Control", "no-cache");
+        match self {
+            TileResponse::Modified { etag, data } => {
+                response
+                    .header(ContentType::new("application", "vnd.mapbox-vector-tile"))
+                    .raw_header("ETag", etag)
+                    .sized_body(Cursor::new(data));
+            }
+            TileResponse::NotModified { etag } => {
+                response.status(Status::NotModified).raw_header("ETag", etag);
+            }
+        }
+        response.ok()
+    }
+}
+
+fn parse_tile_coord(z: u8, x: u32, y: &str) -> result::Result<mvt::TileCoord, ParameterError> {
+    if !y.ends_with(MVT_FILE_EXTENSION) {
+        warn!("Unsupported tile format: {}", y);
+        return Err(ParameterError::Tile);
+    }
+    let y = y[..y.len() - MVT_FILE_EXTENSION.len()]
+        .parse()
+        .map_err(|_| ParameterError::Tile)?;
+    let tile = mvt::TileCoord { z, x, y };
+    if !tile.is_valid() {
+        warn!("Invalid tile coordinates: {:?}", tile);
+        return Err(ParameterError::Tile);
+    }
+    Ok(tile)
+}
+
+/// The entity tag only depends on the contents of the tile.
+fn etag_of(data: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+#[get("/tiles/<z>/<x>/<y>?<filter..>")]
+pub fn get_tile(
+    search_engine: tantivy::SearchEngine,
+    if_none_match: IfNoneMatch,
+    z: u8,
+    x: u32,
+    y: String,
+    filter: Form<TileQuery>,
+) -> result::Result<TileResponse, AppError> {
+    let tile = parse_tile_coord(z, x, &y)
+        .map_err(Error::Parameter)
+        .map_err(AppError::Business)?;
+
+    let categories = filter
+        .categories
+        .as_ref()
+        .map(String::as_str)
+        .map(util::split_ids)
+        .unwrap_or_default();
+
+    let hash_tags = filter
+        .tags
+        .as_ref()
+        .map(String::as_str)
+        .map(util::split_ids)
+        .unwrap_or_default();
+
+    let req = usecases::SearchRequest {
+        bbox: tile.bbox(),
+        radius: None,
+        area: None,
+        ids: vec![],
+        categories,
+        hash_tags,
+        text: None,
+        min_rating: None,
+        min_context_ratings: vec![],
+        open_at: None,
+        fuzzy: false,
+        sort: EntrySortOrder::Rating,
+        highlight: false,
+        facets: None,
+    };
+    let entries = usecases::search_all(&search_engine, req, MAX_TILE_ENTRIES)?;
+
+    let data = mvt::encode_entries(tile, &entries);
+    let etag = etag_of(&data);
+    if if_none_match.matches(&etag) {
+        return Ok(TileResponse::NotModified { etag });
+    }
+    Ok(TileResponse::Modified { etag, data })
+}
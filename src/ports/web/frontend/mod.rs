@@ -3,7 +3,6 @@ use crate::{
     infrastructure::{db::sqlite, error::*, flows::prelude::*},
     ports::web::{api::events::EventQuery, guards::*, tantivy::SearchEngine},
 };
-use chrono::NaiveDateTime;
 use maud::Markup;
 use rocket::{
     self,
@@ -40,10 +39,20 @@ pub fn get_index_html() -> Markup {
     view::index(None)
 }
 
-#[get("/search?<q>&<limit>")]
-pub fn get_search(search_engine: SearchEngine, q: &RawStr, limit: Option<usize>) -> Result<Markup> {
+#[get("/search?<q>&<limit>&<highlight>")]
+pub fn get_search(
+    search_engine: SearchEngine,
+    q: &RawStr,
+    limit: Option<usize>,
+    highlight: Option<bool>,
+) -> Result<Markup> {
     let q = q.url_decode()?;
-    let entries = usecases::global_search(&search_engine, &q, limit.unwrap_or(10))?;
+    let entries = usecases::global_search(
+        &search_engine,
+        &q,
+        limit.unwrap_or(10),
+        highlight.unwrap_or(false),
+    )?;
     Ok(view::search_results(None, &q, &entries))
 }
 
@@ -95,28 +104,32 @@ pub fn get_event(db: sqlite::Connections, id: &RawStr) -> Result<Markup> {
 }
 
 #[get("/events?<query..>")]
-pub fn get_events(db: sqlite::Connections, query: EventQuery) -> Result<Markup> {
+pub fn get_events(
+    db: sqlite::Connections,
+    search_engine: SearchEngine,
+    query: EventQuery,
+) -> Result<Markup> {
     if query.created_by.is_some() {
         return Err(Error::Parameter(ParameterError::Unauthorized).into());
     }
 
-    let start_min = query
-        .start_min
-        .map(|x| NaiveDateTime::from_timestamp(x, 0))
-        .unwrap_or_else(|| {
+    let (mut index_query, created_by) = query.into_index_query();
+    if index_query.start_min.is_none() {
+        index_query.start_min = Some(
             chrono::Utc::now()
                 .checked_sub_signed(chrono::Duration::days(1))
                 .unwrap()
-                .naive_utc()
-        });
+                .naive_utc(),
+        );
+    }
 
     let events = usecases::query_events(
         &*db.shared()?,
-        query.tags,
-        query.bbox,
-        Some(start_min),
-        query.start_max.map(|x| NaiveDateTime::from_timestamp(x, 0)),
-        query.created_by,
+        &search_engine,
+        index_query,
+        0,
+        None,
+        created_by,
         None,
     )?;
 
@@ -214,6 +227,7 @@ mod tests {
     use super::*;
     use crate::infrastructure::db::tantivy;
     use crate::ports::web::tests::prelude::*;
+    use chrono::NaiveDateTime;
 
     fn setup() -> (
         rocket::local::Client,
@@ -247,6 +261,8 @@ mod tests {
                     registration: Some(RegistrationType::Email),
                     organizer: None,
                     archived: None,
+                    rrule: None,
+                    exdates: vec![],
                 },
                 Event {
                     id: "5678".into(),
@@ -265,6 +281,8 @@ mod tests {
                     registration: Some(RegistrationType::Email),
                     organizer: None,
                     archived: None,
+                    rrule: None,
+                    exdates: vec![],
                 },
                 Event {
                     id: "0000".into(),
@@ -283,6 +301,8 @@ mod tests {
                     registration: Some(RegistrationType::Email),
                     organizer: None,
                     archived: None,
+                    rrule: None,
+                    exdates: vec![],
                 },
             ];
 
@@ -322,6 +342,8 @@ mod tests {
                     registration: Some(RegistrationType::Email),
                     organizer: None,
                     archived: None,
+                    rrule: None,
+                    exdates: vec![],
                 },
                 Event {
                     id: "5678".into(),
@@ -340,6 +362,8 @@ mod tests {
                     registration: Some(RegistrationType::Email),
                     organizer: None,
                     archived: None,
+                    rrule: None,
+                    exdates: vec![],
                 },
                 Event {
                     id: "0000".into(),
@@ -358,6 +382,8 @@ mod tests {
                     registration: Some(RegistrationType::Email),
                     organizer: None,
                     archived: None,
+                    rrule: None,
+                    exdates: vec![],
                 },
             ];
 
@@ -394,6 +420,8 @@ mod tests {
                 registration: Some(RegistrationType::Email),
                 organizer: None,
                 archived: None,
+                rrule: None,
+                exdates: vec![],
             }];
 
             {
@@ -458,8 +486,9 @@ mod tests {
                 license: "CC0-1.0".into(),
                 image_url: None,
                 image_link_url: None,
+                opening_hours: None,
             };
-            let e_id = flows::prelude::create_entry(db, search, e).unwrap();
+            let e_id = flows::prelude::create_entry(db, search, None, e).unwrap();
             let r = usecases::RateEntry {
                 title: "A rating".into(),
                 comment: "Foo".into(),
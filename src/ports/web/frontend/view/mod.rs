@@ -1,5 +1,5 @@
 use crate::core::prelude::*;
-use maud::{html, Markup};
+use maud::{html, Markup, PreEscaped};
 use rocket::request::FlashMessage;
 
 const LEAFLET_CSS_URL: &str = "https://cdnjs.cloudflare.com/ajax/libs/leaflet/1.4.0/leaflet.css";
@@ -95,11 +95,26 @@ pub fn search_results(email: Option<&str>, search_term: &str, entries: &[Indexed
 }
 
 fn entry_result(e: &IndexedEntry) -> Markup {
+    // Snippets are already HTML-escaped
+    let title_snippet = e.snippets.as_ref().and_then(|s| s.title.as_ref());
+    let description_snippet = e.snippets.as_ref().and_then(|s| s.description.as_ref());
     html! {
         h3 {
-            a href=(format!("entries/{}",e.id)) {(e.title)}
+            a href=(format!("entries/{}",e.id)) {
+                @if let Some(snippet) = title_snippet {
+                    (PreEscaped(snippet))
+                } @else {
+                    (e.title)
+                }
+            }
+        }
+        p {
+            @if let Some(snippet) = description_snippet {
+                (PreEscaped(snippet))
+            } @else {
+                (e.description)
+            }
         }
-        p {(e.description)}
     }
 }
 
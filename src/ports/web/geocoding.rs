@@ -0,0 +1,15 @@
+pub use crate::infrastructure::geocoding::*;
+
+use rocket::{
+    request::{self, FromRequest},
+    Outcome, Request, State,
+};
+
+impl<'a, 'r> FromRequest<'a, 'r> for GeocodingService {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<GeocodingService, ()> {
+        let geocoding = request.guard::<State<GeocodingService>>()?;
+        Outcome::Success(geocoding.clone())
+    }
+}
@@ -107,3 +107,33 @@ impl<'a, 'r> FromRequest<'a, 'r> for Admin {
         .or_forward(())
     }
 }
+
+/// The entity tags of the `If-None-Match` header (if any).
+#[derive(Debug)]
+pub struct IfNoneMatch(pub Option<String>);
+
+impl IfNoneMatch {
+    /// Checks if the client already has the current
+    /// representation of the requested resource.
+    pub fn matches(&self, etag: &str) -> bool {
+        self.0
+            .as_ref()
+            .map(|header| {
+                header.split(',').map(str::trim).any(|tag| {
+                    // Weak comparison
+                    tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/")
+                })
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for IfNoneMatch {
+    type Error = !;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<IfNoneMatch, !> {
+        Outcome::Success(IfNoneMatch(
+            request.headers().get_one("If-None-Match").map(Into::into),
+        ))
+    }
+}
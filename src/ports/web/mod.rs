@@ -1,5 +1,9 @@
 use crate::{
-    core::{db::EntryIndexer, prelude::*, util::sort::Rated},
+    core::{
+        db::{EntryIndexer, EventIndexer},
+        prelude::*,
+        usecases,
+    },
     infrastructure::error::AppError,
 };
 use rocket::{config::Config, Rocket, Route};
@@ -10,10 +14,12 @@ pub mod api;
 #[cfg(feature = "frontend")]
 mod frontend;
 mod guards;
+mod geocoding;
 #[cfg(test)]
 mod mockdb;
 mod sqlite;
 mod tantivy;
+mod worker;
 
 type Result<T> = result::Result<Json<T>, AppError>;
 
@@ -21,38 +27,106 @@ fn index_all_entries<D: EntryGateway + RatingRepository>(
     db: &D,
     entry_indexer: &mut dyn EntryIndexer,
 ) -> Result<()> {
-    // TODO: Split into chunks with fixed size instead of
-    // loading all entries at once!
-    let entries = db.all_entries()?;
-    for entry in entries {
-        let ratings = db.load_ratings_of_entry(&entry.id)?;
-        if let Err(err) =
-            entry_indexer.add_or_update_entry(&entry, &entry.avg_ratings(&ratings[..]))
-        {
-            error!("Failed to index entry {:?}: {}", entry, err);
+    let started = Timestamp::now();
+    let mut indexed_count = 0;
+    let mut last_id = None;
+    loop {
+        let ids = usecases::index_entries_chunk(
+            db,
+            entry_indexer,
+            last_id.as_ref().map(String::as_str),
+        )?;
+        if ids.is_empty() {
+            break;
         }
+        indexed_count += ids.len();
+        debug!("Indexed {} entries", indexed_count);
+        last_id = ids.into_iter().last();
     }
-    if let Err(err) = entry_indexer.flush() {
+    info!("Indexed {} entries", indexed_count);
+    if let Err(err) = entry_indexer.flush_with_high_water_mark(started) {
         error!("Failed to build entry index: {}", err);
     }
     Ok(Json(()))
 }
 
+fn index_changed_entries<D: EntryGateway + RatingRepository>(
+    db: &D,
+    entry_indexer: &mut dyn EntryIndexer,
+    since: Timestamp,
+) -> Result<()> {
+    let started = Timestamp::now();
+    usecases::index_changed_entries(db, entry_indexer, since)?;
+    if let Err(err) = entry_indexer.flush_with_high_water_mark(started) {
+        error!("Failed to update entry index: {}", err);
+    }
+    Ok(Json(()))
+}
+
+fn index_entries<D: EntryGateway + RatingRepository>(
+    db: &D,
+    entry_indexer: &mut dyn EntryIndexer,
+) -> Result<()> {
+    match entry_indexer.high_water_mark() {
+        Ok(Some(high_water_mark)) => {
+            let since = Timestamp::from(
+                i64::from(high_water_mark) - usecases::INDEX_CATCH_UP_OVERLAP_IN_SECONDS,
+            );
+            info!("Indexing entries changed since {}...", since);
+            index_changed_entries(db, entry_indexer, since)
+        }
+        Ok(None) => {
+            info!("Indexing all entries...");
+            index_all_entries(db, entry_indexer)
+        }
+        Err(err) => {
+            warn!("Failed to read the state of the entry index: {}", err);
+            info!("Indexing all entries...");
+            index_all_entries(db, entry_indexer)
+        }
+    }
+}
+
+fn index_all_events<D: EventGateway>(db: &D, event_indexer: &mut dyn EventIndexer) -> Result<()> {
+    let events = db.all_events()?;
+    // Deleted and archived events must not be found anymore
+    if let Err(err) = event_indexer.remove_all_events() {
+        error!("Failed to remove outdated events from the index: {}", err);
+    }
+    for event in events {
+        if let Err(err) = event_indexer.add_or_update_event(&event) {
+            error!("Failed to index event {:?}: {}", event, err);
+        }
+    }
+    if let Err(err) = event_indexer.flush_events() {
+        error!("Failed to build event index: {}", err);
+    }
+    Ok(Json(()))
+}
+
 pub(crate) fn rocket_instance(
     connections: sqlite::Connections,
     mut search_engine: tantivy::SearchEngine,
+    worker: worker::Worker,
+    geocoding: geocoding::GeocodingService,
     mounts: Vec<(&str, Vec<Route>)>,
     cfg: Option<Config>,
 ) -> Rocket {
-    info!("Indexing all entries...");
-    index_all_entries(&*connections.exclusive().unwrap(), &mut search_engine).unwrap();
+    index_entries(&*connections.exclusive().unwrap(), &mut search_engine).unwrap();
+    // Events are not versioned and always need to be reindexed
+    info!("Indexing all events...");
+    index_all_events(&*connections.exclusive().unwrap(), &mut search_engine).unwrap();
 
     info!("Initialization finished");
     let r = match cfg {
         Some(cfg) => rocket::custom(cfg),
         None => rocket::ignite(),
     };
-    let mut instance = r.manage(connections).manage(search_engine);
+    let mut instance = r
+        .manage(connections)
+        .manage(search_engine)
+        .manage(worker)
+        .manage(geocoding);
 
     for (m, r) in mounts {
         instance = instance.mount(m, r);
@@ -73,6 +147,7 @@ fn mounts() -> Vec<(&'static str, Vec<Route>)> {
 pub fn run(
     connections: sqlite::Connections,
     search_engine: tantivy::SearchEngine,
+    geocoding: geocoding::GeocodingService,
     enable_cors: bool,
 ) {
     if enable_cors {
@@ -81,7 +156,8 @@ pub fn run(
              \nhttps://github.com/SergioBenitez/Rocket/pull/141\nis merged :("
         );
     }
-    rocket_instance(connections, search_engine, mounts(), None).launch();
+    let worker = worker::Worker::spawn(connections.clone(), search_engine.clone());
+    rocket_instance(connections, search_engine, worker, geocoding, mounts(), None).launch();
 }
 
 #[cfg(test)]
@@ -122,6 +198,8 @@ mod tests {
         let rocket = super::rocket_instance(
             connections.clone(),
             search_engine.clone(),
+            super::worker::Worker::immediate(search_engine.clone()),
+            super::geocoding::GeocodingService::new(vec![]),
             mounts,
             Some(cfg),
         );
@@ -0,0 +1,15 @@
+pub use crate::infrastructure::worker::*;
+
+use rocket::{
+    request::{self, FromRequest},
+    Outcome, Request, State,
+};
+
+impl<'a, 'r> FromRequest<'a, 'r> for Worker {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Worker, ()> {
+        let worker = request.guard::<State<Worker>>()?;
+        Outcome::Success(worker.inner().clone())
+    }
+}